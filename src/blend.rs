@@ -0,0 +1,173 @@
+///
+/// A weight applied to the source or destination color before the two are combined.
+/// The source is the color returned by the shader, and the destination is the color
+/// already in the frame buffer.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+}
+
+impl BlendFactor {
+    /// Returns the weight of `channel` of a color, where channel 3 is alpha.
+    #[inline]
+    fn weight(&self, src: &[f32; 4], dst: &[f32; 4], channel: usize) -> f32 {
+        match *self {
+            BlendFactor::Zero             => 0.0,
+            BlendFactor::One              => 1.0,
+            BlendFactor::SrcColor         => src[channel],
+            BlendFactor::OneMinusSrcColor => 1.0 - src[channel],
+            BlendFactor::DstColor         => dst[channel],
+            BlendFactor::OneMinusDstColor => 1.0 - dst[channel],
+            BlendFactor::SrcAlpha         => src[3],
+            BlendFactor::OneMinusSrcAlpha => 1.0 - src[3],
+            BlendFactor::DstAlpha         => dst[3],
+            BlendFactor::OneMinusDstAlpha => 1.0 - dst[3],
+        }
+    }
+}
+
+///
+/// How the weighted source and destination colors are combined. `Min` and `Max`
+/// ignore the blend factors.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum BlendEquation {
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+impl BlendEquation {
+    #[inline]
+    fn apply(&self, src: f32, src_weight: f32, dst: f32, dst_weight: f32) -> f32 {
+        match *self {
+            BlendEquation::Add             => src * src_weight + dst * dst_weight,
+            BlendEquation::Subtract        => src * src_weight - dst * dst_weight,
+            BlendEquation::ReverseSubtract => dst * dst_weight - src * src_weight,
+            BlendEquation::Min             => f32::min(src, dst),
+            BlendEquation::Max             => f32::max(src, dst),
+        }
+    }
+}
+
+///
+/// The blend factors and equations used to combine a shaded fragment with the
+/// frame buffer. The color channels and alpha are blended separately. Colors are
+/// normalized RGBA, and pixel formats without alpha read back as opaque.
+///
+/// Transparent surfaces usually should not hide what is drawn after them, so they
+/// are drawn with depth writes turned off.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct BlendState {
+    pub color_src: BlendFactor,
+    pub color_dst: BlendFactor,
+    pub color_equation: BlendEquation,
+    pub alpha_src: BlendFactor,
+    pub alpha_dst: BlendFactor,
+    pub alpha_equation: BlendEquation,
+}
+
+impl BlendState {
+    /// Blend with the same factors and equation for color and alpha.
+    pub fn new(src: BlendFactor, dst: BlendFactor, equation: BlendEquation) -> BlendState {
+        BlendState {
+            color_src: src,
+            color_dst: dst,
+            color_equation: equation,
+            alpha_src: src,
+            alpha_dst: dst,
+            alpha_equation: equation,
+        }
+    }
+
+    /// Draw a color with straight alpha over the frame buffer.
+    pub fn over() -> BlendState {
+        BlendState {
+            alpha_src: BlendFactor::One,
+            ..BlendState::new(BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha, BlendEquation::Add)
+        }
+    }
+
+    /// Draw a color whose channels are already multiplied by its alpha over the
+    /// frame buffer.
+    pub fn premultiplied() -> BlendState {
+        BlendState::new(BlendFactor::One, BlendFactor::OneMinusSrcAlpha, BlendEquation::Add)
+    }
+
+    /// Add a color weighted by its alpha to the frame buffer.
+    pub fn additive() -> BlendState {
+        BlendState {
+            alpha_src: BlendFactor::One,
+            ..BlendState::new(BlendFactor::SrcAlpha, BlendFactor::One, BlendEquation::Add)
+        }
+    }
+
+    /// Multiply the frame buffer by a color, which darkens it.
+    pub fn multiply() -> BlendState {
+        BlendState::new(BlendFactor::DstColor, BlendFactor::Zero, BlendEquation::Add)
+    }
+
+    /// Multiply the inverses of the frame buffer and a color, which lightens it.
+    pub fn screen() -> BlendState {
+        BlendState::new(BlendFactor::One, BlendFactor::OneMinusSrcColor, BlendEquation::Add)
+    }
+
+    /// Blend a source color into a destination color, both normalized RGBA.
+    #[inline]
+    pub fn blend(&self, src: &[f32; 4], dst: &[f32; 4]) -> [f32; 4] {
+        let mut rgba = [0.0; 4];
+        for channel in 0..3 {
+            rgba[channel] = self.color_equation.apply(src[channel],
+                                                      self.color_src.weight(src, dst, channel),
+                                                      dst[channel],
+                                                      self.color_dst.weight(src, dst, channel));
+        }
+        rgba[3] = self.alpha_equation.apply(src[3],
+                                            self.alpha_src.weight(src, dst, 3),
+                                            dst[3],
+                                            self.alpha_dst.weight(src, dst, 3));
+
+        rgba
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{BlendState, BlendFactor, BlendEquation};
+
+
+    #[test]
+    fn test_blend_modes_should_combine_source_and_destination() {
+        let src = [1.0, 0.5, 0.0, 0.5];
+        let dst = [0.0, 0.5, 1.0, 1.0];
+
+        assert_eq!(BlendState::over().blend(&src, &dst), [0.5, 0.5, 0.5, 1.0]);
+        assert_eq!(BlendState::premultiplied().blend(&src, &dst), [1.0, 0.75, 0.5, 1.0]);
+        assert_eq!(BlendState::additive().blend(&src, &dst), [0.5, 0.75, 1.0, 1.5]);
+        assert_eq!(BlendState::multiply().blend(&src, &dst), [0.0, 0.25, 0.0, 0.5]);
+        assert_eq!(BlendState::screen().blend(&src, &dst), [1.0, 0.75, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_min_and_max_should_ignore_the_blend_factors() {
+        let state = BlendState::new(BlendFactor::Zero, BlendFactor::Zero, BlendEquation::Max);
+        assert_eq!(state.blend(&[0.25, 0.5, 0.75, 1.0], &[0.5, 0.5, 0.5, 0.5]), [0.5, 0.5, 0.75, 1.0]);
+
+        let state = BlendState { color_equation: BlendEquation::Min, ..state };
+        assert_eq!(state.blend(&[0.25, 0.5, 0.75, 1.0], &[0.5, 0.5, 0.5, 0.5]), [0.25, 0.5, 0.5, 1.0]);
+    }
+}
@@ -1,17 +1,17 @@
 use nalgebra::Matrix4;
 use num_traits::Float;
-use alga::general::Real;
+use alga::general::RealField;
 use raster;
 
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
-pub struct CameraSpec<N> where N: Float + Real {
+pub struct CameraSpec<N> where N: Float + RealField {
     pub focal_length: N,
     pub aperture_width: N,
     pub aperture_height: N,
 }
 
-impl<N> CameraSpec<N> where N: Float + Real {
+impl<N> CameraSpec<N> where N: Float + RealField {
     fn new(focal_length: N, aperture_width: N, aperture_height: N) -> CameraSpec<N> {
         CameraSpec {
             focal_length: focal_length,
@@ -22,7 +22,7 @@ impl<N> CameraSpec<N> where N: Float + Real {
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
-pub struct CameraModel<N> where N: Float + Real {
+pub struct CameraModel<N> where N: Float + RealField {
     spec: CameraSpec<N>,
     l_over_n: N,
     r_over_n: N,
@@ -30,7 +30,7 @@ pub struct CameraModel<N> where N: Float + Real {
     b_over_n: N,
 }
 
-impl<N> CameraModel<N> where N: Float + Real {
+impl<N> CameraModel<N> where N: Float + RealField {
     pub fn new(spec: CameraSpec<N>) -> CameraModel<N> {
         // Here we generate the ratio of (right, left, top, bottom)/near,
         // so to create an orthographic matrix, we pass 
@@ -0,0 +1,261 @@
+use nalgebra::{Vector4, Point3};
+use num_traits::Float;
+use alga::general::RealField;
+
+
+///
+/// A vertex in homogeneous clip space. The barycentric coordinates locate the vertex
+/// relative to the corners of the triangle it was clipped from.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ClipVertex<N> where N: Float + RealField {
+    pub position: Vector4<N>,
+    pub bary: Point3<N>,
+}
+
+impl<N> ClipVertex<N> where N: Float + RealField {
+    pub fn new(position: Vector4<N>, bary: Point3<N>) -> ClipVertex<N> {
+        ClipVertex {
+            position: position,
+            bary: bary,
+        }
+    }
+
+    /// Interpolate linearly between two vertices in clip space.
+    fn lerp(&self, other: &ClipVertex<N>, t: N) -> ClipVertex<N> {
+        let position = self.position + (other.position - self.position) * t;
+        let bary = Point3::new(self.bary[0] + (other.bary[0] - self.bary[0]) * t,
+                               self.bary[1] + (other.bary[1] - self.bary[1]) * t,
+                               self.bary[2] + (other.bary[2] - self.bary[2]) * t);
+
+        ClipVertex::new(position, bary)
+    }
+}
+
+///
+/// Clip a polygon against the half space where `distance` is non-negative. This is one
+/// pass of the Sutherland-Hodgman algorithm.
+///
+fn clip_polygon<N, F>(polygon: &[ClipVertex<N>], distance: F) -> Vec<ClipVertex<N>>
+    where N: Float + RealField,
+          F: Fn(&Vector4<N>) -> N
+{
+    let _0 = N::zero();
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+
+    for i in 0..polygon.len() {
+        let a = &polygon[i];
+        let b = &polygon[(i + 1) % polygon.len()];
+        let d_a = distance(&a.position);
+        let d_b = distance(&b.position);
+
+        if d_a >= _0 {
+            clipped.push(*a);
+        }
+
+        // Only add an intersection when the edge strictly crosses the plane, so that
+        // vertices lying on the plane are not duplicated.
+        if ((d_a > _0) && (d_b < _0)) || ((d_a < _0) && (d_b > _0)) {
+            clipped.push(a.lerp(b, d_a / (d_a - d_b)));
+        }
+    }
+
+    clipped
+}
+
+///
+/// Clip a triangle in homogeneous clip space against the near and far planes of the
+/// canonical view volume, i.e. keep the points with -1 <= z / w <= 1. The result is
+/// a list of triangles covering the visible part of the input, which is empty when
+/// the triangle lies entirely outside the volume.
+///
+/// The perspective projections in `raster` look down the negative z axis and copy
+/// the camera space z coordinate into w, so visible points have negative w, while
+/// orthographic projections leave w at one. Both cases are handled by clipping
+/// against the cones w >= |z| and -w >= |z| separately. Points behind the eye lie
+/// outside of both, so they never reach the perspective divide.
+///
+pub fn clip_triangle<N>(v0: &Vector4<N>,
+                        v1: &Vector4<N>,
+                        v2: &Vector4<N>) -> Vec<[ClipVertex<N>; 3]>
+    where N: Float + RealField
+{
+    let _0 = N::zero();
+    let _1 = N::one();
+
+    let triangle = [ClipVertex::new(*v0, Point3::new(_1, _0, _0)),
+                    ClipVertex::new(*v1, Point3::new(_0, _1, _0)),
+                    ClipVertex::new(*v2, Point3::new(_0, _0, _1))];
+
+    let mut triangles = Vec::new();
+    for &sign in [_1, -_1].iter() {
+        let near = |p: &Vector4<N>| sign * (p.w - p.z);
+        let far  = |p: &Vector4<N>| sign * (p.w + p.z);
+
+        // Accept the triangle as is when it lies entirely inside the volume.
+        if triangle.iter().all(|v| (near(&v.position) >= _0) && (far(&v.position) >= _0)) {
+            return vec![triangle];
+        }
+
+        let polygon = clip_polygon(&triangle, near);
+        let polygon = clip_polygon(&polygon, far);
+
+        // Triangulate the clipped polygon as a fan around its first vertex.
+        if polygon.len() >= 3 {
+            for i in 1..(polygon.len() - 1) {
+                triangles.push([polygon[0], polygon[i], polygon[i + 1]]);
+            }
+        }
+    }
+
+    triangles
+}
+
+
+///
+/// Clip a line segment in homogeneous clip space against the near and far planes of
+/// the canonical view volume, in the same way as `clip_triangle`. This returns `None`
+/// when the segment lies entirely outside the volume.
+///
+pub fn clip_line<N>(a: &ClipVertex<N>, b: &ClipVertex<N>) -> Option<(ClipVertex<N>, ClipVertex<N>)>
+    where N: Float + RealField
+{
+    let _0 = N::zero();
+    let _1 = N::one();
+
+    for &sign in [_1, -_1].iter() {
+        let near = |p: &Vector4<N>| sign * (p.w - p.z);
+        let far  = |p: &Vector4<N>| sign * (p.w + p.z);
+
+        // Shrink the parameter range of the segment to the part on the inside of
+        // each plane.
+        let mut t_min = _0;
+        let mut t_max = _1;
+        let distances = [(near(&a.position), near(&b.position)),
+                         (far(&a.position), far(&b.position))];
+        for &(d_a, d_b) in distances.iter() {
+            if (d_a < _0) && (d_b < _0) {
+                t_max = -_1;
+            } else if d_a < _0 {
+                t_min = Float::max(t_min, d_a / (d_a - d_b));
+            } else if d_b < _0 {
+                t_max = Float::min(t_max, d_a / (d_a - d_b));
+            }
+        }
+
+        if t_min <= t_max {
+            return Some((a.lerp(b, t_min), a.lerp(b, t_max)));
+        }
+    }
+
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector4;
+
+
+    #[test]
+    fn test_triangle_inside_the_view_volume_should_not_be_clipped() {
+        let v0: Vector4<f32> = Vector4::new(-0.5, -0.5, 0.0, 1.0);
+        let v1: Vector4<f32> = Vector4::new(0.5, -0.5, 0.5, 1.0);
+        let v2: Vector4<f32> = Vector4::new(0.0, 0.5, -0.5, 1.0);
+
+        let triangles = super::clip_triangle(&v0, &v1, &v2);
+
+        assert_eq!(triangles.len(), 1);
+        assert_eq!(triangles[0][0].position, v0);
+        assert_eq!(triangles[0][1].position, v1);
+        assert_eq!(triangles[0][2].position, v2);
+    }
+
+    #[test]
+    fn test_triangle_outside_the_view_volume_should_be_rejected() {
+        let v0: Vector4<f32> = Vector4::new(-0.5, -0.5, 2.0, 1.0);
+        let v1: Vector4<f32> = Vector4::new(0.5, -0.5, 3.0, 1.0);
+        let v2: Vector4<f32> = Vector4::new(0.0, 0.5, 1.5, 1.0);
+
+        assert!(super::clip_triangle(&v0, &v1, &v2).is_empty());
+    }
+
+    #[test]
+    fn test_triangle_crossing_the_near_plane_should_be_split() {
+        // One vertex lies beyond the near plane at z / w = 1.
+        let v0: Vector4<f32> = Vector4::new(-0.5, -0.5, 0.0, 1.0);
+        let v1: Vector4<f32> = Vector4::new(0.5, -0.5, 0.0, 1.0);
+        let v2: Vector4<f32> = Vector4::new(0.0, 0.5, 2.0, 1.0);
+
+        let triangles = super::clip_triangle(&v0, &v1, &v2);
+
+        // Cutting off one corner leaves a quadrilateral.
+        assert_eq!(triangles.len(), 2);
+        for triangle in triangles {
+            for vertex in triangle.iter() {
+                let p = vertex.position;
+                assert!(p.z <= p.w);
+                assert!(-p.w <= p.z);
+                assert_relative_eq!(vertex.bary[0] + vertex.bary[1] + vertex.bary[2], 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_clipping_should_keep_visible_points_with_negative_w() {
+        // Perspective projections looking down the negative z axis give visible
+        // points a negative w coordinate.
+        let v0: Vector4<f32> = Vector4::new(-0.5, -0.5, 0.0, -1.0);
+        let v1: Vector4<f32> = Vector4::new(0.5, -0.5, 0.0, -1.0);
+        let v2: Vector4<f32> = Vector4::new(0.0, 0.5, 0.0, -1.0);
+
+        let triangles = super::clip_triangle(&v0, &v1, &v2);
+
+        assert_eq!(triangles.len(), 1);
+    }
+
+    #[test]
+    fn test_clipping_should_remove_points_behind_the_eye() {
+        // The last vertex sits behind the eye, where w changes sign.
+        let v0: Vector4<f32> = Vector4::new(-0.5, -0.5, 0.0, -1.0);
+        let v1: Vector4<f32> = Vector4::new(0.5, -0.5, 0.0, -1.0);
+        let v2: Vector4<f32> = Vector4::new(0.0, 0.5, -3.0, 2.0);
+
+        let triangles = super::clip_triangle(&v0, &v1, &v2);
+
+        assert!(!triangles.is_empty());
+        for triangle in triangles {
+            for vertex in triangle.iter() {
+                let p = vertex.position;
+                assert!(p.w < 0.0);
+                assert!(p.z.abs() <= -p.w + 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_line_crossing_the_near_plane_should_be_shortened() {
+        use nalgebra::Point3;
+        use super::ClipVertex;
+
+        let a = ClipVertex::new(Vector4::new(0.0f32, 0.0, 0.0, 1.0), Point3::new(1.0, 0.0, 0.0));
+        let b = ClipVertex::new(Vector4::new(1.0f32, 0.0, 3.0, 1.0), Point3::new(0.0, 1.0, 0.0));
+
+        let (a, b) = super::clip_line(&a, &b).unwrap();
+
+        assert_relative_eq!(a.position, Vector4::new(0.0, 0.0, 0.0, 1.0));
+        assert_relative_eq!(b.position, Vector4::new(1.0 / 3.0, 0.0, 1.0, 1.0));
+        assert_relative_eq!(b.bary, Point3::new(2.0 / 3.0, 1.0 / 3.0, 0.0));
+    }
+
+    #[test]
+    fn test_line_outside_the_view_volume_should_be_rejected() {
+        use nalgebra::Point3;
+        use super::ClipVertex;
+
+        let a = ClipVertex::new(Vector4::new(0.0f32, 0.0, 2.0, 1.0), Point3::new(1.0, 0.0, 0.0));
+        let b = ClipVertex::new(Vector4::new(1.0f32, 0.0, 3.0, 1.0), Point3::new(0.0, 1.0, 0.0));
+
+        assert!(super::clip_line(&a, &b).is_none());
+    }
+}
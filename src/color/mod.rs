@@ -1,7 +1,8 @@
-pub use self::rgb::Rgb;
-use self::rgb::RgbCast;
+pub use self::rgb::{Rgb, RgbCast};
+pub use self::pixel::{Pixel, Channel, Rgb8, Rgba8, Gray8, Rgb16, Rgb32F, Rgba32F};
 
 mod rgb;
+mod pixel;
 
 ///
 /// Calculate the RGB color of a color vector.
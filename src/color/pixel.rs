@@ -0,0 +1,436 @@
+use std::ops;
+use std::fmt;
+use alga::general::RealField;
+use num_traits::{Float, NumCast};
+use color::Rgb;
+
+
+/// An 8 bit per channel RGB pixel, the format the renderer draws into by default.
+pub type Rgb8 = Rgb;
+
+///
+/// A single channel of a pixel. Integer channels hold values from zero to their
+/// maximum, which stands for full intensity, while float channels hold intensities
+/// directly and are free to go above one.
+///
+pub trait Channel: Copy + PartialEq + fmt::Debug {
+    /// The value of a fully lit channel.
+    fn max_value() -> f64;
+
+    fn to_f64(self) -> f64;
+
+    /// Convert a channel value, rounding to the nearest integer and clamping to the
+    /// range of integer channels.
+    fn from_f64(value: f64) -> Self;
+
+    /// Convert a shader intensity already scaled by `max_value`. Integer channels
+    /// truncate it rather than round it.
+    fn from_shaded<N: Float>(value: N) -> Self;
+}
+
+impl Channel for u8 {
+    #[inline]
+    fn max_value() -> f64 {
+        255.0
+    }
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> u8 {
+        Float::round(Float::min(Float::max(value, 0.0), 255.0)) as u8
+    }
+
+    #[inline]
+    fn from_shaded<N: Float>(value: N) -> u8 {
+        u8::from_f64(Float::trunc(value).to_f64().unwrap())
+    }
+}
+
+impl Channel for u16 {
+    #[inline]
+    fn max_value() -> f64 {
+        65535.0
+    }
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> u16 {
+        Float::round(Float::min(Float::max(value, 0.0), 65535.0)) as u16
+    }
+
+    #[inline]
+    fn from_shaded<N: Float>(value: N) -> u16 {
+        u16::from_f64(Float::trunc(value).to_f64().unwrap())
+    }
+}
+
+impl Channel for f32 {
+    #[inline]
+    fn max_value() -> f64 {
+        1.0
+    }
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> f32 {
+        value as f32
+    }
+
+    #[inline]
+    fn from_shaded<N: Float>(value: N) -> f32 {
+        // Values too large for an f32 become infinite.
+        value.to_f64().unwrap() as f32
+    }
+}
+
+///
+/// A pixel format a frame buffer can store. Conversions between formats go through
+/// normalized RGBA, where intensities run from zero to one and formats without an
+/// alpha channel are opaque. Gray formats take the Rec. 709 luminance of a color.
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]` structs made of nothing but `channel_count`
+/// channels, so that a slice of pixels can be viewed as channels or bytes.
+///
+pub unsafe trait Pixel: Copy + PartialEq + Default + fmt::Debug + Send + Sync {
+    type Channel: Channel;
+
+    fn channel_count() -> usize;
+
+    fn channels(&self) -> &[Self::Channel];
+
+    fn channels_mut(&mut self) -> &mut [Self::Channel];
+
+    fn to_rgba(&self) -> [f32; 4];
+
+    /// Convert from normalized RGBA. Integer formats round and clamp each channel.
+    fn from_rgba(rgba: [f32; 4]) -> Self;
+
+    ///
+    /// Convert a shader color with alpha. Integer formats truncate each channel the
+    /// same way `color::rgb` does, so shaded pixels come out the same in every 8
+    /// bit format.
+    ///
+    fn from_color<N: Float + RealField>(rgba: &[N; 4]) -> Self;
+
+    /// Convert the pixel into another format.
+    #[inline]
+    fn convert<Q: Pixel>(&self) -> Q {
+        Q::from_rgba(self.to_rgba())
+    }
+}
+
+/// Take the Rec. 709 luminance of a linear color.
+#[inline]
+fn luminance<N: Float>(r: N, g: N, b: N) -> N {
+    let cast = |value: f64| -> N { <N as NumCast>::from(value).unwrap() };
+    cast(0.2126) * r + cast(0.7152) * g + cast(0.0722) * b
+}
+
+/// Scale a shader intensity to a channel value.
+#[inline]
+fn quantize<C: Channel, N: Float>(value: N) -> C {
+    let max_value: N = <N as NumCast>::from(C::max_value()).unwrap();
+    C::from_shaded(max_value * value)
+}
+
+#[inline]
+fn normalize<C: Channel>(value: C) -> f32 {
+    (value.to_f64() / C::max_value()) as f32
+}
+
+#[inline]
+fn denormalize<C: Channel>(value: f32) -> C {
+    C::from_f64(value as f64 * C::max_value())
+}
+
+unsafe impl Pixel for Rgb {
+    type Channel = u8;
+
+    #[inline]
+    fn channel_count() -> usize {
+        3
+    }
+
+    #[inline]
+    fn channels(&self) -> &[u8] {
+        Rgb::channels(self)
+    }
+
+    #[inline]
+    fn channels_mut(&mut self) -> &mut [u8] {
+        Rgb::channels_mut(self)
+    }
+
+    #[inline]
+    fn to_rgba(&self) -> [f32; 4] {
+        [normalize(self.r()), normalize(self.g()), normalize(self.b()), 1.0]
+    }
+
+    #[inline]
+    fn from_rgba(rgba: [f32; 4]) -> Rgb {
+        Rgb::from_channels(denormalize(rgba[0]), denormalize(rgba[1]), denormalize(rgba[2]))
+    }
+
+    #[inline]
+    fn from_color<N: Float + RealField>(rgba: &[N; 4]) -> Rgb {
+        Rgb::from_channels(quantize(rgba[0]), quantize(rgba[1]), quantize(rgba[2]))
+    }
+}
+
+macro_rules! pixel_struct {
+    ($(#[$attr: meta])* $name: ident, $channel: ty, $count: expr, [$($field: ident: $index: expr),*]) => {
+        $(#[$attr])*
+        #[repr(C)]
+        #[derive(Copy, Clone, PartialEq, Default, Debug)]
+        pub struct $name {
+            data: [$channel; $count],
+        }
+
+        impl $name {
+            #[inline]
+            pub fn from_channels($($field: $channel),*) -> $name {
+                $name {
+                    data: [$($field),*]
+                }
+            }
+
+            $(
+                #[inline]
+                pub fn $field(&self) -> $channel {
+                    self.data[$index]
+                }
+            )*
+        }
+
+        impl ops::Index<usize> for $name {
+            type Output = $channel;
+
+            #[inline]
+            fn index(&self, index: usize) -> &$channel {
+                &self.data[index]
+            }
+        }
+
+        impl ops::IndexMut<usize> for $name {
+            #[inline]
+            fn index_mut(&mut self, index: usize) -> &mut $channel {
+                &mut self.data[index]
+            }
+        }
+
+        impl From<[$channel; $count]> for $name {
+            fn from(arr: [$channel; $count]) -> $name {
+                $name {
+                    data: arr
+                }
+            }
+        }
+    }
+}
+
+pixel_struct!(
+    /// An 8 bit per channel RGB pixel with straight alpha.
+    Rgba8, u8, 4, [r: 0, g: 1, b: 2, a: 3]
+);
+pixel_struct!(
+    /// An 8 bit gray pixel.
+    Gray8, u8, 1, [y: 0]
+);
+pixel_struct!(
+    /// A 16 bit per channel RGB pixel.
+    Rgb16, u16, 3, [r: 0, g: 1, b: 2]
+);
+pixel_struct!(
+    /// A float RGB pixel. The channels are not clamped, which keeps high dynamic
+    /// range colors intact until they are tone mapped.
+    Rgb32F, f32, 3, [r: 0, g: 1, b: 2]
+);
+pixel_struct!(
+    /// A float RGB pixel with straight alpha and unclamped color channels.
+    Rgba32F, f32, 4, [r: 0, g: 1, b: 2, a: 3]
+);
+
+impl Eq for Rgba8 {}
+impl Eq for Gray8 {}
+impl Eq for Rgb16 {}
+
+macro_rules! rgb_pixel_impl {
+    ($name: ident, $channel: ty) => {
+        unsafe impl Pixel for $name {
+            type Channel = $channel;
+
+            #[inline]
+            fn channel_count() -> usize {
+                3
+            }
+
+            #[inline]
+            fn channels(&self) -> &[$channel] {
+                &self.data
+            }
+
+            #[inline]
+            fn channels_mut(&mut self) -> &mut [$channel] {
+                &mut self.data
+            }
+
+            #[inline]
+            fn to_rgba(&self) -> [f32; 4] {
+                [normalize(self.data[0]), normalize(self.data[1]), normalize(self.data[2]), 1.0]
+            }
+
+            #[inline]
+            fn from_rgba(rgba: [f32; 4]) -> $name {
+                $name::from_channels(denormalize(rgba[0]), denormalize(rgba[1]), denormalize(rgba[2]))
+            }
+
+            #[inline]
+            fn from_color<N: Float + RealField>(rgba: &[N; 4]) -> $name {
+                $name::from_channels(quantize(rgba[0]), quantize(rgba[1]), quantize(rgba[2]))
+            }
+        }
+    }
+}
+
+macro_rules! rgba_pixel_impl {
+    ($name: ident, $channel: ty) => {
+        unsafe impl Pixel for $name {
+            type Channel = $channel;
+
+            #[inline]
+            fn channel_count() -> usize {
+                4
+            }
+
+            #[inline]
+            fn channels(&self) -> &[$channel] {
+                &self.data
+            }
+
+            #[inline]
+            fn channels_mut(&mut self) -> &mut [$channel] {
+                &mut self.data
+            }
+
+            #[inline]
+            fn to_rgba(&self) -> [f32; 4] {
+                [normalize(self.data[0]), normalize(self.data[1]), normalize(self.data[2]), normalize(self.data[3])]
+            }
+
+            #[inline]
+            fn from_rgba(rgba: [f32; 4]) -> $name {
+                $name::from_channels(denormalize(rgba[0]),
+                                     denormalize(rgba[1]),
+                                     denormalize(rgba[2]),
+                                     denormalize(Float::min(Float::max(rgba[3], 0.0), 1.0)))
+            }
+
+            #[inline]
+            fn from_color<N: Float + RealField>(rgba: &[N; 4]) -> $name {
+                $name::from_channels(quantize(rgba[0]),
+                                     quantize(rgba[1]),
+                                     quantize(rgba[2]),
+                                     quantize(Float::min(Float::max(rgba[3], N::zero()), N::one())))
+            }
+        }
+    }
+}
+
+rgb_pixel_impl!(Rgb16, u16);
+rgb_pixel_impl!(Rgb32F, f32);
+rgba_pixel_impl!(Rgba8, u8);
+rgba_pixel_impl!(Rgba32F, f32);
+
+unsafe impl Pixel for Gray8 {
+    type Channel = u8;
+
+    #[inline]
+    fn channel_count() -> usize {
+        1
+    }
+
+    #[inline]
+    fn channels(&self) -> &[u8] {
+        &self.data
+    }
+
+    #[inline]
+    fn channels_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    #[inline]
+    fn to_rgba(&self) -> [f32; 4] {
+        let y = normalize(self.data[0]);
+        [y, y, y, 1.0]
+    }
+
+    #[inline]
+    fn from_rgba(rgba: [f32; 4]) -> Gray8 {
+        Gray8::from_channels(denormalize(luminance(rgba[0], rgba[1], rgba[2])))
+    }
+
+    #[inline]
+    fn from_color<N: Float + RealField>(rgba: &[N; 4]) -> Gray8 {
+        Gray8::from_channels(quantize(luminance(rgba[0], rgba[1], rgba[2])))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+    use color::Rgb;
+    use super::{Pixel, Rgba8, Gray8, Rgb16, Rgb32F, Rgba32F};
+
+
+    #[test]
+    fn test_integer_conversions_should_round_trip_through_rgba() {
+        let rgb = Rgb::from_channels(10, 128, 255);
+
+        assert_eq!(rgb.convert::<Rgb16>(), Rgb16::from_channels(2570, 32896, 65535));
+        assert_eq!(rgb.convert::<Rgb16>().convert::<Rgb>(), rgb);
+        assert_eq!(rgb.convert::<Rgba8>(), Rgba8::from_channels(10, 128, 255, 255));
+        assert_eq!(rgb.convert::<Rgba32F>().convert::<Rgb>(), rgb);
+        assert_eq!(Rgba8::from_channels(1, 2, 3, 4).convert::<Rgba32F>().convert::<Rgba8>(),
+                   Rgba8::from_channels(1, 2, 3, 4));
+    }
+
+    #[test]
+    fn test_converting_to_gray_should_take_the_luminance() {
+        assert_eq!(Rgb::from_channels(255, 255, 255).convert::<Gray8>(), Gray8::from_channels(255));
+        assert_eq!(Rgb::from_channels(0, 255, 0).convert::<Gray8>(), Gray8::from_channels(182));
+        assert_eq!(Gray8::from_channels(51).convert::<Rgb>(), Rgb::from_channels(51, 51, 51));
+    }
+
+    #[test]
+    fn test_float_formats_should_keep_high_dynamic_range_until_quantized() {
+        let hdr = Rgb32F::from_color(&[4.0, 0.5, -1.0, 1.0]);
+
+        assert_eq!(hdr, Rgb32F::from_channels(4.0, 0.5, -1.0));
+        assert_eq!(hdr.convert::<Rgb>(), Rgb::from_channels(255, 128, 0));
+        assert_eq!(hdr.convert::<Rgba32F>().a(), 1.0);
+    }
+
+    #[test]
+    fn test_shader_colors_should_truncate_like_rgb_cast() {
+        let color = Vector3::new(0.5, 0.999, 1.0);
+
+        assert_eq!(Rgb::from_color(&[0.5, 0.999, 1.0, 1.0]), ::color::rgb::<_, Rgb>(color));
+        assert_eq!(Rgba8::from_color(&[0.5, 0.999, 1.0, 0.25]), Rgba8::from_channels(127, 254, 255, 63));
+    }
+}
@@ -1,10 +1,13 @@
 use std::ops;
 use std::convert::From;
 use std::fmt;
-use alga::general::Real;
+use num_traits::Float;
 use nalgebra::{Point3, Vector3};
 
 
+/// A 24 bit color. The layout is fixed to the three channels in order, so a slice
+/// of colors can be viewed as packed bytes.
+#[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct Rgb {
     data: [u8; 3],
@@ -43,6 +46,10 @@ impl Rgb {
     pub fn channels(&self) -> &[u8] {
         &self.data
     }
+
+    pub fn channels_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
 }
 
 impl ops::Index<usize> for Rgb {
@@ -69,7 +76,7 @@ impl From<[u8; 3]> for Rgb {
     }
 }
 
-impl<'a> From<&'a [u8; 3]> for Rgb {
+impl From<&[u8; 3]> for Rgb {
     fn from(arr: &[u8; 3]) -> Rgb {
         Rgb {
             data: *arr
@@ -111,9 +118,9 @@ macro_rules! rgb_cast_impl {
 
             #[inline]
             fn rgb_cast(color: $ident<$float_type>) -> Self::RgbValue {
-                let r: u8 = Real::trunc($max_value * color.x) as u8;
-                let g: u8 = Real::trunc($max_value * color.y) as u8;
-                let b: u8 = Real::trunc($max_value * color.z) as u8;
+                let r: u8 = Float::trunc($max_value * color.x) as u8;
+                let g: u8 = Float::trunc($max_value * color.y) as u8;
+                let b: u8 = Float::trunc($max_value * color.z) as u8;
 
                 Rgb::from_channels(r, g, b)
             }
@@ -124,9 +131,9 @@ macro_rules! rgb_cast_impl {
 
             #[inline]
             fn rgb_cast(color: &'a $ident<$float_type>) -> Self::RgbValue {
-                let r: u8 = Real::trunc($max_value * color.x) as u8;
-                let g: u8 = Real::trunc($max_value * color.y) as u8;
-                let b: u8 = Real::trunc($max_value * color.z) as u8;
+                let r: u8 = Float::trunc($max_value * color.x) as u8;
+                let g: u8 = Float::trunc($max_value * color.y) as u8;
+                let b: u8 = Float::trunc($max_value * color.z) as u8;
 
                 Rgb::from_channels(r, g, b)
             }
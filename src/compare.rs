@@ -0,0 +1,413 @@
+use std::f64;
+use std::fmt;
+use num_traits::Float;
+use color::{Pixel, Channel, Rgb};
+use frame_buffer::{FrameBuffer, Origin};
+
+
+///
+/// How far two images may differ and still match. Errors are measured in the
+/// units of the channels of the pixel format, so a tolerance of one allows a one
+/// bit change in an integer format, while floating point formats count in
+/// normalized values.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Tolerance {
+    /// The largest difference allowed in any channel of a pixel.
+    pub channel_error: f64,
+    /// How many pixels may differ by more than `channel_error`.
+    pub pixel_count: usize,
+}
+
+impl Tolerance {
+    pub fn new(channel_error: f64, pixel_count: usize) -> Tolerance {
+        Tolerance {
+            channel_error: channel_error,
+            pixel_count: pixel_count,
+        }
+    }
+
+    /// Only identical images match.
+    pub fn exact() -> Tolerance {
+        Tolerance::new(0.0, 0)
+    }
+}
+
+///
+/// The differences between two images. Errors are absolute differences between
+/// channels, in the units of the channels.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Comparison {
+    pub tolerance: Tolerance,
+    pub max_error: f64,
+    /// The row, counted from the bottom, and column of the first pixel with the
+    /// largest error, or `None` when the images are identical.
+    pub max_error_at: Option<(usize, usize)>,
+    pub mean_error: f64,
+    /// The peak signal to noise ratio in decibels, which is infinite for identical
+    /// images.
+    pub psnr: f64,
+    /// The mean structural similarity over every channel, which is one for
+    /// identical images and falls towards zero as their structure differs.
+    pub ssim: f64,
+    pub pixels_over_tolerance: usize,
+}
+
+impl Comparison {
+    /// Returns whether no more pixels are over the tolerance than it allows.
+    #[inline]
+    pub fn is_within_tolerance(&self) -> bool {
+        self.pixels_over_tolerance <= self.tolerance.pixel_count
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "max error {}", self.max_error)?;
+        if let Some((i, j)) = self.max_error_at {
+            write!(f, " at row {} column {}", i, j)?;
+        }
+        write!(f, ", mean error {:.6}, PSNR {:.2} dB, SSIM {:.6}, {} pixels over {} (at most {} allowed)",
+               self.mean_error, self.psnr, self.ssim,
+               self.pixels_over_tolerance, self.tolerance.channel_error, self.tolerance.pixel_count)
+    }
+}
+
+///
+/// Returns the difference between two channels. A NaN on either side counts as an
+/// infinite error, so that it can never match.
+///
+#[inline]
+fn channel_error<C: Channel>(expected: &C, actual: &C) -> f64 {
+    let (a, b) = (expected.to_f64(), actual.to_f64());
+    if a == b {
+        0.0
+    } else if a.is_nan() || b.is_nan() {
+        f64::INFINITY
+    } else {
+        (a - b).abs()
+    }
+}
+
+/// Returns the largest difference between the channels of two pixels.
+#[inline]
+fn pixel_error<P: Pixel>(expected: &P, actual: &P) -> f64 {
+    expected.channels().iter().zip(actual.channels().iter()).fold(0.0, |error, (a, b)| {
+        Float::max(error, channel_error(a, b))
+    })
+}
+
+///
+/// Compare an image against the one it is expected to match. The images may have
+/// different origins, and rows are matched from the bottom up, the same as
+/// comparing the buffers with `==` does.
+///
+/// Panics when the images have different shapes.
+///
+pub fn compare<OrA, OrB, P>(expected: &FrameBuffer<OrA, P>, actual: &FrameBuffer<OrB, P>, tolerance: &Tolerance) -> Comparison
+    where OrA: Origin, OrB: Origin, P: Pixel
+{
+    assert_eq!(expected.shape(), actual.shape(), "Only images of the same shape can be compared.");
+    let (width, height) = expected.shape();
+
+    let mut max_error = 0.0;
+    let mut max_error_at = None;
+    let mut total_error = 0.0;
+    let mut squared_error = 0.0;
+    let mut pixels_over_tolerance = 0;
+    for i in 0..height {
+        for (j, (a, b)) in expected.row(i).iter().zip(actual.row(i).iter()).enumerate() {
+            for (x, y) in a.channels().iter().zip(b.channels().iter()) {
+                let error = channel_error(x, y);
+                total_error += error;
+                squared_error += error * error;
+            }
+
+            let error = pixel_error(a, b);
+            if error > max_error {
+                max_error = error;
+                max_error_at = Some((i, j));
+            }
+            if error > tolerance.channel_error {
+                pixels_over_tolerance += 1;
+            }
+        }
+    }
+
+    // Empty images are identical.
+    let samples = (width * height * P::channel_count()) as f64;
+    if samples == 0.0 {
+        return Comparison {
+            tolerance: *tolerance,
+            max_error: 0.0,
+            max_error_at: None,
+            mean_error: 0.0,
+            psnr: f64::INFINITY,
+            ssim: 1.0,
+            pixels_over_tolerance: 0,
+        };
+    }
+
+    let mse = squared_error / samples;
+    let peak = P::Channel::max_value();
+    let psnr = if mse == 0.0 { f64::INFINITY } else { 10.0 * Float::log10(peak * peak / mse) };
+
+    Comparison {
+        tolerance: *tolerance,
+        max_error: max_error,
+        max_error_at: max_error_at,
+        mean_error: total_error / samples,
+        psnr: psnr,
+        ssim: ssim(expected, actual),
+        pixels_over_tolerance: pixels_over_tolerance,
+    }
+}
+
+///
+/// Returns the weighted sum of `count` values, `step` apart from `start`, under a
+/// kernel centered on the value at `index`. The kernel is cut off at the ends of
+/// the values and renormalized.
+///
+#[inline]
+fn filter_at(values: &[f64], start: usize, step: usize, count: usize, index: usize, kernel: &[f64]) -> f64 {
+    let radius = kernel.len() / 2;
+    let first = index.saturating_sub(radius);
+    let last = Ord::min(index + radius + 1, count);
+
+    let (mut sum, mut total) = (0.0, 0.0);
+    for n in first..last {
+        let weight = kernel[n + radius - index];
+        sum += weight * values[start + n * step];
+        total += weight;
+    }
+
+    sum / total
+}
+
+/// Blur an image of one channel, stored row by row, with a separable kernel.
+fn blur(values: &[f64], width: usize, height: usize, kernel: &[f64]) -> Vec<f64> {
+    let mut rows = Vec::with_capacity(values.len());
+    for i in 0..height {
+        for j in 0..width {
+            rows.push(filter_at(values, i * width, 1, width, j, kernel));
+        }
+    }
+
+    let mut blurred = Vec::with_capacity(values.len());
+    for i in 0..height {
+        for j in 0..width {
+            blurred.push(filter_at(&rows, j, width, height, i, kernel));
+        }
+    }
+
+    blurred
+}
+
+///
+/// Returns the structural similarity of two images of the same shape, averaged
+/// over every pixel and channel. The local statistics are weighted by an eleven
+/// pixel Gaussian window with a standard deviation of 1.5 pixels, as Wang et al.
+/// propose.
+///
+fn ssim<OrA, OrB, P>(expected: &FrameBuffer<OrA, P>, actual: &FrameBuffer<OrB, P>) -> f64
+    where OrA: Origin, OrB: Origin, P: Pixel
+{
+    let (width, height) = expected.shape();
+    let kernel: Vec<f64> = (-5..6).map(|k: i32| Float::exp(-((k * k) as f64) / (2.0 * 1.5 * 1.5))).collect();
+    let peak = P::Channel::max_value();
+    let (c1, c2) = ((0.01 * peak) * (0.01 * peak), (0.03 * peak) * (0.03 * peak));
+
+    let mut total = 0.0;
+    for channel in 0..P::channel_count() {
+        let (mut x, mut y) = (Vec::with_capacity(width * height), Vec::with_capacity(width * height));
+        for i in 0..height {
+            x.extend(expected.row(i).iter().map(|pixel| pixel.channels()[channel].to_f64()));
+            y.extend(actual.row(i).iter().map(|pixel| pixel.channels()[channel].to_f64()));
+        }
+        let xx: Vec<f64> = x.iter().map(|a| a * a).collect();
+        let yy: Vec<f64> = y.iter().map(|b| b * b).collect();
+        let xy: Vec<f64> = x.iter().zip(y.iter()).map(|(a, b)| a * b).collect();
+
+        let mu_x = blur(&x, width, height, &kernel);
+        let mu_y = blur(&y, width, height, &kernel);
+        let e_xx = blur(&xx, width, height, &kernel);
+        let e_yy = blur(&yy, width, height, &kernel);
+        let e_xy = blur(&xy, width, height, &kernel);
+
+        for k in 0..(width * height) {
+            let var_x = e_xx[k] - mu_x[k] * mu_x[k];
+            let var_y = e_yy[k] - mu_y[k] * mu_y[k];
+            let cov = e_xy[k] - mu_x[k] * mu_y[k];
+            total += ((2.0 * mu_x[k] * mu_y[k] + c1) * (2.0 * cov + c2))
+                   / ((mu_x[k] * mu_x[k] + mu_y[k] * mu_y[k] + c1) * (var_x + var_y + c2));
+        }
+    }
+
+    total / (width * height * P::channel_count()) as f64
+}
+
+///
+/// Returns a false color image of where two images differ. Matching pixels show
+/// the expected image in dark gray, pixels that differ within the tolerance are
+/// blue, brighter the larger the error, and pixels over the tolerance run from
+/// red to yellow as the error grows to the largest channel value. The result has
+/// the origin of the expected image, and it can be written out as a PPM file.
+///
+/// Panics when the images have different shapes.
+///
+pub fn diff_image<OrA, OrB, P>(expected: &FrameBuffer<OrA, P>,
+                               actual: &FrameBuffer<OrB, P>,
+                               tolerance: &Tolerance) -> FrameBuffer<OrA, Rgb>
+    where OrA: Origin, OrB: Origin, P: Pixel
+{
+    assert_eq!(expected.shape(), actual.shape(), "Only images of the same shape can be compared.");
+    let (width, height) = expected.shape();
+    let peak = P::Channel::max_value();
+    let limit = tolerance.channel_error;
+
+    let mut diff = FrameBuffer::new(width, height);
+    for i in 0..height {
+        let pixels = expected.row(i).iter().zip(actual.row(i).iter());
+        for (pixel, (a, b)) in diff.row_mut(i).iter_mut().zip(pixels) {
+            let error = pixel_error(a, b);
+            let rgba = if error == 0.0 {
+                let rgba = a.to_rgba();
+                let gray = 0.25 * (0.2126 * rgba[0] + 0.7152 * rgba[1] + 0.0722 * rgba[2]);
+                [gray, gray, gray, 1.0]
+            } else if error <= limit {
+                [0.0, 0.0, (0.5 + 0.5 * error / limit) as f32, 1.0]
+            } else {
+                let t = if peak > limit { Float::min((error - limit) / (peak - limit), 1.0) } else { 1.0 };
+                [1.0, t as f32, 0.0, 1.0]
+            };
+            *pixel = Rgb::from_rgba(rgba);
+        }
+    }
+
+    diff
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{f32, f64};
+    use color::{Rgb, Rgb32F};
+    use frame_buffer::{FrameBuffer, TopLeft, BottomLeft};
+    use super::{Tolerance, compare, diff_image};
+
+
+    fn gray(width: usize, height: usize, value: u8) -> FrameBuffer<TopLeft> {
+        let mut frame_buffer = FrameBuffer::new(width, height);
+        frame_buffer.initialize_with(Rgb::from_channels(value, value, value));
+        frame_buffer
+    }
+
+    #[test]
+    fn test_identical_images_should_match_exactly() {
+        let expected = gray(4, 3, 100);
+        let mut actual = FrameBuffer::<BottomLeft>::new(4, 3);
+        actual.initialize_with(Rgb::from_channels(100, 100, 100));
+
+        let comparison = compare(&expected, &actual, &Tolerance::exact());
+        assert_eq!(comparison.max_error, 0.0);
+        assert_eq!(comparison.max_error_at, None);
+        assert_eq!(comparison.mean_error, 0.0);
+        assert_eq!(comparison.psnr, f64::INFINITY);
+        assert_eq!(comparison.ssim, 1.0);
+        assert!(comparison.is_within_tolerance());
+    }
+
+    #[test]
+    fn test_empty_images_should_match_exactly() {
+        let empty = FrameBuffer::<TopLeft>::new(0, 0);
+        let comparison = compare(&empty, &empty, &Tolerance::exact());
+        assert_eq!(comparison.max_error, 0.0);
+        assert_eq!(comparison.mean_error, 0.0);
+        assert_eq!(comparison.psnr, f64::INFINITY);
+        assert_eq!(comparison.ssim, 1.0);
+        assert!(comparison.is_within_tolerance());
+
+        let zero_width = FrameBuffer::<BottomLeft>::new(0, 3);
+        assert!(compare(&FrameBuffer::<TopLeft>::new(0, 3), &zero_width, &Tolerance::exact()).is_within_tolerance());
+    }
+
+    #[test]
+    fn test_a_one_bit_change_should_be_reported_and_fit_a_one_bit_tolerance() {
+        let expected = gray(4, 4, 100);
+        let mut actual = gray(4, 4, 100);
+        actual[2][1] = Rgb::from_channels(100, 101, 100);
+
+        let comparison = compare(&expected, &actual, &Tolerance::exact());
+        assert_eq!(comparison.max_error, 1.0);
+        assert_eq!(comparison.max_error_at, Some((2, 1)));
+        assert_eq!(comparison.pixels_over_tolerance, 1);
+        assert_relative_eq!(comparison.mean_error, 1.0 / 48.0);
+        assert_relative_eq!(comparison.psnr, 10.0 * f64::log10(255.0 * 255.0 * 48.0));
+        assert!(comparison.ssim > 0.99 && comparison.ssim < 1.0);
+        assert!(!comparison.is_within_tolerance());
+        assert!(comparison.to_string().contains("at row 2 column 1"));
+
+        assert!(compare(&expected, &actual, &Tolerance::new(1.0, 0)).is_within_tolerance());
+        assert!(compare(&expected, &actual, &Tolerance::new(0.0, 1)).is_within_tolerance());
+    }
+
+    #[test]
+    fn test_ssim_should_penalize_structure_more_than_brightness() {
+        let flat = gray(16, 16, 128);
+        let brighter = gray(16, 16, 136);
+        let mut checkers = gray(16, 16, 0);
+        for i in 0..16 {
+            for j in 0..16 {
+                if (i + j) % 2 == 0 {
+                    checkers[i][j] = Rgb::from_channels(255, 255, 255);
+                }
+            }
+        }
+
+        let shifted = compare(&flat, &brighter, &Tolerance::exact());
+        let scrambled = compare(&flat, &checkers, &Tolerance::exact());
+        assert!(shifted.ssim > 0.9);
+        assert!(scrambled.ssim < 0.1);
+        assert!(shifted.psnr > scrambled.psnr);
+
+        // Floating point formats count errors in normalized values.
+        let mut hdr = FrameBuffer::<TopLeft, Rgb32F>::new(2, 2);
+        hdr[0][0] = Rgb32F::from_channels(0.5, 0.0, 0.0);
+        let comparison = compare(&FrameBuffer::<TopLeft, Rgb32F>::new(2, 2), &hdr, &Tolerance::new(0.25, 0));
+        assert_eq!(comparison.max_error, 0.5);
+        assert_eq!(comparison.pixels_over_tolerance, 1);
+    }
+
+    #[test]
+    fn test_nan_channels_should_never_match() {
+        let expected = FrameBuffer::<TopLeft, Rgb32F>::new(2, 1);
+        let mut actual = FrameBuffer::<TopLeft, Rgb32F>::new(2, 1);
+        actual[0][1] = Rgb32F::from_channels(0.0, f32::NAN, 0.0);
+
+        let comparison = compare(&expected, &actual, &Tolerance::new(1.0, 0));
+        assert_eq!(comparison.max_error, f64::INFINITY);
+        assert_eq!(comparison.max_error_at, Some((0, 1)));
+        assert_eq!(comparison.pixels_over_tolerance, 1);
+        assert!(!comparison.is_within_tolerance());
+        assert!(!compare(&actual, &actual, &Tolerance::exact()).is_within_tolerance());
+
+        let diff = diff_image(&expected, &actual, &Tolerance::new(1.0, 0));
+        assert_eq!(diff.row(0), &[Rgb::from_channels(0, 0, 0), Rgb::from_channels(255, 255, 0)][..]);
+    }
+
+    #[test]
+    fn test_diff_images_should_color_pixels_by_their_error() {
+        let expected = gray(4, 1, 200);
+        let mut actual = gray(4, 1, 200);
+        actual[0][1] = Rgb::from_channels(201, 200, 200);
+        actual[0][2] = Rgb::from_channels(200, 200, 255);
+        actual[0][3] = Rgb::from_channels(0, 200, 200);
+
+        let diff = diff_image(&expected, &actual, &Tolerance::new(1.0, 0));
+        assert_eq!(diff.row(0), &[Rgb::from_channels(50, 50, 50),
+                                  Rgb::from_channels(0, 0, 255),
+                                  Rgb::from_channels(255, 54, 0),
+                                  Rgb::from_channels(255, 200, 0)][..]);
+    }
+}
@@ -0,0 +1,517 @@
+use std::cmp::Ordering;
+use nalgebra::Point2;
+use num_traits::Float;
+use alga::general::RealField;
+use color::Pixel;
+use frame_buffer::{FrameBuffer, Origin};
+
+
+///
+/// A rectangle of pixels for 2D drawing. Like every position in this module, `x`
+/// counts columns from the left and `y` counts rows away from the origin of the
+/// frame buffer, so `y` runs down the image for `TopLeft` buffers and up it for
+/// `BottomLeft` ones. Rectangles may hang off the edges of the buffer, and only
+/// their pixels inside of it are drawn.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Rect {
+    pub x: isize,
+    pub y: isize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: isize, y: isize, width: usize, height: usize) -> Rect {
+        Rect {
+            x: x,
+            y: y,
+            width: width,
+            height: height,
+        }
+    }
+
+    /// The column just past the right edge, saturating at the largest position.
+    #[inline]
+    fn x_end(&self) -> isize {
+        self.x.saturating_add(Ord::min(self.width, isize::MAX as usize) as isize)
+    }
+
+    /// The row just past the edge farthest from the origin, saturating at the largest position.
+    #[inline]
+    fn y_end(&self) -> isize {
+        self.y.saturating_add(Ord::min(self.height, isize::MAX as usize) as isize)
+    }
+}
+
+///
+/// Which points a polygon that crosses itself fills. The even-odd rule fills the
+/// points that a ray leaving them crosses the outline an odd number of times, and
+/// the non-zero rule fills the points that the outline winds around at all.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum WindingRule {
+    EvenOdd,
+    NonZero,
+}
+
+///
+/// Returns the row, counted from the bottom, that lies `y` rows from the origin.
+/// `storage_row` maps the other way, and it is its own inverse for both origins.
+///
+#[inline]
+fn row_from_origin<Or: Origin>(y: usize, height: usize) -> usize {
+    Or::storage_row(y, height)
+}
+
+///
+/// Fill the pixels of row `y` from column `x_min` up to but not including `x_max`.
+fn fill_span<Or, P>(frame_buffer: &mut FrameBuffer<Or, P>, y: isize, x_min: isize, x_max: isize, color: P)
+    where Or: Origin, P: Pixel
+{
+    let (width, height) = frame_buffer.shape();
+    if (y < 0) || (y >= height as isize) {
+        return;
+    }
+
+    let x_min = Ord::max(x_min, 0);
+    let x_max = Ord::min(x_max, width as isize);
+    if x_min >= x_max {
+        return;
+    }
+
+    let row = row_from_origin::<Or>(y as usize, height);
+    for pixel in frame_buffer.row_mut(row)[(x_min as usize)..(x_max as usize)].iter_mut() {
+        *pixel = color;
+    }
+}
+
+#[inline]
+fn plot<Or, P>(frame_buffer: &mut FrameBuffer<Or, P>, x: isize, y: isize, color: P)
+    where Or: Origin, P: Pixel
+{
+    fill_span(frame_buffer, y, x, x.saturating_add(1), color);
+}
+
+/// Fill every pixel of a rectangle.
+pub fn fill_rect<Or, P>(frame_buffer: &mut FrameBuffer<Or, P>, rect: &Rect, color: P)
+    where Or: Origin, P: Pixel
+{
+    let height = frame_buffer.height() as isize;
+    for y in Ord::max(rect.y, 0)..Ord::min(rect.y_end(), height) {
+        fill_span(frame_buffer, y, rect.x, rect.x_end(), color);
+    }
+}
+
+/// Draw the one pixel wide outline just inside of a rectangle.
+pub fn stroke_rect<Or, P>(frame_buffer: &mut FrameBuffer<Or, P>, rect: &Rect, color: P)
+    where Or: Origin, P: Pixel
+{
+    if (rect.width == 0) || (rect.height == 0) {
+        return;
+    }
+
+    let height = frame_buffer.height() as isize;
+    let (x_max, y_max) = (rect.x_end(), rect.y_end());
+    fill_span(frame_buffer, rect.y, rect.x, x_max, color);
+    fill_span(frame_buffer, y_max - 1, rect.x, x_max, color);
+    for y in Ord::max(rect.y.saturating_add(1), 0)..Ord::min(y_max - 1, height) {
+        plot(frame_buffer, rect.x, y, color);
+        plot(frame_buffer, x_max - 1, y, color);
+    }
+}
+
+///
+/// Clip the segment between two points to a rectangle with Liang and Barsky's
+/// algorithm. Returns the ends of the part of the segment inside, or `None` when
+/// it misses the rectangle.
+///
+fn clip_line(from: (f64, f64), to: (f64, f64), min: (f64, f64), max: (f64, f64)) -> Option<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let (mut t_min, mut t_max) = (0.0, 1.0);
+    for &(p, q) in [(-dx, from.0 - min.0), (dx, max.0 - from.0), (-dy, from.1 - min.1), (dy, max.1 - from.1)].iter() {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t_min = Float::max(t_min, q / p);
+        } else {
+            t_max = Float::min(t_max, q / p);
+        }
+    }
+    if t_min > t_max {
+        return None;
+    }
+
+    Some(((from.0 + t_min * dx, from.1 + t_min * dy), (from.0 + t_max * dx, from.1 + t_max * dy)))
+}
+
+///
+/// Draw a line between the centers of two pixels, including both ends. This uses
+/// Bresenham's algorithm, so the line is one pixel thick and has no gaps. A line
+/// that leaves the frame buffer is first clipped to within a pixel of its edges,
+/// and then runs between the pixels nearest the clipped ends.
+///
+pub fn draw_line<Or, P>(frame_buffer: &mut FrameBuffer<Or, P>, from: (isize, isize), to: (isize, isize), color: P)
+    where Or: Origin, P: Pixel
+{
+    let (width, height) = (frame_buffer.width() as isize, frame_buffer.height() as isize);
+    let inside = |(x, y): (isize, isize)| (0 <= x) && (x < width) && (0 <= y) && (y < height);
+    let (from, to) = if inside(from) && inside(to) {
+        (from, to)
+    } else {
+        let as_point = |(x, y): (isize, isize)| (x as f64, y as f64);
+        let as_pixel = |(x, y): (f64, f64)| (Float::round(x) as isize, Float::round(y) as isize);
+        match clip_line(as_point(from), as_point(to), (-1.0, -1.0), (width as f64, height as f64)) {
+            Some((from, to)) => (as_pixel(from), as_pixel(to)),
+            None => return,
+        }
+    };
+
+    let (mut x, mut y) = from;
+    let dx = (to.0 - x).abs();
+    let dy = -(to.1 - y).abs();
+    let step_x = if x < to.0 { 1 } else { -1 };
+    let step_y = if y < to.1 { 1 } else { -1 };
+    let mut error = dx + dy;
+
+    loop {
+        plot(frame_buffer, x, y, color);
+        if (x, y) == to {
+            break;
+        }
+
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+///
+/// Returns how many pixels each side of the center the row `dy` rows from the
+/// center of an ellipse reaches, or `None` when the row misses the ellipse. A pixel
+/// is inside of the ellipse when its center is.
+///
+fn ellipse_half_width(rx: usize, ry: usize, dy: isize) -> Option<isize> {
+    if dy.abs() > ry as isize {
+        return None;
+    }
+
+    let t = if ry == 0 { 0.0 } else { dy as f64 / ry as f64 };
+    Some(Float::floor(rx as f64 * Float::sqrt(1.0 - t * t)) as isize)
+}
+
+///
+/// Fill the pixels of an ellipse centered on the pixel at `center`, reaching
+/// `rx` pixels across and `ry` pixels along the rows from the center.
+///
+pub fn fill_ellipse<Or, P>(frame_buffer: &mut FrameBuffer<Or, P>,
+                           center: (isize, isize),
+                           rx: usize,
+                           ry: usize,
+                           color: P)
+    where Or: Origin, P: Pixel
+{
+    let (cx, cy) = center;
+    for dy in -(ry as isize)..(ry as isize + 1) {
+        if let Some(half_width) = ellipse_half_width(rx, ry, dy) {
+            fill_span(frame_buffer, cy + dy, cx - half_width, cx + half_width + 1, color);
+        }
+    }
+}
+
+///
+/// Draw the outline of the ellipse that `fill_ellipse` fills. These are its pixels
+/// that have a neighbor above or below outside of the ellipse, along with the ends
+/// of each row, which keeps the outline free of gaps.
+///
+pub fn stroke_ellipse<Or, P>(frame_buffer: &mut FrameBuffer<Or, P>,
+                             center: (isize, isize),
+                             rx: usize,
+                             ry: usize,
+                             color: P)
+    where Or: Origin, P: Pixel
+{
+    let (cx, cy) = center;
+    for dy in -(ry as isize)..(ry as isize + 1) {
+        if let Some(half_width) = ellipse_half_width(rx, ry, dy) {
+            let above = ellipse_half_width(rx, ry, dy - 1).unwrap_or(-1);
+            let below = ellipse_half_width(rx, ry, dy + 1).unwrap_or(-1);
+            let inner = Ord::min(Ord::min(above, below), half_width - 1);
+            fill_span(frame_buffer, cy + dy, cx - half_width, cx - inner, color);
+            fill_span(frame_buffer, cy + dy, cx + inner + 1, cx + half_width + 1, color);
+        }
+    }
+}
+
+/// Fill the pixels of a circle of radius `radius` centered on the pixel at `center`.
+pub fn fill_circle<Or, P>(frame_buffer: &mut FrameBuffer<Or, P>, center: (isize, isize), radius: usize, color: P)
+    where Or: Origin, P: Pixel
+{
+    fill_ellipse(frame_buffer, center, radius, radius, color);
+}
+
+/// Draw the outline of the circle that `fill_circle` fills.
+pub fn stroke_circle<Or, P>(frame_buffer: &mut FrameBuffer<Or, P>, center: (isize, isize), radius: usize, color: P)
+    where Or: Origin, P: Pixel
+{
+    stroke_ellipse(frame_buffer, center, radius, radius, color);
+}
+
+///
+/// Fill a polygon, which may be concave or cross itself. Pixel centers sit on
+/// integer coordinates, and a pixel is filled when its center is inside of the
+/// polygon by the winding rule. Centers on an edge are only filled when the edge
+/// bounds the polygon on its left or on its side nearest the origin, so polygons
+/// sharing an edge do not overlap. Nothing is drawn when a vertex is NaN or
+/// infinite.
+///
+pub fn fill_polygon<Or, P, N>(frame_buffer: &mut FrameBuffer<Or, P>,
+                              points: &[Point2<N>],
+                              rule: WindingRule,
+                              color: P)
+    where Or: Origin, P: Pixel, N: Float + RealField
+{
+    if points.len() < 3 {
+        return;
+    }
+
+    let points: Vec<(f64, f64)> = points.iter()
+                                        .map(|p| (p.x.to_f64().unwrap(), p.y.to_f64().unwrap()))
+                                        .collect();
+    if points.iter().any(|p| !p.0.is_finite() || !p.1.is_finite()) {
+        return;
+    }
+
+    let y_min = points.iter().fold(Float::infinity(), |y_min, p| Float::min(y_min, p.1));
+    let y_max = points.iter().fold(Float::neg_infinity(), |y_max, p| Float::max(y_max, p.1));
+    let height = frame_buffer.height() as f64;
+    let first = Float::max(Float::ceil(y_min), 0.0) as isize;
+    let last = Float::min(Float::ceil(y_max), height) as isize;
+
+    let mut crossings: Vec<(f64, i32)> = Vec::new();
+    for y in first..last {
+        let yc = y as f64;
+
+        // Each edge that crosses the row adds the position where it does and the
+        // direction it winds in. Edges include their start and not their end.
+        crossings.clear();
+        for (i, a) in points.iter().enumerate() {
+            let b = &points[(i + 1) % points.len()];
+            if (a.1 <= yc) && (yc < b.1) {
+                crossings.push((a.0 + (yc - a.1) * (b.0 - a.0) / (b.1 - a.1), 1));
+            } else if (b.1 <= yc) && (yc < a.1) {
+                crossings.push((a.0 + (yc - a.1) * (b.0 - a.0) / (b.1 - a.1), -1));
+            }
+        }
+        crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        let mut winding = 0;
+        for pair in crossings.windows(2) {
+            winding += pair[0].1;
+            let inside = match rule {
+                WindingRule::EvenOdd => winding % 2 != 0,
+                WindingRule::NonZero => winding != 0,
+            };
+            if inside {
+                fill_span(frame_buffer, y, Float::ceil(pair[0].0) as isize, Float::ceil(pair[1].0) as isize, color);
+            }
+        }
+    }
+}
+
+///
+/// Draw the closed outline of a polygon, with its corners rounded to the nearest
+/// pixels. Nothing is drawn when a corner is NaN, infinite or out of the range of
+/// pixel positions.
+///
+pub fn stroke_polygon<Or, P, N>(frame_buffer: &mut FrameBuffer<Or, P>, points: &[Point2<N>], color: P)
+    where Or: Origin, P: Pixel, N: Float + RealField
+{
+    let corners: Option<Vec<(isize, isize)>> = points.iter().map(|p| {
+        Some((Float::round(p.x).to_isize()?, Float::round(p.y).to_isize()?))
+    }).collect();
+    let corners = match corners {
+        Some(corners) => corners,
+        None => return,
+    };
+
+    for (i, &corner) in corners.iter().enumerate() {
+        draw_line(frame_buffer, corner, corners[(i + 1) % corners.len()], color);
+    }
+}
+
+///
+/// Copy the pixels of `rect` in `src` into `dst`, with the corner of the rectangle
+/// nearest the origin landing on the pixel at `position`. The copy is clipped to
+/// both frame buffers. Both share an origin, so the image keeps its orientation;
+/// convert one with `into_origin` to copy between buffers with different origins.
+///
+pub fn blit<Or, P>(dst: &mut FrameBuffer<Or, P>, position: (isize, isize), src: &FrameBuffer<Or, P>, rect: &Rect)
+    where Or: Origin, P: Pixel
+{
+    // Clip the rectangle to the source, then the shifted rectangle to the target.
+    let (src_width, src_height) = (src.width() as isize, src.height() as isize);
+    let (dst_width, dst_height) = (dst.width() as isize, dst.height() as isize);
+    let (dx, dy) = (position.0 - rect.x, position.1 - rect.y);
+    let x_min = Ord::max(Ord::max(rect.x, 0), -dx);
+    let y_min = Ord::max(Ord::max(rect.y, 0), -dy);
+    let x_max = Ord::min(Ord::min(rect.x_end(), src_width), dst_width - dx);
+    let y_max = Ord::min(Ord::min(rect.y_end(), src_height), dst_height - dy);
+    if (x_min >= x_max) || (y_min >= y_max) {
+        return;
+    }
+
+    let columns = (x_min as usize)..(x_max as usize);
+    let shifted = ((x_min + dx) as usize)..((x_max + dx) as usize);
+    for y in y_min..y_max {
+        let src_row = row_from_origin::<Or>(y as usize, src.height());
+        let dst_row = row_from_origin::<Or>((y + dy) as usize, dst.height());
+        dst.row_mut(dst_row)[shifted.clone()].copy_from_slice(&src.row(src_row)[columns.clone()]);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Point2;
+    use color::Rgb;
+    use frame_buffer::{FrameBuffer, TopLeft, BottomLeft};
+    use super::{Rect, WindingRule};
+
+
+    /// Draw the frame buffer as text, from the top row down, with `#` for white pixels.
+    fn picture<Or: ::frame_buffer::Origin>(frame_buffer: &FrameBuffer<Or>) -> Vec<String> {
+        let white = Rgb::from_channels(255, 255, 255);
+        (0..frame_buffer.height()).rev().map(|i| {
+            frame_buffer.row(i).iter().map(|&rgb| if rgb == white { '#' } else { '.' }).collect()
+        }).collect()
+    }
+
+    #[test]
+    fn test_rectangles_should_be_placed_from_the_origin_and_clipped() {
+        let white = Rgb::from_channels(255, 255, 255);
+        let mut top_left = FrameBuffer::<TopLeft>::new(5, 4);
+        let mut bottom_left = FrameBuffer::<BottomLeft>::new(5, 4);
+        super::fill_rect(&mut top_left, &Rect::new(-1, 0, 3, 2), white);
+        super::fill_rect(&mut bottom_left, &Rect::new(-1, 0, 3, 2), white);
+
+        assert_eq!(picture(&top_left), vec!["##...", "##...", ".....", "....."]);
+        assert_eq!(picture(&bottom_left), vec![".....", ".....", "##...", "##..."]);
+
+        let mut buf = FrameBuffer::<TopLeft>::new(5, 4);
+        super::stroke_rect(&mut buf, &Rect::new(1, 0, 4, 4), white);
+        assert_eq!(picture(&buf), vec![".####", ".#..#", ".#..#", ".####"]);
+    }
+
+    #[test]
+    fn test_shapes_far_off_the_frame_buffer_should_be_clipped() {
+        let white = Rgb::from_channels(255, 255, 255);
+        let mut buf = FrameBuffer::<TopLeft>::new(5, 4);
+        super::fill_rect(&mut buf, &Rect::new(0, -1_000_000_000_000, 2, 1_000_000_000_001), white);
+        super::fill_rect(&mut buf, &Rect::new(isize::MAX - 1, isize::MAX - 1, usize::MAX, usize::MAX), white);
+        super::stroke_rect(&mut buf, &Rect::new(isize::MIN, isize::MIN, usize::MAX, usize::MAX), white);
+        super::stroke_rect(&mut buf, &Rect::new(isize::MAX, isize::MAX, 1, 1), white);
+        assert_eq!(picture(&buf), vec!["##...", ".....", ".....", "....."]);
+
+        let mut buf = FrameBuffer::<TopLeft>::new(5, 4);
+        super::draw_line(&mut buf, (0, 2), (isize::MAX, 2), white);
+        super::draw_line(&mut buf, (isize::MIN, isize::MIN), (isize::MIN, isize::MAX), white);
+        super::draw_line(&mut buf, (-4, -4), (2, 2), white);
+        assert_eq!(picture(&buf), vec!["#....", ".#...", "#####", "....."]);
+    }
+
+    #[test]
+    fn test_circles_should_be_symmetric_and_outlines_should_have_no_gaps() {
+        let white = Rgb::from_channels(255, 255, 255);
+        let mut filled = FrameBuffer::<TopLeft>::new(7, 7);
+        let mut outline = FrameBuffer::<TopLeft>::new(7, 7);
+        super::fill_circle(&mut filled, (3, 3), 3, white);
+        super::stroke_circle(&mut outline, (3, 3), 3, white);
+
+        assert_eq!(picture(&filled), vec!["...#...",
+                                          ".#####.",
+                                          ".#####.",
+                                          "#######",
+                                          ".#####.",
+                                          ".#####.",
+                                          "...#..."]);
+        assert_eq!(picture(&outline), vec!["...#...",
+                                           ".##.##.",
+                                           ".#...#.",
+                                           "#.....#",
+                                           ".#...#.",
+                                           ".##.##.",
+                                           "...#..."]);
+
+        let mut ellipse = FrameBuffer::<TopLeft>::new(9, 3);
+        super::fill_ellipse(&mut ellipse, (4, 1), 4, 1, white);
+        assert_eq!(picture(&ellipse), vec!["....#....", "#########", "....#...."]);
+    }
+
+    #[test]
+    fn test_self_intersecting_polygons_should_follow_the_winding_rule() {
+        let white = Rgb::from_channels(255, 255, 255);
+        // A square traced twice around winds twice around its pixels, while its
+        // even-odd interior is empty.
+        let square = [Point2::new(0.0, 0.0), Point2::new(4.0, 0.0), Point2::new(4.0, 4.0), Point2::new(0.0, 4.0)];
+        let twice: Vec<Point2<f64>> = square.iter().chain(square.iter()).cloned().collect();
+
+        let mut even_odd = FrameBuffer::<TopLeft>::new(5, 5);
+        let mut non_zero = FrameBuffer::<TopLeft>::new(5, 5);
+        super::fill_polygon(&mut even_odd, &twice, WindingRule::EvenOdd, white);
+        super::fill_polygon(&mut non_zero, &twice, WindingRule::NonZero, white);
+
+        assert_eq!(picture(&even_odd), vec!["....."; 5]);
+        assert_eq!(picture(&non_zero), vec!["####.", "####.", "####.", "####.", "....."]);
+
+        // A concave arrow head.
+        let arrow = [Point2::new(0.0, 0.0), Point2::new(6.0, 3.0), Point2::new(0.0, 6.0), Point2::new(3.0, 3.0)];
+        let mut buf = FrameBuffer::<TopLeft>::new(7, 7);
+        super::fill_polygon(&mut buf, &arrow, WindingRule::EvenOdd, white);
+        assert_eq!(picture(&buf), vec![".......",
+                                       ".#.....",
+                                       "..##...",
+                                       "...###.",
+                                       "..##...",
+                                       ".#.....",
+                                       "......."]);
+    }
+
+    #[test]
+    fn test_polygons_with_non_finite_vertices_should_not_be_drawn() {
+        let white = Rgb::from_channels(255, 255, 255);
+        let nan = [Point2::new(0.0, 0.0), Point2::new(4.0, f64::NAN), Point2::new(0.0, 4.0)];
+        let infinite = [Point2::new(0.0, 0.0), Point2::new(f64::INFINITY, 0.0), Point2::new(0.0, 4.0)];
+        let huge = [Point2::new(0.0, 0.0), Point2::new(1.0e30, 0.0), Point2::new(0.0, 4.0)];
+
+        let mut buf = FrameBuffer::<TopLeft>::new(5, 5);
+        super::fill_polygon(&mut buf, &nan, WindingRule::EvenOdd, white);
+        super::fill_polygon(&mut buf, &infinite, WindingRule::NonZero, white);
+        super::stroke_polygon(&mut buf, &nan, white);
+        super::stroke_polygon(&mut buf, &infinite, white);
+        super::stroke_polygon(&mut buf, &huge, white);
+        assert_eq!(picture(&buf), vec!["....."; 5]);
+    }
+
+    #[test]
+    fn test_blits_should_be_clipped_to_both_frame_buffers() {
+        let white = Rgb::from_channels(255, 255, 255);
+        let mut src = FrameBuffer::<BottomLeft>::new(4, 4);
+        super::draw_line(&mut src, (0, 0), (3, 3), white);
+        let mut dst = FrameBuffer::<BottomLeft>::new(4, 3);
+
+        super::blit(&mut dst, (2, -1), &src, &Rect::new(-2, 0, 8, 8));
+        assert_eq!(picture(&dst), vec!["....", "....", "...."]);
+
+        super::blit(&mut dst, (1, -1), &src, &Rect::new(0, 0, 4, 4));
+        assert_eq!(picture(&dst), vec!["....", "...#", "..#."]);
+    }
+}
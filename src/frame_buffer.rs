@@ -1,12 +1,15 @@
-use color::Rgb;
+use color::{Rgb, Pixel, Channel};
+use raster::SampleCount;
 use std::ops;
+use std::mem;
 use std::marker::PhantomData;
+use std::slice;
 
 
 /// Return an initialized heap allocated frame buffer.
 pub fn frame_buffer(width: usize, height: usize) -> Box<FrameBuffer<TopLeft>> {
     let mut frame_buffer = Box::new(FrameBuffer::new(width, height));
-    (&mut (*frame_buffer)).initialize();
+    frame_buffer.initialize();
 
     frame_buffer
 }
@@ -19,49 +22,78 @@ pub fn frame_buffer(width: usize, height: usize) -> Box<FrameBuffer<TopLeft>> {
 /// buffer starts from the top left corner of the screen, the image will be
 /// drawn upside down. 
 ///
-pub trait Origin {}
+pub trait Origin {
+    /// The row in memory holding row `row` of an image `height` rows tall, where
+    /// rows are counted from the bottom of the image.
+    fn storage_row(row: usize, height: usize) -> usize;
+}
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum TopLeft {}
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum BottomLeft {}
 
-impl Origin for TopLeft {}
-impl Origin for BottomLeft {}
+impl Origin for TopLeft {
+    #[inline]
+    fn storage_row(row: usize, height: usize) -> usize {
+        height - row - 1
+    }
+}
+
+impl Origin for BottomLeft {
+    #[inline]
+    fn storage_row(row: usize, _height: usize) -> usize {
+        row
+    }
+}
 
+///
+/// A frame buffer stored in one contiguous allocation. Rows sit `stride` pixels
+/// apart in memory, starting from the top row when the origin is `TopLeft` and
+/// from the bottom row when it is `BottomLeft`. Indexing always counts rows from
+/// the bottom of the image, so `buf[i][j]` is the pixel in row `i` and column `j`
+/// whatever the origin. Pixels are stored in the format `P`, which defaults to 8
+/// bit RGB.
+///
 #[derive(PartialEq, Eq, Debug)]
-pub struct FrameBuffer<Or: Origin> {
+pub struct FrameBuffer<Or: Origin, P: Pixel = Rgb> {
     width: usize,
     height: usize,
-    buf: Vec<Vec<Rgb>>,
+    stride: usize,
+    buf: Vec<P>,
     _phantom: PhantomData<Or>,
 }
 
-impl<Or: Origin> FrameBuffer<Or> {
-    pub fn new(width: usize, height: usize) -> FrameBuffer<Or> {
-        let mut frame_buffer = FrameBuffer {
+impl<Or: Origin, P: Pixel> FrameBuffer<Or, P> {
+    pub fn new(width: usize, height: usize) -> FrameBuffer<Or, P> {
+        FrameBuffer::with_stride(width, height, width)
+    }
+
+    ///
+    /// Create a frame buffer whose rows sit `stride` pixels apart, which lets rows
+    /// be padded out to an alignment. The padding is never drawn to.
+    ///
+    pub fn with_stride(width: usize, height: usize, stride: usize) -> FrameBuffer<Or, P> {
+        assert!(stride >= width);
+
+        FrameBuffer {
             width: width,
             height: height,
-            buf: Vec::with_capacity(height),
+            stride: stride,
+            buf: vec![P::default(); stride * height],
             _phantom: PhantomData,
-        };
-
-        for _ in 0..frame_buffer.height {
-            frame_buffer.buf.push(Vec::with_capacity(width));
         }
-
-        frame_buffer
     }
 
+    /// Clear every pixel to the default pixel, which is black, and transparent in
+    /// formats with an alpha channel.
     pub fn initialize(&mut self) {
-        self.initialize_with(Rgb::from_channels(0,0,0));
+        self.initialize_with(P::default());
     }
 
-    pub fn initialize_with(&mut self, rgb: Rgb) {
-        for row in 0..self.height {
-            for _ in 0..self.width {
-                self.buf[row].push(rgb);
-            }
+    pub fn initialize_with(&mut self, value: P) {
+        for pixel in self.buf.iter_mut() {
+            *pixel = value;
         }
     }
 
@@ -76,142 +108,232 @@ impl<Or: Origin> FrameBuffer<Or> {
     pub fn shape(&self) -> (usize, usize) {
         (self.width, self.height)
     }
-}
 
-impl FrameBuffer<BottomLeft> {
-    pub fn dump_frame(&self, other_buf: &mut [u8]) -> Option<usize> {
-        if other_buf.len() >= 3 * self.height * self.width {
-            for i in 0..self.height {
-                for j in 0..self.width {
-                    other_buf[3*(self.width * i + j)]   = self.buf[i][j][0]; 
-                    other_buf[3*(self.width * i + j)+1] = self.buf[i][j][1];
-                    other_buf[3*(self.width * i + j)+2] = self.buf[i][j][2];
-                }
-            }
+    /// The distance in pixels between the starts of consecutive rows in memory.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
 
-            Some(3 * self.height * self.width)
-        } else {
-            None
-        }
+    #[inline]
+    fn offset(&self, i: usize) -> usize {
+        Or::storage_row(i, self.height) * self.stride
     }
 
-    pub fn lines(&self) -> RowIter<BottomLeft> {
+    /// Returns row `i`, counted from the bottom of the image.
+    #[inline]
+    pub fn row(&self, i: usize) -> &[P] {
+        assert!(i < self.height);
+        let start = self.offset(i);
+
+        &self.buf[start..(start + self.width)]
+    }
+
+    #[inline]
+    pub fn row_mut(&mut self, i: usize) -> &mut [P] {
+        assert!(i < self.height);
+        let start = self.offset(i);
+
+        &mut self.buf[start..(start + self.width)]
+    }
+
+    ///
+    /// Returns the pixel in row `i` and column `j` without bounds checks.
+    ///
+    /// # Safety
+    ///
+    /// `i` must be less than the height and `j` less than the width.
+    ///
+    #[inline]
+    pub unsafe fn get_unchecked(&self, i: usize, j: usize) -> &P {
+        self.buf.get_unchecked(self.offset(i) + j)
+    }
+
+    ///
+    /// Returns the pixel in row `i` and column `j` mutably without bounds checks.
+    ///
+    /// # Safety
+    ///
+    /// `i` must be less than the height and `j` less than the width.
+    ///
+    #[inline]
+    pub unsafe fn get_unchecked_mut(&mut self, i: usize, j: usize) -> &mut P {
+        let offset = self.offset(i);
+        self.buf.get_unchecked_mut(offset + j)
+    }
+
+    /// Returns every row, including any padding, in the order they sit in memory.
+    pub fn as_slice(&self) -> &[P] {
+        &self.buf
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [P] {
+        &mut self.buf
+    }
+
+    /// View the whole buffer as packed channels in memory order.
+    pub fn as_channels(&self) -> &[P::Channel] {
+        let len = P::channel_count() * self.buf.len();
+        // Pixels are C layout structs holding nothing but their channels.
+        unsafe { slice::from_raw_parts(self.buf.as_ptr() as *const P::Channel, len) }
+    }
+
+    ///
+    /// View the whole buffer as packed bytes in memory order. Channels wider than a
+    /// byte are in native byte order. When the stride equals the width, an 8 bit
+    /// RGB `TopLeft` buffer can be handed straight to a `NetPBMEncoder`. Use
+    /// `dump_frame` for other origins, which stores them upside down.
+    ///
+    pub fn as_bytes(&self) -> &[u8] {
+        let len = mem::size_of::<P>() * self.buf.len();
+        unsafe { slice::from_raw_parts(self.buf.as_ptr() as *const u8, len) }
+    }
+
+    /// Iterate over the rows of the image, starting from the bottom.
+    pub fn lines(&self) -> RowIter<'_, Or, P> {
         RowIter {
             index: 0,
-            rows: &self.buf,
-            _phantom: PhantomData,
+            frame_buffer: self,
         }
     }
-}
 
-impl FrameBuffer<TopLeft> {
+    ///
+    /// Copy the image into a buffer of packed bytes, leaving out any padding. Rows
+    /// are written from the top of the image down whatever the origin, which is
+    /// the order image formats like NetPBM expect. Returns the number of bytes
+    /// written, or `None` when the buffer is too small.
+    ///
     pub fn dump_frame(&self, other_buf: &mut [u8]) -> Option<usize> {
-        if other_buf.len() >= 3 * self.height * self.width {
-            for i in 0..self.height {
-                for j in 0..self.width {
-                    other_buf[3*(self.width * i + j)]   = self.buf[i][j][0]; 
-                    other_buf[3*(self.width * i + j)+1] = self.buf[i][j][1];
-                    other_buf[3*(self.width * i + j)+2] = self.buf[i][j][2];
-                }
-            }
+        let row_len = mem::size_of::<P>() * self.width;
+        let len = row_len * self.height;
+        if other_buf.len() < len {
+            return None;
+        }
+        if row_len == 0 {
+            return Some(0);
+        }
 
-            Some(3 * self.height * self.width)
-        } else {
-            None
+        for (i, dest) in other_buf[..len].chunks_mut(row_len).enumerate() {
+            let row = self.row(self.height - i - 1);
+            // Pixels are C layout structs holding nothing but their channels.
+            let bytes = unsafe { slice::from_raw_parts(row.as_ptr() as *const u8, row_len) };
+            dest.copy_from_slice(bytes);
         }
+
+        Some(len)
     }
 
-    pub fn lines(&self) -> RowIter<TopLeft> {
-        RowIter {
-            index: self.height,
-            rows: &self.buf,
-            _phantom: PhantomData,
+    ///
+    /// Copy the channels of the image into a buffer like `dump_frame` does, from
+    /// the top row down and without padding. Channels keep their own type, so a
+    /// 16 bit RGB image can be handed to `NetPBMEncoder::encode_rgb16`. Returns the
+    /// number of channels written, or `None` when the buffer is too small.
+    ///
+    pub fn dump_channels(&self, other_buf: &mut [P::Channel]) -> Option<usize> {
+        let row_len = P::channel_count() * self.width;
+        let len = row_len * self.height;
+        if other_buf.len() < len {
+            return None;
         }
-    }
-}
 
-pub struct RowIter<'a, Or: Origin> {
-    index: usize,
-    rows: &'a [Vec<Rgb>],
-    _phantom: PhantomData<Or>,
-}
+        for i in 0..self.height {
+            let row = self.row(self.height - i - 1);
+            for (dest, pixel) in other_buf[(i * row_len)..((i + 1) * row_len)].chunks_mut(P::channel_count()).zip(row) {
+                dest.copy_from_slice(pixel.channels());
+            }
+        }
 
-// Iteration for frame buffers whose origins starts from 
-// the bottom left.
-impl<'a> Iterator for RowIter<'a, BottomLeft> {
-    type Item = &'a [Rgb];
+        Some(len)
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.index += 1;
-        if self.index < self.rows.len() {
-            Some(&self.rows[self.index])
-        } else {
-            None
+    ///
+    /// Move the image into a frame buffer with another origin, reordering the rows
+    /// in memory so that the image itself is unchanged.
+    ///
+    pub fn into_origin<NewOr: Origin>(self) -> FrameBuffer<NewOr, P> {
+        let mut frame_buffer = FrameBuffer::with_stride(self.width, self.height, self.stride);
+        for i in 0..self.height {
+            frame_buffer.row_mut(i).copy_from_slice(self.row(i));
         }
-    }
-}
 
-impl ops::Index<usize> for FrameBuffer<BottomLeft> {
-    type Output = [Rgb];
+        frame_buffer
+    }
 
-    fn index(&self, index: usize) -> &Self::Output {
-        &self.buf[index]
-    } 
-}
+    /// Turn the image upside down in place.
+    pub fn flip_vertical(&mut self) {
+        // Reversing the order of the rows in memory flips the image whatever the origin.
+        let (width, stride) = (self.width, self.stride);
+        for i in 0..(self.height / 2) {
+            let (first, second) = self.buf.split_at_mut((self.height - i - 1) * stride);
+            first[(i * stride)..(i * stride + width)].swap_with_slice(&mut second[..width]);
+        }
+    }
 
-impl<'a> ops::Index<usize> for &'a FrameBuffer<BottomLeft> {
-    type Output = [Rgb];
+    /// Mirror the image from left to right in place.
+    pub fn flip_horizontal(&mut self) {
+        for i in 0..self.height {
+            self.row_mut(i).reverse();
+        }
+    }
 
-    fn index(&self, index: usize) -> &Self::Output {
-        &self.buf[index]
-    } 
+    ///
+    /// Convert every pixel into another format, keeping the origin and stride.
+    ///
+    pub fn convert<Q: Pixel>(&self) -> FrameBuffer<Or, Q> {
+        FrameBuffer {
+            width: self.width,
+            height: self.height,
+            stride: self.stride,
+            buf: self.buf.iter().map(|pixel| pixel.convert()).collect(),
+            _phantom: PhantomData,
+        }
+    }
 }
 
-impl ops::IndexMut<usize> for FrameBuffer<BottomLeft> {
-    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.buf[index]
-    }
+pub struct RowIter<'a, Or: 'a + Origin, P: 'a + Pixel = Rgb> {
+    index: usize,
+    frame_buffer: &'a FrameBuffer<Or, P>,
 }
 
-// Iteration for Frame Buffers whose origins start from the top
-// left corner of the screen.
-impl<'a> Iterator for RowIter<'a, TopLeft> {
-    type Item = &'a [Rgb];
+impl<'a, Or: Origin, P: Pixel> Iterator for RowIter<'a, Or, P> {
+    type Item = &'a [P];
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.index += 1;
-        if self.index < self.rows.len() {
-            Some(&self.rows[self.rows.len() - self.index - 1])
+        if self.index < self.frame_buffer.height {
+            self.index += 1;
+            Some(self.frame_buffer.row(self.index - 1))
         } else {
             None
         }
     }
 }
 
-impl ops::Index<usize> for FrameBuffer<TopLeft> {
-    type Output = [Rgb];
+impl<Or: Origin, P: Pixel> ops::Index<usize> for FrameBuffer<Or, P> {
+    type Output = [P];
 
+    #[inline]
     fn index(&self, index: usize) -> &Self::Output {
-        &self.buf[self.height - index - 1]
+        self.row(index)
     } 
 }
 
-impl<'a> ops::Index<usize> for &'a FrameBuffer<TopLeft> {
-    type Output = [Rgb];
+impl<Or: Origin, P: Pixel> ops::Index<usize> for &FrameBuffer<Or, P> {
+    type Output = [P];
 
+    #[inline]
     fn index(&self, index: usize) -> &Self::Output {
-        &self.buf[self.height - index - 1]
+        self.row(index)
     } 
 }
 
-impl ops::IndexMut<usize> for FrameBuffer<TopLeft> {
+impl<Or: Origin, P: Pixel> ops::IndexMut<usize> for FrameBuffer<Or, P> {
+    #[inline]
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.buf[self.height - index - 1]
+        self.row_mut(index)
     }
 }
 
-impl PartialEq<FrameBuffer<TopLeft>> for FrameBuffer<BottomLeft> {
-    fn eq(&self, other: &FrameBuffer<TopLeft>) -> bool {
+impl<P: Pixel> PartialEq<FrameBuffer<TopLeft, P>> for FrameBuffer<BottomLeft, P> {
+    fn eq(&self, other: &FrameBuffer<TopLeft, P>) -> bool {
         if self.shape() != other.shape() {
             return false; 
         }
@@ -226,8 +348,8 @@ impl PartialEq<FrameBuffer<TopLeft>> for FrameBuffer<BottomLeft> {
     }
 }
 
-impl PartialEq<FrameBuffer<BottomLeft>> for FrameBuffer<TopLeft> {
-    fn eq(&self, other: &FrameBuffer<BottomLeft>) -> bool {
+impl<P: Pixel> PartialEq<FrameBuffer<BottomLeft, P>> for FrameBuffer<TopLeft, P> {
+    fn eq(&self, other: &FrameBuffer<BottomLeft, P>) -> bool {
         if self.shape() != other.shape() {
             return false;
         }
@@ -242,8 +364,8 @@ impl PartialEq<FrameBuffer<BottomLeft>> for FrameBuffer<TopLeft> {
     }
 }
 
-impl<'a> PartialEq<&'a FrameBuffer<TopLeft>> for FrameBuffer<BottomLeft> {
-    fn eq(&self, other: &&'a FrameBuffer<TopLeft>) -> bool {
+impl<'a, P: Pixel> PartialEq<&'a FrameBuffer<TopLeft, P>> for FrameBuffer<BottomLeft, P> {
+    fn eq(&self, other: &&'a FrameBuffer<TopLeft, P>) -> bool {
         if self.shape() != other.shape() {
             return false;
         }
@@ -258,8 +380,8 @@ impl<'a> PartialEq<&'a FrameBuffer<TopLeft>> for FrameBuffer<BottomLeft> {
     }
 }
 
-impl<'a> PartialEq<&'a FrameBuffer<BottomLeft>> for FrameBuffer<TopLeft> {
-    fn eq(&self, other: &&'a FrameBuffer<BottomLeft>) -> bool {
+impl<'a, P: Pixel> PartialEq<&'a FrameBuffer<BottomLeft, P>> for FrameBuffer<TopLeft, P> {
+    fn eq(&self, other: &&'a FrameBuffer<BottomLeft, P>) -> bool {
         if self.shape() != other.shape() {
             return false;
         }
@@ -274,8 +396,8 @@ impl<'a> PartialEq<&'a FrameBuffer<BottomLeft>> for FrameBuffer<TopLeft> {
     }
 }
 
-impl<'a> PartialEq<FrameBuffer<TopLeft>> for &'a FrameBuffer<BottomLeft> {
-    fn eq(&self, other: &FrameBuffer<TopLeft>) -> bool {
+impl<P: Pixel> PartialEq<FrameBuffer<TopLeft, P>> for &FrameBuffer<BottomLeft, P> {
+    fn eq(&self, other: &FrameBuffer<TopLeft, P>) -> bool {
         if self.shape() != other.shape() {
             return false;
         }
@@ -290,8 +412,8 @@ impl<'a> PartialEq<FrameBuffer<TopLeft>> for &'a FrameBuffer<BottomLeft> {
     }
 }
 
-impl<'a> PartialEq<FrameBuffer<BottomLeft>> for &'a FrameBuffer<TopLeft> {
-    fn eq(&self, other: &FrameBuffer<BottomLeft>) -> bool {
+impl<P: Pixel> PartialEq<FrameBuffer<BottomLeft, P>> for &FrameBuffer<TopLeft, P> {
+    fn eq(&self, other: &FrameBuffer<BottomLeft, P>) -> bool {
         if self.shape() != other.shape() {
             return false;
         }
@@ -306,9 +428,117 @@ impl<'a> PartialEq<FrameBuffer<BottomLeft>> for &'a FrameBuffer<TopLeft> {
     }
 }
 
+///
+/// A frame buffer that stores several color samples for each pixel. Rows are
+/// indexed from the bottom of the image, the same way as a `FrameBuffer`, and each
+/// pixel holds one sample per position in `SampleCount::positions`.
+///
+#[derive(PartialEq, Eq, Debug)]
+pub struct MultisampleFrameBuffer<P: Pixel = Rgb> {
+    width: usize,
+    height: usize,
+    samples: SampleCount,
+    buf: Vec<P>,
+}
+
+impl<P: Pixel> MultisampleFrameBuffer<P> {
+    pub fn new(width: usize, height: usize, samples: SampleCount) -> MultisampleFrameBuffer<P> {
+        MultisampleFrameBuffer {
+            width: width,
+            height: height,
+            samples: samples,
+            buf: vec![P::default(); width * height * samples.count()],
+        }
+    }
+
+    pub fn initialize(&mut self) {
+        self.initialize_with(P::default());
+    }
+
+    pub fn initialize_with(&mut self, value: P) {
+        for sample in self.buf.iter_mut() {
+            *sample = value;
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn shape(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn samples(&self) -> SampleCount {
+        self.samples
+    }
+
+    /// Returns the samples of the pixel in row `i` and column `j`.
+    #[inline]
+    pub fn pixel(&self, i: usize, j: usize) -> &[P] {
+        let count = self.samples.count();
+        let start = (self.width * i + j) * count;
+
+        &self.buf[start..(start + count)]
+    }
+
+    #[inline]
+    pub fn pixel_mut(&mut self, i: usize, j: usize) -> &mut [P] {
+        let count = self.samples.count();
+        let start = (self.width * i + j) * count;
+
+        &mut self.buf[start..(start + count)]
+    }
+
+    ///
+    /// Average the samples of each pixel into a new frame buffer.
+    ///
+    pub fn resolve(&self) -> FrameBuffer<TopLeft, P> {
+        let mut frame_buffer = FrameBuffer::new(self.width, self.height);
+        frame_buffer.initialize();
+        self.resolve_into(&mut frame_buffer);
+
+        frame_buffer
+    }
+
+    ///
+    /// Average the samples of each pixel into an existing frame buffer with the
+    /// same dimensions.
+    ///
+    pub fn resolve_into<Or>(&self, frame_buffer: &mut FrameBuffer<Or, P>) where Or: Origin {
+        assert_eq!(self.shape(), frame_buffer.shape());
+
+        let count = self.samples.count() as f64;
+        let mut sum = vec![0.0; P::channel_count()];
+        for i in 0..self.height {
+            for j in 0..self.width {
+                for channel in sum.iter_mut() {
+                    *channel = 0.0;
+                }
+                for sample in self.pixel(i, j) {
+                    for (channel, value) in sum.iter_mut().zip(sample.channels()) {
+                        *channel += value.to_f64();
+                    }
+                }
+
+                // Round to the nearest value.
+                let pixel = &mut frame_buffer[i][j];
+                for (value, channel) in pixel.channels_mut().iter_mut().zip(&sum) {
+                    *value = P::Channel::from_f64(channel / count);
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use color::Rgb;
+    use raster::SampleCount;
 
 
     #[test]
@@ -376,10 +606,8 @@ mod tests {
             }
         }
 
-        for line in buf.lines() {
-            // No panics should occur.
-            assert!(true);
-        }
+        // No panics should occur.
+        for _line in buf.lines() {}
     }
 
     #[test]
@@ -389,7 +617,7 @@ mod tests {
 
         // Write a test pattern into the frame buffer that's just the 
         // row number repeated across the line.
-        let mut top_left = super::frame_buffer(width, height);
+        let top_left = super::frame_buffer(width, height);
         for (i, line) in top_left.lines().enumerate() {
             let new_rgb = Rgb::from_channels(i as u8, i as u8, i as u8);
             for ref mut old_rgb in line {
@@ -409,4 +637,202 @@ mod tests {
         // TopLeft and BottomLeft type should be equal with the rows flipped.
         assert_eq!(&*top_left, bottom_left);
     }
+
+    #[test]
+    fn test_resolving_a_multisample_frame_buffer_should_average_the_samples() {
+        let width  = 4;
+        let height = 3;
+        let mut buf = super::MultisampleFrameBuffer::new(width, height, SampleCount::X4);
+        buf.initialize_with(Rgb::from_channels(10, 20, 30));
+
+        // Paint half the samples of the bottom left pixel white.
+        buf.pixel_mut(0, 0)[1] = Rgb::from_channels(255, 255, 255);
+        buf.pixel_mut(0, 0)[3] = Rgb::from_channels(255, 255, 255);
+
+        let resolved = buf.resolve();
+
+        assert_eq!(resolved.shape(), (width, height));
+        assert_eq!(resolved[0][0], Rgb::from_channels(133, 138, 143));
+        for i in 0..height {
+            for j in 0..width {
+                if (i, j) != (0, 0) {
+                    assert_eq!(resolved[i][j], Rgb::from_channels(10, 20, 30));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_frame_buffer_rows_should_be_contiguous_in_memory_order() {
+        use super::{FrameBuffer, TopLeft, BottomLeft};
+
+        let mut top_left = FrameBuffer::<TopLeft>::new(2, 3);
+        let mut bottom_left = FrameBuffer::<BottomLeft>::new(2, 3);
+        for i in 0..3 {
+            let rgb = Rgb::from_channels(i as u8, 0, 0);
+            for pixel in top_left.row_mut(i).iter_mut().chain(bottom_left.row_mut(i).iter_mut()) {
+                *pixel = rgb;
+            }
+        }
+
+        // Top left buffers hold the top row first, which is row 2 counted from the bottom.
+        assert_eq!(top_left.as_bytes(), &[2, 0, 0, 2, 0, 0, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0][..]);
+        assert_eq!(bottom_left.as_bytes(), &[0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 0, 0, 2, 0, 0, 2, 0, 0][..]);
+        assert_eq!(unsafe { *top_left.get_unchecked(2, 1) }, Rgb::from_channels(2, 0, 0));
+        assert!(top_left == bottom_left);
+
+        let rows: Vec<u8> = top_left.lines().map(|row| row[0].r()).collect();
+        assert_eq!(rows, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_padded_frame_buffer_should_dump_only_the_image() {
+        use super::{FrameBuffer, BottomLeft};
+
+        let mut buf = FrameBuffer::<BottomLeft>::with_stride(2, 2, 3);
+        buf.initialize_with(Rgb::from_channels(9, 9, 9));
+        buf[0][1] = Rgb::from_channels(1, 2, 3);
+        buf[1][0] = Rgb::from_channels(4, 5, 6);
+
+        assert_eq!(buf.stride(), 3);
+        assert_eq!(buf.as_slice().len(), 6);
+        assert_eq!(buf.row(1).len(), 2);
+
+        let mut dump = vec![0; 12];
+        assert_eq!(buf.dump_frame(&mut dump), Some(12));
+        assert_eq!(dump, vec![4, 5, 6, 9, 9, 9, 9, 9, 9, 1, 2, 3]);
+        assert_eq!(buf.dump_frame(&mut [0; 11]), None);
+    }
+
+    #[test]
+    fn test_frame_buffers_should_convert_between_pixel_formats() {
+        use color::{Gray8, Rgb16, Rgba32F};
+        use super::{FrameBuffer, BottomLeft};
+
+        let mut buf = FrameBuffer::<BottomLeft>::with_stride(2, 1, 3);
+        buf[0][0] = Rgb::from_channels(255, 0, 0);
+        buf[0][1] = Rgb::from_channels(0, 0, 255);
+
+        let gray = buf.convert::<Gray8>();
+        assert_eq!(gray.stride(), 3);
+        assert_eq!(gray[0], [Gray8::from_channels(54), Gray8::from_channels(18)][..]);
+
+        let deep = buf.convert::<Rgb16>();
+        assert_eq!(&deep.as_channels()[..6], &[65535, 0, 0, 0, 0, 65535][..]);
+        assert_eq!(deep.convert::<Rgb>(), buf);
+
+        let float = buf.convert::<Rgba32F>();
+        assert_eq!(float[0][1], Rgba32F::from_channels(0.0, 0.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_dump_frame_should_write_every_channel_of_the_pixel_format() {
+        use color::{Gray8, Rgb32F};
+        use std::mem;
+        use super::{FrameBuffer, BottomLeft};
+
+        let mut gray = FrameBuffer::<BottomLeft, Gray8>::with_stride(2, 2, 4);
+        gray[0][1] = Gray8::from_channels(1);
+        gray[1][0] = Gray8::from_channels(2);
+        let mut dump = vec![0; 4];
+        assert_eq!(gray.dump_frame(&mut dump), Some(4));
+        assert_eq!(dump, vec![2, 0, 0, 1]);
+
+        let mut float = FrameBuffer::<BottomLeft, Rgb32F>::new(1, 1);
+        float[0][0] = Rgb32F::from_channels(1.5, 0.0, -2.0);
+        let mut dump = vec![0; 12];
+        assert_eq!(float.dump_frame(&mut dump), Some(12));
+        // Float channels are dumped in native byte order.
+        let expected: [u8; 12] = unsafe { mem::transmute([1.5f32, 0.0, -2.0]) };
+        assert_eq!(dump, expected.to_vec());
+    }
+
+    #[test]
+    fn test_16_bit_frame_buffers_should_encode_as_big_endian_pix_maps() {
+        use color::Rgb16;
+        use ppm::{NetPBM, NetPBMEncoder};
+        use super::{FrameBuffer, BottomLeft};
+
+        let mut frame_buffer = FrameBuffer::<BottomLeft, Rgb16>::with_stride(1, 2, 3);
+        frame_buffer[0][0] = Rgb16::from_channels(1, 2, 3);
+        frame_buffer[1][0] = Rgb16::from_channels(0x1234, 0, 0xFFFF);
+        let mut channels = vec![0; 6];
+        assert_eq!(frame_buffer.dump_channels(&mut channels), Some(6));
+        assert_eq!(channels, vec![0x1234, 0, 0xFFFF, 1, 2, 3]);
+        assert_eq!(frame_buffer.dump_channels(&mut [0; 5]), None);
+
+        let mut binary = Vec::new();
+        NetPBMEncoder::new(NetPBM::PixMapBinary, &mut binary).encode_rgb16(&channels, 1, 2).unwrap();
+        let mut expected = b"P6\n1 2\n65535\n".to_vec();
+        expected.extend_from_slice(&[0x12, 0x34, 0, 0, 0xFF, 0xFF, 0, 1, 0, 2, 0, 3]);
+        assert_eq!(binary, expected);
+
+        let mut ascii = Vec::new();
+        NetPBMEncoder::new(NetPBM::PixMapAscii, &mut ascii).encode_rgb16(&channels, 1, 2).unwrap();
+        assert_eq!(String::from_utf8(ascii).unwrap(), "P3\n1 2\n65535\n4660 0 65535 \n1 2 3 \n");
+
+        let mut gray_map = Vec::new();
+        assert!(NetPBMEncoder::new(NetPBM::GrayMapBinary, &mut gray_map).encode_rgb16(&channels, 1, 2).is_err());
+    }
+
+    #[test]
+    fn test_empty_frame_buffers_should_dump_nothing() {
+        use super::{FrameBuffer, BottomLeft};
+
+        let frame_buffer = FrameBuffer::<BottomLeft>::new(0, 3);
+        assert_eq!(frame_buffer.dump_frame(&mut []), Some(0));
+        assert_eq!(frame_buffer.dump_channels(&mut []), Some(0));
+    }
+
+    #[test]
+    fn test_changing_origin_should_keep_the_image_and_the_dump() {
+        use super::{FrameBuffer, TopLeft, BottomLeft};
+
+        let mut top_left = FrameBuffer::<TopLeft>::with_stride(2, 3, 4);
+        for i in 0..3 {
+            for j in 0..2 {
+                top_left[i][j] = Rgb::from_channels(i as u8, j as u8, 0);
+            }
+        }
+        let mut expected = vec![0; 18];
+        top_left.dump_frame(&mut expected);
+
+        let bottom_left: FrameBuffer<BottomLeft> = top_left.into_origin();
+        assert_eq!(bottom_left.stride(), 4);
+        assert_eq!(bottom_left[2][1], Rgb::from_channels(2, 1, 0));
+        assert_eq!(&bottom_left.as_slice()[..2], &[Rgb::from_channels(0, 0, 0), Rgb::from_channels(0, 1, 0)][..]);
+
+        let mut dump = vec![0; 18];
+        bottom_left.dump_frame(&mut dump);
+        assert_eq!(dump, expected);
+        assert_eq!(&dump[..6], &[2, 0, 0, 2, 1, 0][..]);
+    }
+
+    #[test]
+    fn test_flips_should_mirror_the_image_in_place() {
+        use super::{FrameBuffer, TopLeft, BottomLeft};
+
+        let mut top_left = FrameBuffer::<TopLeft>::with_stride(3, 3, 5);
+        let mut bottom_left = FrameBuffer::<BottomLeft>::new(3, 2);
+        for i in 0..3 {
+            for j in 0..3 {
+                top_left[i][j] = Rgb::from_channels(i as u8, j as u8, 0);
+                if i < 2 {
+                    bottom_left[i][j] = Rgb::from_channels(i as u8, j as u8, 0);
+                }
+            }
+        }
+
+        top_left.flip_vertical();
+        bottom_left.flip_vertical();
+        assert_eq!(top_left[0][2], Rgb::from_channels(2, 2, 0));
+        assert_eq!(top_left[1][0], Rgb::from_channels(1, 0, 0));
+        assert_eq!(top_left[2][1], Rgb::from_channels(0, 1, 0));
+        assert_eq!(bottom_left[0][1], Rgb::from_channels(1, 1, 0));
+        assert_eq!(bottom_left[1][1], Rgb::from_channels(0, 1, 0));
+
+        top_left.flip_horizontal();
+        assert_eq!(top_left[0][0], Rgb::from_channels(2, 2, 0));
+        assert_eq!(top_left[2][2], Rgb::from_channels(0, 0, 0));
+    }
 }
@@ -1,20 +1,36 @@
 #![feature(fn_traits)]
 #![feature(unboxed_closures)]
 #![crate_name="graphics"]
+// Struct literals spell out `field: field`, the raster code names its constants
+// `_0` and `_1`, the rasterizer threads its state through long argument lists,
+// and the camera model lives in `camera::camera`.
+#![allow(clippy::redundant_field_names)]
+#![allow(clippy::just_underscores_and_digits)]
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::module_inception)]
 extern crate nalgebra;
 extern crate num_traits;
 extern crate num_integer;
 extern crate alga;
-#[macro_use]
+#[cfg_attr(test, macro_use)]
 extern crate approx;
 
 pub mod vertex;
 pub mod raster;
 pub mod z_buffer;
+pub mod stencil_buffer;
+pub mod blend;
 pub mod frame_buffer;
+pub mod draw;
+pub mod text;
+pub mod resample;
+pub mod compare;
 pub mod camera;
 mod util;
 pub mod shader;
 pub mod color;
 pub mod shape;
-pub mod ppm;
\ No newline at end of file
+pub mod ppm;
+pub mod mesh;
+pub mod clip;
+pub mod renderer;
\ No newline at end of file
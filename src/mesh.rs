@@ -1,54 +1,43 @@
 use nalgebra::{Point3};
 use num_traits::Float;
-use alga::general::Real;
+use alga::general::RealField;
 use std::marker::PhantomData;
 use std::ops;
 use std::convert::AsRef;
+use std::collections::HashSet;
 
 
 type VertexIdx = usize;
 type Vertex<N> = Point3<N>;
 
-struct VertexMap<N> where N: Float + Real {
-    data: Box<Vec<Vertex<N>>>,
+struct VertexMap<N> where N: Float + RealField {
+    data: Vec<Vertex<N>>,
 }
 
-impl<N> VertexMap<N> where N: Float + Real {
+impl<N> VertexMap<N> where N: Float + RealField {
     fn with_capacity(n_verts: usize) -> VertexMap<N> {
-        let data = Box::new(Vec::with_capacity(n_verts));
+        let data = Vec::with_capacity(n_verts);
         VertexMap {
             data: data,
         }
     }
 
-    fn is_empty(&self) -> bool {
-        self.data.is_empty()
-    }
-
     fn push(&mut self, vertex: Vertex<N>) {
         self.data.push(vertex);
     }
 
-    fn lookup(&self, index: VertexIdx) -> Option<&Vertex<N>> {
-        self.data.get(index)
-    }
-
     #[inline]
     fn len(&self) -> usize {
         self.data.len()
     }
 
     #[inline]
-    fn capacity(&self) -> usize {
-        self.data.capacity()
-    }
-
     fn as_slice(&self) -> &[Vertex<N>] {
         self.data.as_slice()
     }
 }
 
-impl<N> ops::Index<VertexIdx> for VertexMap<N> where N: Float + Real {
+impl<N> ops::Index<VertexIdx> for VertexMap<N> where N: Float + RealField {
     type Output = Vertex<N>;
 
     fn index(&self, _index: VertexIdx) -> &Self::Output {
@@ -56,7 +45,7 @@ impl<N> ops::Index<VertexIdx> for VertexMap<N> where N: Float + Real {
     }
 }
 
-impl<N> AsRef<[Vertex<N>]> for VertexMap<N> where N: Float + Real {
+impl<N> AsRef<[Vertex<N>]> for VertexMap<N> where N: Float + RealField {
     fn as_ref(&self) -> &[Vertex<N>] {
         self.data.as_ref()
     }
@@ -85,9 +74,13 @@ impl<N> Face<N> {
     pub fn len(&self) -> usize {
         3
     }
+
+    pub fn is_empty(&self) -> bool {
+        false
+    }
 }
 
-impl<N> ops::Index<usize> for Face<N> where N: Float + Real {
+impl<N> ops::Index<usize> for Face<N> where N: Float + RealField {
     type Output = usize;
 
     fn index(&self, _index: usize) -> &Self::Output {
@@ -104,42 +97,38 @@ impl<N> ops::Index<usize> for Face<N> where N: Float + Real {
 
 type FaceIdx = usize;
 
+/// An edge between two vertices of a mesh, along with the first face that uses it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Edge {
+    pub v0: VertexIdx,
+    pub v1: VertexIdx,
+    pub face: FaceIdx,
+}
+
 struct FaceMap<N> {
-    data: Box<Vec<Face<N>>>,
+    data: Vec<Face<N>>,
     _phantom: PhantomData<N>,
 }
 
-impl<N> FaceMap<N> where N: Float + Real {
+impl<N> FaceMap<N> where N: Float + RealField {
     fn with_capacity(n_faces: usize) -> FaceMap<N> {
-        let data = Box::new(Vec::with_capacity(n_faces));
+        let data = Vec::with_capacity(n_faces);
         FaceMap {
             data: data,
             _phantom: PhantomData,
         }
     }
 
-    fn is_empty(&self) -> bool {
-        self.data.is_empty()
-    }
-
     fn push(&mut self, face: &Face<N>) {
         self.data.push(*face);
     }
 
-    fn lookup(&self, index: FaceIdx) -> Option<&Face<N>> {
-        self.data.get(index)
-    }
-
     #[inline]
     fn len(&self) -> usize {
         self.data.len()
     }
 
     #[inline]
-    fn capacity(&self) -> usize {
-        self.data.capacity()
-    }
-
     fn as_slice(&self) -> &[Face<N>] {
         self.data.as_slice()
     }
@@ -163,12 +152,12 @@ impl<N> AsRef<[Face<N>]> for FaceMap<N> {
 /// changing much, we would have to switch to something like a wing-edge
 /// graph to represent the edges and faces of the simplices to make them
 /// easy to manipulate dynamically, i.e. creating and deleting lots of triangles.
-pub struct Mesh<N> where N: Float + Real {
+pub struct Mesh<N> where N: Float + RealField {
     vertex_table: VertexMap<N>,
     face_table: FaceMap<N>,
 }
 
-impl<N> Mesh<N> where N: Float + Real {
+impl<N> Mesh<N> where N: Float + RealField {
     /// Create a mesh with at most `n_verts` vertices, and `n_faces` faces.
     pub fn with_dims(n_verts: usize, n_faces: usize) -> Mesh<N> {
         Mesh {
@@ -202,10 +191,51 @@ impl<N> Mesh<N> where N: Float + Real {
     pub fn faces(&self) -> &[Face<N>] {
         self.face_table.as_slice()
     }
+
+    ///
+    /// Collect the edges of the faces in the mesh. An edge shared by several faces
+    /// is listed once, in the direction and with the face it was first seen in.
+    ///
+    pub fn edges(&self) -> Vec<Edge> {
+        let mut seen = HashSet::new();
+        let mut edges = Vec::new();
+
+        for (face_idx, face) in self.faces().iter().enumerate() {
+            for &(v0, v1) in [(face.v0, face.v1), (face.v1, face.v2), (face.v2, face.v0)].iter() {
+                let key = if v0 < v1 { (v0, v1) } else { (v1, v0) };
+                if seen.insert(key) {
+                    edges.push(Edge { v0: v0, v1: v1, face: face_idx });
+                }
+            }
+        }
+
+        edges
+    }
 }
 
 
 #[cfg(test)]
 mod tests {
+    use nalgebra::Point3;
+    use super::{Edge, Face, Mesh};
+
 
+    #[test]
+    fn test_mesh_edges_should_list_shared_edges_once() {
+        let mut mesh = Mesh::with_dims(4, 2);
+        mesh.push_vertex(Point3::new(0.0, 0.0, 0.0));
+        mesh.push_vertex(Point3::new(0.0, 1.0, 0.0));
+        mesh.push_vertex(Point3::new(1.0, 1.0, 0.0));
+        mesh.push_vertex(Point3::new(1.0, 0.0, 0.0));
+        mesh.push_face(&Face::new(0, 1, 3));
+        mesh.push_face(&Face::new(3, 1, 2));
+
+        let edges = mesh.edges();
+
+        assert_eq!(edges, vec![Edge { v0: 0, v1: 1, face: 0 },
+                               Edge { v0: 1, v1: 3, face: 0 },
+                               Edge { v0: 3, v1: 0, face: 0 },
+                               Edge { v0: 1, v1: 2, face: 1 },
+                               Edge { v0: 2, v1: 3, face: 1 }]);
+    }
 }
@@ -25,6 +25,13 @@ impl NetPBM {
         }
     }
 
+    fn is_binary(&self) -> bool {
+        match *self {
+            NetPBM::BitMapBinary | NetPBM::GrayMapBinary | NetPBM::PixMapBinary => true,
+            NetPBM::BitMapAscii  | NetPBM::GrayMapAscii  | NetPBM::PixMapAscii  => false,
+        }
+    }
+
     fn file_extension(&self) -> &str {
         match *self {
             NetPBM::BitMapAscii  | NetPBM::BitMapBinary  => "pbm",
@@ -34,12 +41,14 @@ impl NetPBM {
     }
 }
 
-// We only support an 8-bit color depth here.
+// We support an 8-bit color depth, and a 16-bit depth for gray and pix maps.
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 enum ColorType {
     BitMap,
     Gray,
+    Gray16,
     Rgb,
+    Rgb16,
 }
 
 impl ColorType {
@@ -47,6 +56,7 @@ impl ColorType {
         match *self {
             ColorType::BitMap => 1,
             ColorType::Gray | ColorType::Rgb => 255,
+            ColorType::Gray16 | ColorType::Rgb16 => 65535,
         }
     }
 }
@@ -57,7 +67,7 @@ pub struct NetPBMEncoder<'a, W: 'a> {
 }
 
 impl<'a, W> NetPBMEncoder<'a, W> where W: 'a + io::Write {
-    pub fn new(enc_type: NetPBM, writer: &mut W) -> NetPBMEncoder<W> {
+    pub fn new(enc_type: NetPBM, writer: &'a mut W) -> NetPBMEncoder<'a, W> {
         NetPBMEncoder {
             enc_type: enc_type,
             writer: writer,
@@ -78,20 +88,98 @@ impl<'a, W> NetPBMEncoder<'a, W> where W: 'a + io::Write {
         self.__encode(image, width, height, pixel_type)
     }
 
+    ///
+    /// Encode a gray map with 16 bits per pixel. Binary gray maps store each pixel
+    /// as two bytes, most significant byte first. This fails for anything other
+    /// than a gray map.
+    ///
+    pub fn encode16(&mut self,
+                    image: &[u16],
+                    width: u32,
+                    height: u32) -> io::Result<()>
+    {
+        match self.enc_type {
+            NetPBM::GrayMapAscii | NetPBM::GrayMapBinary => {}
+            _ => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          "Only gray maps support 16-bit pixels."));
+            }
+        }
+        if image.is_empty() {
+            return Err(empty_image());
+        }
+
+        self.write_magic_number()?;
+        self.write_header(width, height, ColorType::Gray16)?;
+
+        self.write_image16(image, width as usize)
+    }
+
+    ///
+    /// Encode a pix map with 16 bits per channel, from three channels per pixel.
+    /// Binary pix maps store each channel as two bytes, most significant byte
+    /// first. This fails for anything other than a pix map.
+    ///
+    pub fn encode_rgb16(&mut self,
+                        image: &[u16],
+                        width: u32,
+                        height: u32) -> io::Result<()>
+    {
+        match self.enc_type {
+            NetPBM::PixMapAscii | NetPBM::PixMapBinary => {}
+            _ => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          "Only pix maps support 16-bit RGB pixels."));
+            }
+        }
+        if image.is_empty() {
+            return Err(empty_image());
+        }
+
+        self.write_magic_number()?;
+        self.write_header(width, height, ColorType::Rgb16)?;
+
+        self.write_image16(image, 3 * width as usize)
+    }
+
+    fn write_image16(&mut self, image: &[u16], samples_per_line: usize) -> io::Result<()> {
+        if self.enc_type.is_binary() {
+            let mut bytes = Vec::with_capacity(2 * image.len());
+            for sample in image {
+                bytes.push((*sample >> 8) as u8);
+                bytes.push(*sample as u8);
+            }
+            self.writer.write_all(&bytes)?;
+        } else {
+            for line in image.chunks(samples_per_line) {
+                for sample in line {
+                    write!(self.writer, "{} ", sample)?;
+                }
+                self.writer.write_all("\n".as_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn __encode(&mut self,
                 image: &[u8],
                 width: u32,
                 height: u32,
                 pixel_type: ColorType) -> io::Result<()>
     {
-        try!(self.write_magic_number());
-        try!(self.write_header(width, height, pixel_type));
+        if image.is_empty() {
+            return Err(empty_image());
+        }
+
+        self.write_magic_number()?;
+        self.write_header(width, height, pixel_type)?;
 
         self.write_image(image, width, height, pixel_type)
     }
 
     fn write_magic_number(&mut self) -> io::Result<()> {
-        write!(self.writer, "{}\n", self.enc_type.magic_number())
+        writeln!(self.writer, "{}", self.enc_type.magic_number())
     }
 
     fn write_header(&mut self, width: u32, height: u32, pixel_type: ColorType) -> io::Result<()> {
@@ -105,38 +193,199 @@ impl<'a, W> NetPBMEncoder<'a, W> where W: 'a + io::Write {
                    height: u32,
                    pixel_type: ColorType) -> io::Result<()> 
     {
-        assert!(image.len() > 0);
         match pixel_type {
             ColorType::BitMap => {
                 for line in image.chunks(width as usize) {
                     for pixel in line {
                         if *pixel == 0 {
-                            try!(write!(self.writer, "0 "));
+                            write!(self.writer, "0 ")?;
                         } else {
-                            try!(write!(self.writer, "1 "));
+                            write!(self.writer, "1 ")?;
                         }
                     }
-                    try!(write!(self.writer, "\n"));
+                    writeln!(self.writer)?;
                 }
             }
+            ColorType::Gray if self.enc_type.is_binary() => {
+                let len = (width as usize) * (height as usize);
+                self.writer.write_all(&image[..len])?;
+            }
             ColorType::Gray => {
                 for i in 0..height as usize {
                     for j in 0..width as usize {
-                        try!(write!(self.writer, "{} ", image[(width as usize) * i + j]));
+                        write!(self.writer, "{} ", image[(width as usize) * i + j])?;
                     }
-                    try!(self.writer.write_all("\n".as_bytes()));
+                    self.writer.write_all("\n".as_bytes())?;
                 }
             }
             ColorType::Rgb => {
                 for line in image.chunks(3 * width as usize) {
                     for pixel in line.chunks(3) {
-                        try!(write!(self.writer, "{} {} {} ", pixel[0], pixel[1], pixel[2]));
+                        write!(self.writer, "{} {} {} ", pixel[0], pixel[1], pixel[2])?;
                     }
-                    try!(write!(self.writer, "\n"));
+                    writeln!(self.writer)?;
                 }
             }
+            ColorType::Gray16 | ColorType::Rgb16 => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          "16-bit pixels must be written with write_image16."));
+            }
         }
 
         Ok(())
     }
 }
+
+pub struct NetPBMDecoder<'a, R: 'a> {
+    reader: &'a mut R,
+}
+
+impl<'a, R> NetPBMDecoder<'a, R> where R: 'a + io::Read {
+    pub fn new(reader: &'a mut R) -> NetPBMDecoder<'a, R> {
+        NetPBMDecoder {
+            reader: reader,
+        }
+    }
+
+    ///
+    /// Decode an ASCII or binary bit map. Returns its pixels from the top row down,
+    /// one byte each, with 1 for black and 0 for white, along with its width and
+    /// height. This is the layout `encode` takes for a bit map.
+    ///
+    pub fn decode_bitmap(&mut self) -> io::Result<(Vec<u8>, u32, u32)> {
+        let mut bytes = Vec::new();
+        self.reader.read_to_end(&mut bytes)?;
+
+        let mut position = 0;
+        let binary = match next_token(&bytes, &mut position)? {
+            b"P1" => false,
+            b"P4" => true,
+            _ => return Err(invalid_data("Only bit maps can be decoded.")),
+        };
+        let width = parse_dimension(next_token(&bytes, &mut position)?)?;
+        let height = parse_dimension(next_token(&bytes, &mut position)?)?;
+
+        let (w, h) = (width as usize, height as usize);
+        let pixels = match w.checked_mul(h) {
+            Some(pixels) => pixels,
+            None => return Err(invalid_data("The bit map is too large.")),
+        };
+        if pixels == 0 {
+            return Ok((Vec::new(), width, height));
+        }
+
+        let mut image = Vec::new();
+        if binary {
+            // A single whitespace character separates the header from the rows,
+            // and each row is padded out to a whole byte.
+            position += 1;
+            let row_bytes = (w + 7) / 8;
+            let end = match row_bytes.checked_mul(h).and_then(|n| n.checked_add(position)) {
+                Some(end) if end <= bytes.len() => end,
+                _ => return Err(invalid_data("The bit map ends before its last row.")),
+            };
+            image.reserve(pixels);
+            for row in bytes[position..end].chunks(row_bytes) {
+                for j in 0..w {
+                    image.push((row[j / 8] >> (7 - j % 8)) & 1);
+                }
+            }
+        } else {
+            // Pixels need not be separated by whitespace.
+            while image.len() < pixels {
+                skip_whitespace(&bytes, &mut position);
+                match bytes.get(position) {
+                    Some(&b'0') => image.push(0),
+                    Some(&b'1') => image.push(1),
+                    Some(_) => return Err(invalid_data("Bit map pixels must be 0 or 1.")),
+                    None => return Err(invalid_data("The bit map ends before its last pixel.")),
+                }
+                position += 1;
+            }
+        }
+
+        Ok((image, width, height))
+    }
+}
+
+fn empty_image() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "An image needs at least one pixel.")
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Skip whitespace and comments, which run from a `#` to the end of the line.
+fn skip_whitespace(bytes: &[u8], position: &mut usize) {
+    while *position < bytes.len() {
+        match bytes[*position] {
+            b'#' => {
+                while (*position < bytes.len()) && (bytes[*position] != b'\n') {
+                    *position += 1;
+                }
+            }
+            b' ' | b'\t' | b'\n' | b'\r' => *position += 1,
+            _ => break,
+        }
+    }
+}
+
+fn next_token<'b>(bytes: &'b [u8], position: &mut usize) -> io::Result<&'b [u8]> {
+    skip_whitespace(bytes, position);
+    let start = *position;
+    while (*position < bytes.len()) && !(bytes[*position] as char).is_whitespace() {
+        *position += 1;
+    }
+
+    if start == *position {
+        Err(invalid_data("The header ends early."))
+    } else {
+        Ok(&bytes[start..*position])
+    }
+}
+
+fn parse_dimension(token: &[u8]) -> io::Result<u32> {
+    match ::std::str::from_utf8(token).ok().and_then(|s| s.parse().ok()) {
+        Some(value) => Ok(value),
+        None => Err(invalid_data("The header has an invalid width or height.")),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use super::{NetPBM, NetPBMEncoder, NetPBMDecoder};
+
+
+    #[test]
+    fn test_empty_images_should_fail_to_encode_and_write_nothing() {
+        let mut file = Vec::new();
+        let result = NetPBMEncoder::new(NetPBM::PixMapBinary, &mut file).encode(&[], 0, 0);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(NetPBMEncoder::new(NetPBM::GrayMapAscii, &mut file).encode16(&[], 0, 0).is_err());
+        assert!(NetPBMEncoder::new(NetPBM::PixMapAscii, &mut file).encode_rgb16(&[], 0, 0).is_err());
+        assert!(file.is_empty());
+    }
+
+    #[test]
+    fn test_empty_bit_maps_should_decode_to_no_pixels() {
+        let bitmap = b"P4 0 3\n";
+        let result = NetPBMDecoder::new(&mut &bitmap[..]).decode_bitmap().unwrap();
+        assert_eq!(result, (vec![], 0, 3));
+
+        let bitmap = b"P1 2 0\n";
+        let result = NetPBMDecoder::new(&mut &bitmap[..]).decode_bitmap().unwrap();
+        assert_eq!(result, (vec![], 2, 0));
+    }
+
+    #[test]
+    fn test_bit_maps_larger_than_their_data_should_fail_to_decode() {
+        let bitmap = b"P4 1 4294967295\n\0";
+        assert!(NetPBMDecoder::new(&mut &bitmap[..]).decode_bitmap().is_err());
+
+        let bitmap = b"P4 4294967295 4294967295\n\0";
+        assert!(NetPBMDecoder::new(&mut &bitmap[..]).decode_bitmap().is_err());
+    }
+}
@@ -1,20 +1,19 @@
 #![allow(dead_code)]
-use nalgebra;
 use nalgebra::{Vector3, Point3, Matrix4};
 use num_traits::Float;
-use alga::general::Real;
+use alga::general::RealField;
 use util;
 
 
-/// TODO: Check divisions for 0's in matrix code.
-/// TODO: Convert all raster functions to homogeneous 4D coordinates.
-/// TODO: Make interface to raster functions consistent (with pointers to data types instead of copying them.)
+// TODO: Check divisions for 0's in matrix code.
+// TODO: Convert all raster functions to homogeneous 4D coordinates.
+// TODO: Make interface to raster functions consistent (with pointers to data types instead of copying them.)
 
 ///
 /// Generate the world transformation from the given camera data.
 ///
 pub fn camera_to_world_matrix<N>(eye: Vector3<N>, gaze: Vector3<N>, top: Vector3<N>) -> Matrix4<N>
-    where N: Float + Real
+    where N: Float + RealField
 {
     // The vectors are all cast into homogeneous coordinates here. Points are affected
     // by translation, so `eye` has a `1` in its fourth comp_1nt, while vectors are
@@ -57,7 +56,7 @@ pub fn camera_to_world_matrix<N>(eye: Vector3<N>, gaze: Vector3<N>, top: Vector3
 /// Generates the world to camera matrix.
 ///
 pub fn world_to_camera_matrix<N>(eye: Vector3<N>, gaze: Vector3<N>, top: Vector3<N>) -> Matrix4<N>
-    where N: Float + Real
+    where N: Float + RealField
 {
         let _0 = N::zero();
         let _1 = N::one();
@@ -85,7 +84,7 @@ pub fn world_to_camera_matrix<N>(eye: Vector3<N>, gaze: Vector3<N>, top: Vector3
 /// transformations. This is for looking down the negative z-axis.
 ///
 pub fn perspective_matrix<N>(near: N, far: N) -> Matrix4<N>
-    where N: Float + Real
+    where N: Float + RealField
 {
     assert!(near > far);
 
@@ -119,7 +118,7 @@ pub fn perspective_matrix<N>(near: N, far: N) -> Matrix4<N>
 /// Constructs a translation matrix from a three-dimensional vector. 
 ///
 pub fn translation_matrix<N>(eye: Vector3<N>) -> Matrix4<N>
-    where N: Float + Real
+    where N: Float + RealField
 {
     let _0 = N::zero();
     let _1 = N::one();
@@ -151,7 +150,7 @@ pub fn translation_matrix<N>(eye: Vector3<N>) -> Matrix4<N>
 /// Constructs a rotation matrix from a set of coordinate axes.
 ///
 pub fn rotation_matrix<N>(gaze: Vector3<N>, top: Vector3<N>) -> Matrix4<N>
-    where N: Float + Real
+    where N: Float + RealField
 {
     let _0 = N::zero();
     let _1 = N::one();
@@ -195,7 +194,7 @@ pub fn orthographic_matrix<N>(left: N,
                               bottom: N, 
                               near: N, 
                               far: N) -> Matrix4<N> 
-    where N: Float + Real
+    where N: Float + RealField
 {
     assert!(near > far);
 
@@ -236,7 +235,81 @@ pub fn perspective_projection_matrix<N>(left: N,
                                         bottom: N, 
                                         near: N, 
                                         far: N) -> Matrix4<N> 
-    where N: Float + Real
+    where N: Float + RealField
+{
+    let _1 = N::one();
+    let _2 = _1 + _1;
+
+    let m33 = (far + near) / (near - far);
+    let m43 = (_2 * far * near) / (far - near);
+
+    frustum_matrix(left, right, top, bottom, near, m33, m43)
+}
+
+///
+/// Perspective projection transformation for reversed-Z. This maps the near plane
+/// to the far side of the canonical view volume and the far plane to the near side,
+/// so that depths run from 1 at the near plane to 0 at the far plane. Floating
+/// point depth buffers have most of their precision near 0, which reversed-Z spends
+/// on distant geometry. Draw with `DepthConvention::Reversed`.
+///
+pub fn reversed_perspective_projection_matrix<N>(left: N,
+                                                 right: N,
+                                                 top: N,
+                                                 bottom: N,
+                                                 near: N,
+                                                 far: N) -> Matrix4<N>
+    where N: Float + RealField
+{
+    let _1 = N::one();
+    let _2 = _1 + _1;
+
+    let m33 = (far + near) / (far - near);
+    let m43 = (_2 * far * near) / (near - far);
+
+    frustum_matrix(left, right, top, bottom, near, m33, m43)
+}
+
+///
+/// Perspective projection transformation with the far plane at infinity. This is
+/// the limit of `perspective_projection_matrix` as `far` goes to negative infinity.
+///
+pub fn infinite_perspective_projection_matrix<N>(left: N,
+                                                 right: N,
+                                                 top: N,
+                                                 bottom: N,
+                                                 near: N) -> Matrix4<N>
+    where N: Float + RealField
+{
+    let _1 = N::one();
+    let _2 = _1 + _1;
+
+    frustum_matrix(left, right, top, bottom, near, -_1, _2 * near)
+}
+
+///
+/// Perspective projection transformation for reversed-Z with the far plane at
+/// infinity. The depth of a point at camera space depth `z` is `near / z`.
+///
+pub fn reversed_infinite_perspective_projection_matrix<N>(left: N,
+                                                          right: N,
+                                                          top: N,
+                                                          bottom: N,
+                                                          near: N) -> Matrix4<N>
+    where N: Float + RealField
+{
+    let _1 = N::one();
+    let _2 = _1 + _1;
+
+    frustum_matrix(left, right, top, bottom, near, _1, -_2 * near)
+}
+
+///
+/// Assemble a perspective projection from the frustum at the near plane and the
+/// coefficients that map camera space depth to clip space depth.
+///
+fn frustum_matrix<N>(left: N, right: N, top: N, bottom: N, near: N, m33: N, m43: N) -> Matrix4<N>
+    where N: Float + RealField
 {
     let _0 = N::zero();
     let _1 = N::one();
@@ -252,8 +325,6 @@ pub fn perspective_projection_matrix<N>(left: N,
     let m42 = _0;
     let m13 = _0;
     let m23 = _0;
-    let m33 = (far + near) / (near - far);
-    let m43 = (_2 * far * near) / (far - near);
     let m14 = _0;
     let m24 = _0;
     let m34 = _1;
@@ -277,7 +348,18 @@ pub fn perspective_projection_matrix<N>(left: N,
 /// wide in this coordinate system.
 ///
 pub fn viewport_matrix<N>(num_x: usize, num_y: usize) -> Matrix4<N>
-    where N: Float + Real
+    where N: Float + RealField
+{
+    offset_viewport_matrix(0, 0, num_x, num_y)
+}
+
+///
+/// Compute the viewport transformation onto a block of `num_x` by `num_y` pixels
+/// whose lower left pixel is in column `x` and row `y` of the image. This is
+/// `viewport_matrix` shifted by `(x, y)`.
+///
+pub fn offset_viewport_matrix<N>(x: usize, y: usize, num_x: usize, num_y: usize) -> Matrix4<N>
+    where N: Float + RealField
 {
     let _0 = N::zero();
     let _1  = N::one();
@@ -298,11 +380,11 @@ pub fn viewport_matrix<N>(num_x: usize, num_y: usize) -> Matrix4<N>
     let m11 = image_width / _2;
     let m21 = _0;
     let m31 = _0;
-    let m41 = (image_width - _1) / _2;
+    let m41 = util::cast::<N>(x) + (image_width - _1) / _2;
     let m12 = _0;
     let m22 = image_height / _2;
     let m32 = _0;
-    let m42 = (image_height - _1) / _2;
+    let m42 = util::cast::<N>(y) + (image_height - _1) / _2;
     let m13 = _0;
     let m23 = _0;
     let m33 = _1;
@@ -327,7 +409,7 @@ pub fn world_to_raster_matrix<N>(left: N,
                                  far: N, 
                                  image_width: usize, 
                                  image_height: usize) -> Matrix4<N> 
-    where N: Float + Real
+    where N: Float + RealField
 {
     let pp_matrix: Matrix4<N> = perspective_projection_matrix(left, right, top, bottom, near, far);
     let vp_matrix: Matrix4<N> = viewport_matrix(image_width, image_height);
@@ -335,6 +417,7 @@ pub fn world_to_raster_matrix<N>(left: N,
     vp_matrix * pp_matrix
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct BoundingBox<N> {
     pub x_min: N,
     pub x_max: N,
@@ -342,18 +425,79 @@ pub struct BoundingBox<N> {
     pub y_max: N
 }
 
+///
+/// The block of the image that a scene is drawn into, along with the range of
+/// depths that the near and far planes map to. The block starts at the pixel in
+/// column `x` and row `y`, with rows counted from the bottom of the image.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Viewport<N> {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub depth_range: (N, N),
+}
+
+impl<N> Viewport<N> where N: Float + RealField {
+    /// A viewport over a block of pixels with the full depth range.
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Viewport<N> {
+        Viewport {
+            x: x,
+            y: y,
+            width: width,
+            height: height,
+            depth_range: (N::zero(), N::one()),
+        }
+    }
+
+    /// The half open ranges of pixels covered by the viewport.
+    pub fn bounds(&self) -> BoundingBox<usize> {
+        BoundingBox {
+            x_min: self.x,
+            x_max: self.x + self.width,
+            y_min: self.y,
+            y_max: self.y + self.height,
+        }
+    }
+
+    /// The transformation from the canonical view volume onto the viewport.
+    pub fn matrix(&self) -> Matrix4<N> {
+        offset_viewport_matrix(self.x, self.y, self.width, self.height)
+    }
+}
+
+///
+/// Intersect two boxes holding half open ranges of pixels. This returns `None`
+/// when they share no pixels.
+///
+pub fn intersect_bounds(a: &BoundingBox<usize>, b: &BoundingBox<usize>) -> Option<BoundingBox<usize>> {
+    let bounds = BoundingBox {
+        x_min: Ord::max(a.x_min, b.x_min),
+        x_max: Ord::min(a.x_max, b.x_max),
+        y_min: Ord::max(a.y_min, b.y_min),
+        y_max: Ord::min(a.y_max, b.y_max),
+    };
+
+    if (bounds.x_min < bounds.x_max) && (bounds.y_min < bounds.y_max) {
+        Some(bounds)
+    } else {
+        None
+    }
+}
+
 /// Given a triangle primitive, this function computers the bounding bounding_box
 /// on the screen for that primitive. This function does not take into account 
 /// the boundaries of the image frame.
 pub fn bounding_box<N>(p1: &Point3<N>,
                        p2: &Point3<N>,
                        p3: &Point3<N>) -> BoundingBox<N>
-    where N: Float + Real
+    where N: Float + RealField
 {
-    let x_min = Real::floor(util::min3(p1.x, p2.x, p3.x));
-    let x_max = Real::ceil(util::max3(p1.x, p2.x, p3.x));
-    let y_min = Real::floor(util::min3(p1.y, p2.y, p3.y));
-    let y_max = Real::ceil(util::max3(p1.y, p2.y, p3.y));
+    let x_min = Float::floor(util::min3(p1.x, p2.x, p3.x));
+    let x_max = Float::ceil(util::max3(p1.x, p2.x, p3.x));
+    let y_min = Float::floor(util::min3(p1.y, p2.y, p3.y));
+    let y_max = Float::ceil(util::max3(p1.y, p2.y, p3.y));
 
     BoundingBox {
         x_min: x_min,
@@ -363,6 +507,46 @@ pub fn bounding_box<N>(p1: &Point3<N>,
     }   
 }
 
+/// Clip a bounding box to the pixels of an image with the given dimensions. Pixel
+/// centers sit on integer coordinates in raster space, so the result holds the
+/// inclusive range of pixel indices the box overlaps. This returns `None` when the
+/// box lies entirely outside the image, so the primitive can be skipped.
+pub fn clip_bounding_box<N>(bbox: &BoundingBox<N>,
+                            width: usize,
+                            height: usize) -> Option<BoundingBox<usize>>
+    where N: Float + RealField
+{
+    let _0 = N::zero();
+
+    // Comparisons against NaN are false, so this also drops boxes with NaN corners.
+    if width == 0 || height == 0 || !(bbox.x_min <= bbox.x_max && bbox.y_min <= bbox.y_max) {
+        return None;
+    }
+
+    let x_last: N = util::cast(width - 1);
+    let y_last: N = util::cast(height - 1);
+    if bbox.x_max < _0 || bbox.y_max < _0 || bbox.x_min > x_last || bbox.y_min > y_last {
+        return None;
+    }
+
+    let x_min = Float::max(bbox.x_min, _0).to_usize();
+    let x_max = Float::min(bbox.x_max, x_last).to_usize();
+    let y_min = Float::max(bbox.y_min, _0).to_usize();
+    let y_max = Float::min(bbox.y_max, y_last).to_usize();
+
+    match (x_min, x_max, y_min, y_max) {
+        (Some(x_min), Some(x_max), Some(y_min), Some(y_max)) => {
+            Some(BoundingBox {
+                x_min: x_min,
+                x_max: x_max,
+                y_min: y_min,
+                y_max: y_max,
+            })
+        }
+        _ => None,
+    }
+}
+
 /// Compute on which side of a triangle edge a point is on. They are defined such that
 /// They are iterated in clockwise order. This way a point is positive if it lies
 /// entirely within the triangle.
@@ -370,7 +554,7 @@ pub fn bounding_box<N>(p1: &Point3<N>,
 pub fn compute_edge<N>(v1: &Point3<N>, 
                        v2: &Point3<N>,
                         p: &Point3<N>) -> N
-    where N: Float + Real
+    where N: Float + RealField
 {
     (p.x - v1.x)*(v2.y - v1.y) - (p.y - v1.y)*(v2.x - v1.x)
 }
@@ -383,7 +567,7 @@ pub fn barycentric_coords<N>(v0: &Point3<N>,
                              v1: &Point3<N>,
                              v2: &Point3<N>,
                               p: &Point3<N>) -> Point3<N>
-    where N: Float + Real
+    where N: Float + RealField
 {
     let w0 = compute_edge(v1, v2, p);
     let w1 = compute_edge(v2, v0, p);
@@ -392,15 +576,712 @@ pub fn barycentric_coords<N>(v0: &Point3<N>,
     Point3::new(w0, w1, w2)
 }
 
+///
+/// The rule deciding which pixels a triangle covers.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum FillRule {
+    /// Accept a pixel when all of its barycentric coordinates are non-negative. Pixels
+    /// centered on an edge shared by two triangles are covered by both of them.
+    Inclusive,
+    /// Watertight rasterization. Each pixel centered on an edge shared by two triangles
+    /// is covered by exactly one of them: the one for which the edge is a top or a left
+    /// edge.
+    TopLeft,
+}
+
+///
+/// Compute an edge function such that swapping `v1` and `v2` gives exactly the
+/// negated value. Two triangles sharing an edge then agree on which side of it a 
+/// pixel lies, even in the presence of rounding error.
+///
+#[inline]
+pub fn compute_edge_watertight<N>(v1: &Point3<N>,
+                                  v2: &Point3<N>,
+                                   p: &Point3<N>) -> N
+    where N: Float + RealField
+{
+    if (v1.x < v2.x) || ((v1.x == v2.x) && (v1.y <= v2.y)) {
+        compute_edge(v1, v2, p)
+    } else {
+        -compute_edge(v2, v1, p)
+    }
+}
+
+///
+/// Compute the unnormalized barycentric coordinates of a point with edge functions that
+/// are consistent between triangles sharing an edge.
+///
+pub fn barycentric_coords_watertight<N>(v0: &Point3<N>,
+                                        v1: &Point3<N>,
+                                        v2: &Point3<N>,
+                                         p: &Point3<N>) -> Point3<N>
+    where N: Float + RealField
+{
+    let w0 = compute_edge_watertight(v1, v2, p);
+    let w1 = compute_edge_watertight(v2, v0, p);
+    let w2 = compute_edge_watertight(v0, v1, p);
+
+    Point3::new(w0, w1, w2)
+}
+
+///
+/// Determine whether the edge from `v1` to `v2` is a top edge or a left edge of its
+/// triangle. Raster space has its y axis pointing up. The sign of `area` gives the
+/// winding of the triangle: when it is positive the interior lies to the right of
+/// each edge, and when it is negative the interior lies to the left.
+///
+pub fn is_top_left_edge<N>(v1: &Point3<N>, v2: &Point3<N>, area: N) -> bool
+    where N: Float + RealField
+{
+    let _0 = N::zero();
+
+    // Orient the edge so that the interior is on its right.
+    let (dx, dy) = if area > _0 {
+        (v2.x - v1.x, v2.y - v1.y)
+    } else {
+        (v1.x - v2.x, v1.y - v2.y)
+    };
+
+    // A top edge is horizontal with the interior below it, and a left edge
+    // climbs with the interior to its right.
+    ((dy == _0) && (dx > _0)) || (dy > _0)
+}
+
+///
+/// Decide whether a pixel is inside a triangle from its unnormalized barycentric
+/// coordinates `w`. The flags in `top_left` tell which of the edges opposite `v0`,
+/// `v1`, and `v2` are top or left edges; they are only used by `FillRule::TopLeft`.
+///
+#[inline]
+pub fn is_covered<N>(fill_rule: FillRule, w: &Point3<N>, area: N, top_left: &[bool; 3]) -> bool
+    where N: Float + RealField
+{
+    let _0 = N::zero();
+
+    match fill_rule {
+        FillRule::Inclusive => {
+            (w[0] / area >= _0) && (w[1] / area >= _0) && (w[2] / area >= _0)
+        }
+        FillRule::TopLeft => {
+            // Flip the weights so that the interior is positive for either winding.
+            let sign = if area > _0 { N::one() } else { -N::one() };
+            (0..3).all(|i| {
+                let w_i = sign * w[i];
+                (w_i > _0) || ((w_i == _0) && top_left[i])
+            })
+        }
+    }
+}
+
+///
+/// How the rasterizer evaluates edge functions.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Precision {
+    /// Evaluate the edge functions in floating point at every pixel.
+    Float,
+    /// Snap the vertices to a grid of `1 / 2^SUBPIXEL_BITS` of a pixel and step the
+    /// edge functions with integer arithmetic. Coverage is exact, and the same for
+    /// `f32` and `f64`.
+    FixedPoint,
+}
+
+/// The number of fractional bits in a fixed point subpixel coordinate.
+pub const SUBPIXEL_BITS: u32 = 8;
+
+/// The largest distance from the origin in pixels that a vertex can be snapped from
+/// without the edge functions overflowing.
+pub const SUBPIXEL_RANGE: i64 = 1 << 20;
+
+///
+/// Snap a point in raster space to the fixed point subpixel grid. This returns `None`
+/// when the point lies outside of `SUBPIXEL_RANGE`.
+///
+pub fn snap_to_subpixel<N>(p: &Point3<N>) -> Option<(i64, i64)>
+    where N: Float + RealField
+{
+    let scale: N = util::cast(1 << SUBPIXEL_BITS);
+    let range: N = util::cast(SUBPIXEL_RANGE as usize);
+
+    // This also rejects NaN.
+    if !((Float::abs(p.x) < range) && (Float::abs(p.y) < range)) {
+        return None;
+    }
+
+    match (Float::round(p.x * scale).to_i64(), Float::round(p.y * scale).to_i64()) {
+        (Some(x), Some(y)) => Some((x, y)),
+        _ => None,
+    }
+}
+
+///
+/// An edge function on the fixed point subpixel grid, holding its value at the
+/// current pixel. The value is the same as `compute_edge` gives for the snapped
+/// points, scaled by `2^(2 * SUBPIXEL_BITS)`.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct FixedEdge {
+    step_x: i64,
+    step_y: i64,
+    pub value: i64,
+}
+
+impl FixedEdge {
+    ///
+    /// Set up the edge function for the edge from `v1` to `v2` at the center of the
+    /// pixel `(x, y)`. The vertices are in subpixel units.
+    ///
+    pub fn new(v1: (i64, i64), v2: (i64, i64), x: usize, y: usize) -> FixedEdge {
+        let px = (x as i64) << SUBPIXEL_BITS;
+        let py = (y as i64) << SUBPIXEL_BITS;
+        let dx = v2.0 - v1.0;
+        let dy = v2.1 - v1.1;
+
+        FixedEdge {
+            step_x: dy << SUBPIXEL_BITS,
+            step_y: -(dx << SUBPIXEL_BITS),
+            value: (px - v1.0) * dy - (py - v1.1) * dx,
+        }
+    }
+
+    /// Move one pixel in the positive x direction.
+    #[inline]
+    pub fn step_x(&mut self) {
+        self.value += self.step_x;
+    }
+
+    /// Move one pixel in the positive y direction.
+    #[inline]
+    pub fn step_y(&mut self) {
+        self.value += self.step_y;
+    }
+
+    ///
+    /// Evaluate the edge function at an offset from the current pixel center. The
+    /// offset is in subpixel units.
+    ///
+    #[inline]
+    pub fn value_at(&self, dx: i64, dy: i64) -> i64 {
+        // Both steps carry a factor of 2^SUBPIXEL_BITS, so the shift is exact.
+        self.value + ((dx * self.step_x + dy * self.step_y) >> SUBPIXEL_BITS)
+    }
+}
+
+///
+/// Compute the signed area of a triangle on the fixed point subpixel grid, in the
+/// same units as `FixedEdge`. It is positive when the vertices run clockwise.
+///
+pub fn compute_area_fixed(v0: (i64, i64), v1: (i64, i64), v2: (i64, i64)) -> i64 {
+    (v2.0 - v0.0) * (v1.1 - v0.1) - (v2.1 - v0.1) * (v1.0 - v0.0)
+}
+
+///
+/// Find the pixels whose centers lie in the bounding box of a triangle on the fixed
+/// point subpixel grid, clipped to an image with the given dimensions. This returns
+/// `None` when there are no such pixels.
+///
+pub fn clip_bounding_box_fixed(v0: (i64, i64),
+                               v1: (i64, i64),
+                               v2: (i64, i64),
+                               width: usize,
+                               height: usize) -> Option<BoundingBox<usize>>
+{
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    // Shifting right rounds toward negative infinity, so round the lower corner
+    // up to the next pixel center by adding just under one pixel first.
+    let round_up = (1 << SUBPIXEL_BITS) - 1;
+    let x_min = Ord::max((Ord::min(Ord::min(v0.0, v1.0), v2.0) + round_up) >> SUBPIXEL_BITS, 0);
+    let y_min = Ord::max((Ord::min(Ord::min(v0.1, v1.1), v2.1) + round_up) >> SUBPIXEL_BITS, 0);
+    let x_max = Ord::min(Ord::max(Ord::max(v0.0, v1.0), v2.0) >> SUBPIXEL_BITS, width as i64 - 1);
+    let y_max = Ord::min(Ord::max(Ord::max(v0.1, v1.1), v2.1) >> SUBPIXEL_BITS, height as i64 - 1);
+
+    if x_min > x_max || y_min > y_max {
+        return None;
+    }
+
+    Some(BoundingBox {
+        x_min: x_min as usize,
+        x_max: x_max as usize,
+        y_min: y_min as usize,
+        y_max: y_max as usize,
+    })
+}
+
+///
+/// Determine whether the edge from `v1` to `v2` on the fixed point subpixel grid is
+/// a top edge or a left edge. This is the same test as `is_top_left_edge`.
+///
+pub fn is_top_left_edge_fixed(v1: (i64, i64), v2: (i64, i64), area: i64) -> bool {
+    let (dx, dy) = if area > 0 {
+        (v2.0 - v1.0, v2.1 - v1.1)
+    } else {
+        (v1.0 - v2.0, v1.1 - v2.1)
+    };
+
+    ((dy == 0) && (dx > 0)) || (dy > 0)
+}
+
+///
+/// Decide whether a pixel is inside a triangle from the values of its fixed point
+/// edge functions. This follows the same rules as `is_covered`, without rounding.
+///
+#[inline]
+pub fn is_covered_fixed(fill_rule: FillRule, w: &[i64; 3], area: i64, top_left: &[bool; 3]) -> bool {
+    let sign = if area > 0 { 1 } else { -1 };
+
+    match fill_rule {
+        FillRule::Inclusive => (0..3).all(|i| sign * w[i] >= 0),
+        FillRule::TopLeft => {
+            (0..3).all(|i| {
+                let w_i = sign * w[i];
+                (w_i > 0) || ((w_i == 0) && top_left[i])
+            })
+        }
+    }
+}
+
+///
+/// The number of samples taken per pixel when multisampling.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum SampleCount {
+    X2,
+    X4,
+    X8,
+}
+
+impl SampleCount {
+    /// Returns the number of samples in each pixel.
+    #[inline]
+    pub fn count(&self) -> usize {
+        match *self {
+            SampleCount::X2 => 2,
+            SampleCount::X4 => 4,
+            SampleCount::X8 => 8,
+        }
+    }
+
+    ///
+    /// Returns the position of each sample relative to the pixel center, in
+    /// sixteenths of a pixel. These are the standard Direct3D sample patterns
+    /// with the y axis flipped to point up as it does in raster space.
+    ///
+    pub fn positions(&self) -> &'static [(i64, i64)] {
+        const X2: [(i64, i64); 2] = [(4, -4), (-4, 4)];
+        const X4: [(i64, i64); 4] = [(-2, 6), (6, 2), (-6, -2), (2, -6)];
+        const X8: [(i64, i64); 8] = [(1, 3), (-1, -3), (5, -1), (-3, 5),
+                                     (-5, -5), (-7, 1), (3, -7), (7, 7)];
+
+        match *self {
+            SampleCount::X2 => &X2,
+            SampleCount::X4 => &X4,
+            SampleCount::X8 => &X8,
+        }
+    }
+}
+
+///
+/// A function comparing a new value against a stored reference value, which decides
+/// whether a depth or stencil test passes.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum CompareFunction {
+    Never,
+    Less,
+    LessEqual,
+    Equal,
+    GreaterEqual,
+    Greater,
+    NotEqual,
+    Always,
+}
+
+impl CompareFunction {
+    /// Returns whether `value` passes the test against `reference`.
+    #[inline]
+    pub fn compare<T: PartialOrd>(&self, value: T, reference: T) -> bool {
+        match *self {
+            CompareFunction::Never        => false,
+            CompareFunction::Less         => value < reference,
+            CompareFunction::LessEqual    => value <= reference,
+            CompareFunction::Equal        => value == reference,
+            CompareFunction::GreaterEqual => value >= reference,
+            CompareFunction::Greater      => value > reference,
+            CompareFunction::NotEqual     => value != reference,
+            CompareFunction::Always       => true,
+        }
+    }
+}
+
+///
+/// Which end of the depth range lies closest to the eye. The standard convention
+/// puts the near plane at depth 0 and keeps the smallest depths, while reversed-Z
+/// puts the near plane at depth 1 and keeps the largest. Reversed-Z must be paired
+/// with one of the reversed projections, such as
+/// `reversed_perspective_projection_matrix`.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum DepthConvention {
+    Standard,
+    Reversed,
+}
+
+impl DepthConvention {
+    /// The depth test that keeps the surfaces closest to the eye.
+    pub fn compare_function(&self) -> CompareFunction {
+        match *self {
+            DepthConvention::Standard => CompareFunction::Less,
+            DepthConvention::Reversed => CompareFunction::Greater,
+        }
+    }
+}
+
+///
+/// Which winding of a triangle in raster space counts as facing the viewer.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum FrontFace {
+    /// Clockwise triangles face the viewer. This matches the order in which
+    /// `mesh::Face` stores its vertices.
+    Clockwise,
+    /// Counter-clockwise triangles face the viewer. Use this for meshes built
+    /// with the opposite convention.
+    CounterClockwise,
+}
+
+///
+/// Which triangles are discarded before rasterization based on their winding.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum CullMode {
+    /// Draw every triangle.
+    None,
+    /// Discard triangles facing away from the viewer.
+    Back,
+    /// Discard triangles facing the viewer.
+    Front,
+}
+
+///
+/// Decide whether a triangle should be culled from its signed area in raster space,
+/// as computed by `compute_edge(v0, v1, v2)`. The area is positive when the vertices
+/// run clockwise. Degenerate triangles face neither way, and are culled by both
+/// `CullMode::Back` and `CullMode::Front`.
+///
+pub fn is_culled<N>(cull_mode: CullMode, front_face: FrontFace, area: N) -> bool
+    where N: Float + RealField
+{
+    let _0 = N::zero();
+
+    let is_front = match front_face {
+        FrontFace::Clockwise        => area > _0,
+        FrontFace::CounterClockwise => area < _0,
+    };
+
+    match cull_mode {
+        CullMode::None  => false,
+        CullMode::Back  => !is_front,
+        CullMode::Front => is_front || (area == _0),
+    }
+}
+
+///
+/// The algorithm used to rasterize lines.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum LineMode {
+    /// Bresenham's algorithm. Lines are one pixel wide and fully opaque.
+    Bresenham,
+    /// Xiaolin Wu's algorithm. Lines are anti-aliased by spreading each step over the
+    /// two nearest pixels, weighted by how close their centers are to the line.
+    XiaolinWu,
+}
+
+///
+/// How the faces of a mesh are drawn.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum PolygonMode {
+    /// Fill the interior of each face.
+    Fill,
+    /// Draw the edges of each face as lines.
+    Line,
+}
+
+///
+/// Clip the segment from `p0` to `p1` to a box with the Liang-Barsky algorithm.
+/// This returns the range of the segment parameter that lies inside the box, or
+/// `None` when the segment misses it.
+///
+pub fn clip_line_to_box<N>(p0: &Point3<N>, p1: &Point3<N>, bbox: &BoundingBox<N>) -> Option<(N, N)>
+    where N: Float + RealField
+{
+    let _0 = N::zero();
+    let _1 = N::one();
+
+    let dx = p1.x - p0.x;
+    let dy = p1.y - p0.y;
+    let boundaries = [(-dx, p0.x - bbox.x_min),
+                      (dx, bbox.x_max - p0.x),
+                      (-dy, p0.y - bbox.y_min),
+                      (dy, bbox.y_max - p0.y)];
+
+    let mut t_min = _0;
+    let mut t_max = _1;
+    for &(p, q) in boundaries.iter() {
+        if p == _0 {
+            // The segment runs parallel to this boundary.
+            if q < _0 {
+                return None;
+            }
+        } else if p < _0 {
+            t_min = Float::max(t_min, q / p);
+        } else {
+            t_max = Float::min(t_max, q / p);
+        }
+    }
+
+    // Comparisons against NaN are false, so this also drops NaN endpoints.
+    if t_min <= t_max {
+        Some((t_min, t_max))
+    } else {
+        None
+    }
+}
+
+///
+/// Rasterize the line segment from `p0` to `p1` in raster space into an image with
+/// the given dimensions. The function `plot` is called with the column and row of
+/// each pixel on the line, the parameter along the segment where the pixel lies,
+/// and the coverage of the pixel, which is one except for anti-aliased lines.
+///
+pub fn rasterize_line<N, F>(mode: LineMode,
+                            p0: &Point3<N>,
+                            p1: &Point3<N>,
+                            width: usize,
+                            height: usize,
+                            mut plot: F)
+    where N: Float + RealField,
+          F: FnMut(usize, usize, N, N)
+{
+    let _1 = N::one();
+    let half = _1 / (_1 + _1);
+
+    // Pixel centers sit on integer coordinates, so the pixels of the image cover
+    // the box from -1/2 to the dimensions less a half.
+    let bbox = BoundingBox {
+        x_min: -half,
+        x_max: util::cast::<N>(width) - half,
+        y_min: -half,
+        y_max: util::cast::<N>(height) - half,
+    };
+    let (t0, t1) = match clip_line_to_box(p0, p1, &bbox) {
+        Some(range) => range,
+        None => return,
+    };
+
+    let q0 = Point3::new(p0.x + (p1.x - p0.x) * t0, p0.y + (p1.y - p0.y) * t0, p0.z);
+    let q1 = Point3::new(p0.x + (p1.x - p0.x) * t1, p0.y + (p1.y - p0.y) * t1, p0.z);
+
+    // Map the parameter along the clipped segment back onto the original one, and
+    // drop pixels that rounding has pushed off the image.
+    let mut plot_clipped = |x: i64, y: i64, t: N, coverage: N| {
+        if (x >= 0) && (y >= 0) && ((x as usize) < width) && ((y as usize) < height) {
+            plot(x as usize, y as usize, t0 + (t1 - t0) * t, coverage);
+        }
+    };
+
+    match mode {
+        LineMode::Bresenham => bresenham_line(&q0, &q1, &mut plot_clipped),
+        LineMode::XiaolinWu => xiaolin_wu_line(&q0, &q1, &mut plot_clipped),
+    }
+}
+
+///
+/// Step along a line with Bresenham's algorithm, from the pixel nearest to `p0` to
+/// the pixel nearest to `p1`.
+///
+fn bresenham_line<N, F>(p0: &Point3<N>, p1: &Point3<N>, plot: &mut F)
+    where N: Float + RealField,
+          F: FnMut(i64, i64, N, N)
+{
+    let _0 = N::zero();
+    let _1 = N::one();
+    let half = _1 / (_1 + _1);
+
+    let (x0, y0, x1, y1) = match (Float::floor(p0.x + half).to_i64(), Float::floor(p0.y + half).to_i64(),
+                                  Float::floor(p1.x + half).to_i64(), Float::floor(p1.y + half).to_i64()) {
+        (Some(x0), Some(y0), Some(x1), Some(y1)) => (x0, y0, x1, y1),
+        _ => return,
+    };
+
+    let dx = (x1 - x0).abs();
+    let dy = -(y1 - y0).abs();
+    let sx = if x0 < x1 { 1 } else { -1 };
+    let sy = if y0 < y1 { 1 } else { -1 };
+    let steps: N = util::cast(Ord::max(dx, -dy) as usize);
+
+    let (mut x, mut y) = (x0, y0);
+    let mut step = 0;
+    let mut err = dx + dy;
+    loop {
+        let t = if steps > _0 { util::cast::<N>(step) / steps } else { _0 };
+        plot(x, y, t, _1);
+        if (x == x1) && (y == y1) {
+            break;
+        }
+
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+        step += 1;
+    }
+}
+
+///
+/// Step along a line with Xiaolin Wu's algorithm, which plots the two pixels
+/// straddling the line at each step along its major axis.
+///
+fn xiaolin_wu_line<N, F>(p0: &Point3<N>, p1: &Point3<N>, plot: &mut F)
+    where N: Float + RealField,
+          F: FnMut(i64, i64, N, N)
+{
+    let _0 = N::zero();
+    let _1 = N::one();
+    let half = _1 / (_1 + _1);
+    let fpart = |v: N| v - Float::floor(v);
+    let rfpart = |v: N| _1 - fpart(v);
+
+    // Walk along the major axis from left to right, swapping the coordinates back
+    // when plotting.
+    let steep = Float::abs(p1.y - p0.y) > Float::abs(p1.x - p0.x);
+    let (mut x0, mut y0, mut x1, mut y1) = if steep {
+        (p0.y, p0.x, p1.y, p1.x)
+    } else {
+        (p0.x, p0.y, p1.x, p1.y)
+    };
+    let reversed = x0 > x1;
+    if reversed {
+        ::std::mem::swap(&mut x0, &mut x1);
+        ::std::mem::swap(&mut y0, &mut y1);
+    }
+
+    let dx = x1 - x0;
+    let gradient = if dx == _0 { _1 } else { (y1 - y0) / dx };
+
+    let mut plot_major = |x: N, y: N, coverage: N| {
+        let t = if dx == _0 { _0 } else { Float::max(_0, Float::min(_1, (x - x0) / dx)) };
+        let t = if reversed { _1 - t } else { t };
+        let (px, py) = if steep { (y, x) } else { (x, y) };
+        if let (Some(px), Some(py)) = (px.to_i64(), py.to_i64()) {
+            plot(px, py, t, coverage);
+        }
+    };
+
+    // The endpoints cover only part of their pixels along the major axis.
+    let x_start = Float::floor(x0 + half);
+    let y_start = y0 + gradient * (x_start - x0);
+    let gap_start = rfpart(x0 + half);
+    plot_major(x_start, Float::floor(y_start), rfpart(y_start) * gap_start);
+    plot_major(x_start, Float::floor(y_start) + _1, fpart(y_start) * gap_start);
+
+    let x_end = Float::floor(x1 + half);
+    let y_end = y1 + gradient * (x_end - x1);
+    let gap_end = fpart(x1 + half);
+    if x_end > x_start {
+        plot_major(x_end, Float::floor(y_end), rfpart(y_end) * gap_end);
+        plot_major(x_end, Float::floor(y_end) + _1, fpart(y_end) * gap_end);
+    }
+
+    let mut x = x_start + _1;
+    let mut y = y_start + gradient;
+    while x < x_end {
+        plot_major(x, Float::floor(y), rfpart(y));
+        plot_major(x, Float::floor(y) + _1, fpart(y));
+        x += _1;
+        y += gradient;
+    }
+}
+
+///
+/// The shape of the splat drawn for a point primitive.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum PointShape {
+    /// Fill a square with sides as long as the point size.
+    Square,
+    /// Fill a disk with a diameter equal to the point size.
+    Round,
+}
+
+///
+/// Rasterize a point splat of `size` pixels centered on `p` in raster space, into an
+/// image with the given dimensions. The function `plot` is called with the column
+/// and row of each pixel whose center lies inside the splat.
+///
+pub fn rasterize_point<N, F>(shape: PointShape,
+                             p: &Point3<N>,
+                             size: N,
+                             width: usize,
+                             height: usize,
+                             mut plot: F)
+    where N: Float + RealField,
+          F: FnMut(usize, usize)
+{
+    let _0 = N::zero();
+    let _1 = N::one();
+    let radius = size / (_1 + _1);
+
+    // Cover the pixel centers in the half open square [p - r, p + r), so that a
+    // splat of n pixels spans exactly n rows and columns.
+    let bbox = BoundingBox {
+        x_min: Float::ceil(p.x - radius),
+        x_max: Float::ceil(p.x + radius) - _1,
+        y_min: Float::ceil(p.y - radius),
+        y_max: Float::ceil(p.y + radius) - _1,
+    };
+    let bbox = match clip_bounding_box(&bbox, width, height) {
+        Some(bbox) => bbox,
+        None => return,
+    };
+
+    for y in bbox.y_min..(bbox.y_max + 1) {
+        for x in bbox.x_min..(bbox.x_max + 1) {
+            let inside = match shape {
+                PointShape::Square => true,
+                PointShape::Round => {
+                    let dx = util::cast::<N>(x) - p.x;
+                    let dy = util::cast::<N>(y) - p.y;
+                    dx * dx + dy * dy < radius * radius
+                }
+            };
+
+            if inside {
+                plot(x, y);
+            }
+        }
+    }
+}
+
 ///
 /// Computes the area of a triangle primitive.
 ///
 pub fn compute_area<N>(v0: &Point3<N>,
                        v1: &Point3<N>,
                        v2: &Point3<N>,) -> N
-    where N: Float + Real
+    where N: Float + RealField
 {
-    nalgebra::abs(&compute_edge(v0, v1, v2))
+    Float::abs(compute_edge(v0, v1, v2))
 }
 
 #[cfg(test)]
@@ -425,6 +1306,163 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_clipped_bounding_box_should_lie_inside_the_image() {
+        let v0: Point3<f32> = Point3::new(-48.0, -10.0, 82.0);
+        let v1: Point3<f32> = Point3::new(29.0, -15.0, 44.0);
+        let v2: Point3<f32> = Point3::new(13.0, 34.0, 114.0);
+
+        let bbox = super::bounding_box(&v0, &v1, &v2);
+        let clipped = super::clip_bounding_box(&bbox, 20, 30).unwrap();
+
+        assert_eq!(clipped.x_min, 0);
+        assert_eq!(clipped.x_max, 19);
+        assert_eq!(clipped.y_min, 0);
+        assert_eq!(clipped.y_max, 29);
+    }
+
+    #[test]
+    fn test_clipped_bounding_box_should_keep_a_primitive_inside_the_image() {
+        let v0: Point3<f32> = Point3::new(2.5, 3.5, 0.0);
+        let v1: Point3<f32> = Point3::new(10.2, 4.0, 0.0);
+        let v2: Point3<f32> = Point3::new(6.0, 12.7, 0.0);
+
+        let bbox = super::bounding_box(&v0, &v1, &v2);
+        let clipped = super::clip_bounding_box(&bbox, 64, 64).unwrap();
+
+        assert_eq!(clipped.x_min, 2);
+        assert_eq!(clipped.x_max, 11);
+        assert_eq!(clipped.y_min, 3);
+        assert_eq!(clipped.y_max, 13);
+    }
+
+    #[test]
+    fn test_clipped_bounding_box_should_reject_a_primitive_off_screen() {
+        let v0: Point3<f32> = Point3::new(-48.0, -10.0, 82.0);
+        let v1: Point3<f32> = Point3::new(-29.0, -15.0, 44.0);
+        let v2: Point3<f32> = Point3::new(-13.0, 34.0, 114.0);
+
+        let bbox = super::bounding_box(&v0, &v1, &v2);
+
+        assert!(super::clip_bounding_box(&bbox, 64, 64).is_none());
+    }
+
+    /// Count the number of triangles covering each pixel of a `size` x `size` grid.
+    fn coverage(triangles: &[[Point3<f32>; 3]], size: usize) -> Vec<Vec<usize>> {
+        let mut counts = vec![vec![0; size]; size];
+        for tri in triangles {
+            let (v0, v1, v2) = (&tri[0], &tri[1], &tri[2]);
+            let area = super::compute_edge(v0, v1, v2);
+            let top_left = [super::is_top_left_edge(v1, v2, area),
+                            super::is_top_left_edge(v2, v0, area),
+                            super::is_top_left_edge(v0, v1, area)];
+            for (y, row) in counts.iter_mut().enumerate() {
+                for (x, count) in row.iter_mut().enumerate() {
+                    let p = Point3::new(x as f32, y as f32, 0.0);
+                    let w = super::barycentric_coords_watertight(v0, v1, v2, &p);
+                    if super::is_covered(super::FillRule::TopLeft, &w, area, &top_left) {
+                        *count += 1;
+                    }
+                }
+            }
+        }
+
+        counts
+    }
+
+    #[test]
+    fn test_top_left_rule_should_cover_each_pixel_of_a_shared_edge_once() {
+        // A square split along its diagonal. Many pixel centers lie exactly on the edges.
+        let v0 = Point3::new(0.0, 0.0, 0.0);
+        let v1 = Point3::new(0.0, 8.0, 0.0);
+        let v2 = Point3::new(8.0, 8.0, 0.0);
+        let v3 = Point3::new(8.0, 0.0, 0.0);
+
+        let counts = coverage(&[[v0, v1, v3], [v3, v1, v2]], 10);
+
+        for (y, row) in counts.iter().enumerate() {
+            for (x, &count) in row.iter().enumerate() {
+                assert!(count <= 1);
+                if (0 < x) && (x < 8) && (0 < y) && (y < 8) {
+                    assert_eq!(count, 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_top_left_rule_should_not_depend_on_winding() {
+        let v0 = Point3::new(0.0, 0.0, 0.0);
+        let v1 = Point3::new(0.0, 8.0, 0.0);
+        let v2 = Point3::new(8.0, 8.0, 0.0);
+        let v3 = Point3::new(8.0, 0.0, 0.0);
+
+        let clockwise = coverage(&[[v0, v1, v3], [v3, v1, v2]], 10);
+        let counter_clockwise = coverage(&[[v0, v3, v1], [v3, v2, v1]], 10);
+
+        assert_eq!(clockwise, counter_clockwise);
+    }
+
+    #[test]
+    fn test_top_left_rule_should_not_overlap_on_an_arbitrary_shared_edge() {
+        let v0 = Point3::new(0.3, 0.1, 0.0);
+        let v1 = Point3::new(7.9, 0.7, 0.0);
+        let v2 = Point3::new(2.2, 9.4, 0.0);
+        let v3 = Point3::new(9.7, 8.8, 0.0);
+
+        let counts = coverage(&[[v0, v2, v1], [v1, v2, v3]], 12);
+
+        for row in counts {
+            for count in row {
+                assert!(count <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_watertight_edge_function_should_be_antisymmetric() {
+        let v1: Point3<f32> = Point3::new(7.9, 0.7, 0.0);
+        let v2: Point3<f32> = Point3::new(2.2, 9.4, 0.0);
+        let p:  Point3<f32> = Point3::new(4.3, 5.1, 0.0);
+
+        let e12 = super::compute_edge_watertight(&v1, &v2, &p);
+        let e21 = super::compute_edge_watertight(&v2, &v1, &p);
+
+        assert_eq!(e12, -e21);
+    }
+
+    #[test]
+    fn test_back_face_culling_should_only_discard_triangles_facing_away() {
+        use super::{CullMode, FrontFace};
+
+        let v0: Point3<f32> = Point3::new(0.0, 0.0, 0.0);
+        let v1: Point3<f32> = Point3::new(0.0, 8.0, 0.0);
+        let v2: Point3<f32> = Point3::new(8.0, 0.0, 0.0);
+        let clockwise = super::compute_edge(&v0, &v1, &v2);
+        let counter_clockwise = super::compute_edge(&v0, &v2, &v1);
+
+        assert!(!super::is_culled(CullMode::Back, FrontFace::Clockwise, clockwise));
+        assert!(super::is_culled(CullMode::Back, FrontFace::Clockwise, counter_clockwise));
+        assert!(super::is_culled(CullMode::Front, FrontFace::Clockwise, clockwise));
+        assert!(!super::is_culled(CullMode::Front, FrontFace::Clockwise, counter_clockwise));
+        assert!(!super::is_culled(CullMode::None, FrontFace::Clockwise, clockwise));
+        assert!(!super::is_culled(CullMode::None, FrontFace::Clockwise, counter_clockwise));
+    }
+
+    #[test]
+    fn test_flipping_the_front_face_should_swap_culled_triangles() {
+        use super::{CullMode, FrontFace};
+
+        let v0: Point3<f32> = Point3::new(0.0, 0.0, 0.0);
+        let v1: Point3<f32> = Point3::new(0.0, 8.0, 0.0);
+        let v2: Point3<f32> = Point3::new(8.0, 0.0, 0.0);
+        let clockwise = super::compute_edge(&v0, &v1, &v2);
+        let counter_clockwise = super::compute_edge(&v0, &v2, &v1);
+
+        assert!(super::is_culled(CullMode::Back, FrontFace::CounterClockwise, clockwise));
+        assert!(!super::is_culled(CullMode::Back, FrontFace::CounterClockwise, counter_clockwise));
+    }
+
     #[test]
     fn test_perspective_matrix_equation_should_be_the_product_of_orthographic_and_perspective() {
         let left: f32   = -4.5; 
@@ -511,6 +1549,26 @@ mod tests {
 
         assert_relative_eq!(world_to_raster, vp * ppm);
     }
+
+    #[test]
+    fn test_offset_viewport_should_map_the_view_volume_onto_its_block_of_pixels() {
+        let viewport = super::Viewport::<f32>::new(10, 20, 8, 4);
+        let m = viewport.matrix();
+
+        // The corners of the view volume land half a pixel outside the corner pixels.
+        let lower = m * Point3::new(-1.0, -1.0, 0.0).to_homogeneous();
+        let upper = m * Point3::new(1.0, 1.0, 0.0).to_homogeneous();
+        assert_relative_eq!(lower, Point3::new(9.5, 19.5, 0.0).to_homogeneous());
+        assert_relative_eq!(upper, Point3::new(17.5, 23.5, 0.0).to_homogeneous());
+        assert_relative_eq!(super::offset_viewport_matrix::<f32>(0, 0, 8, 4), super::viewport_matrix(8, 4));
+
+        let bounds = viewport.bounds();
+        let frame = super::BoundingBox { x_min: 0, x_max: 16, y_min: 0, y_max: 64 };
+        assert_eq!(super::intersect_bounds(&bounds, &frame),
+                   Some(super::BoundingBox { x_min: 10, x_max: 16, y_min: 20, y_max: 24 }));
+        assert_eq!(super::intersect_bounds(&bounds, &super::BoundingBox { x_min: 0, x_max: 10, y_min: 0, y_max: 64 }),
+                   None);
+    }
     
     #[test]
     fn test_world_to_camera_matrix_should_be_a_rigid_body_transformation() {
@@ -537,7 +1595,7 @@ mod tests {
     fn test_world_to_camera_matrix() {
         let p_xyz = Point4::new(10.0, 5.0, 9.0, 1.0);
         let eye   = Vector3::new(45.0, 32.5, -19.0);
-        let gaze  = Vector3::new(-3.6, -4.0, 5.0);
+        let gaze  = Vector3::new(-3.6f64, -4.0, 5.0);
         let top   = Vector3::new(0.0, 0.0, 1.0);
         
         let w     = -gaze / gaze.norm();
@@ -579,4 +1637,227 @@ mod tests {
         assert_eq!(area2.abs(), area3.abs());
         assert_eq!(area3.abs(), area4.abs());
     }
+
+    #[test]
+    fn test_snapping_should_round_to_the_subpixel_grid() {
+        let p: Point3<f32> = Point3::new(1.5, -2.25 - 1.0 / 1024.0, 0.0);
+
+        assert_eq!(super::snap_to_subpixel(&p), Some((384, -576)));
+    }
+
+    #[test]
+    fn test_snapping_should_reject_points_outside_the_subpixel_range() {
+        let far: Point3<f64> = Point3::new(1.0e7, 0.0, 0.0);
+        let nan: Point3<f64> = Point3::new(f64::NAN, 0.0, 0.0);
+
+        assert_eq!(super::snap_to_subpixel(&far), None);
+        assert_eq!(super::snap_to_subpixel(&nan), None);
+    }
+
+    #[test]
+    fn test_stepping_a_fixed_edge_should_match_evaluating_it() {
+        let v1 = (-300, 517);
+        let v2 = (2049, -1001);
+
+        let mut row = super::FixedEdge::new(v1, v2, 2, 3);
+        for y in 3..10 {
+            let mut edge = row;
+            for x in 2..12 {
+                assert_eq!(edge.value, super::FixedEdge::new(v1, v2, x, y).value);
+                edge.step_x();
+            }
+            row.step_y();
+        }
+    }
+
+    #[test]
+    fn test_fixed_point_coverage_should_match_floating_point_on_the_grid() {
+        let v0: Point3<f64> = Point3::new(1.25, 0.5, 0.0);
+        let v1: Point3<f64> = Point3::new(3.5, 9.75, 0.0);
+        let v2: Point3<f64> = Point3::new(9.0, 2.0, 0.0);
+        let s0 = super::snap_to_subpixel(&v0).unwrap();
+        let s1 = super::snap_to_subpixel(&v1).unwrap();
+        let s2 = super::snap_to_subpixel(&v2).unwrap();
+        let area = super::compute_edge(&v0, &v1, &v2);
+        let area_fixed = super::compute_area_fixed(s0, s1, s2);
+        let scale = (1 << (2 * super::SUBPIXEL_BITS)) as f64;
+
+        assert_eq!(area * scale, area_fixed as f64);
+        for y in 0..12 {
+            for x in 0..12 {
+                let p = Point3::new(x as f64, y as f64, 0.0);
+                let w = super::barycentric_coords(&v0, &v1, &v2, &p);
+                let w_fixed = [super::FixedEdge::new(s1, s2, x, y).value,
+                               super::FixedEdge::new(s2, s0, x, y).value,
+                               super::FixedEdge::new(s0, s1, x, y).value];
+                for i in 0..3 {
+                    assert_eq!(w[i] * scale, w_fixed[i] as f64);
+                }
+            }
+        }
+    }
+
+    /// Collect the pixels a line touches along with their coverage.
+    fn line_pixels(mode: super::LineMode, p0: Point3<f64>, p1: Point3<f64>) -> Vec<(usize, usize, f64, f64)> {
+        let mut pixels = Vec::new();
+        super::rasterize_line(mode, &p0, &p1, 16, 16, |x, y, t, coverage| pixels.push((x, y, t, coverage)));
+
+        pixels
+    }
+
+    #[test]
+    fn test_bresenham_line_should_connect_its_endpoints_in_every_octant() {
+        let center = Point3::new(8.0, 8.0, 0.0);
+        let ends = [(13, 10), (10, 13), (6, 13), (3, 10), (3, 6), (6, 3), (10, 3), (13, 6)];
+
+        for &(x, y) in ends.iter() {
+            let end = Point3::new(x as f64, y as f64, 0.0);
+            let pixels = line_pixels(super::LineMode::Bresenham, center, end);
+
+            assert_eq!(pixels.len(), 6);
+            assert_eq!((pixels[0].0, pixels[0].1, pixels[0].2), (8, 8, 0.0));
+            assert_eq!((pixels[5].0, pixels[5].1, pixels[5].2), (x, y, 1.0));
+            for window in pixels.windows(2) {
+                // Neighbouring pixels on the line touch along an edge or a corner.
+                assert!((window[0].0 as i64 - window[1].0 as i64).abs() <= 1);
+                assert!((window[0].1 as i64 - window[1].1 as i64).abs() <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lines_should_be_clipped_to_the_image() {
+        let pixels = line_pixels(super::LineMode::Bresenham,
+                                 Point3::new(-100.0, 4.0, 0.0),
+                                 Point3::new(100.0, 4.0, 0.0));
+
+        assert_eq!(pixels.len(), 16);
+        for (x, &(px, py, t, _)) in pixels.iter().enumerate() {
+            assert_eq!((px, py), (x, 4));
+            assert_relative_eq!(t, (x as f64 + 100.0) / 200.0, epsilon = 0.01);
+        }
+
+        assert!(line_pixels(super::LineMode::XiaolinWu,
+                            Point3::new(-10.0, 20.0, 0.0),
+                            Point3::new(30.0, 40.0, 0.0)).is_empty());
+    }
+
+    #[test]
+    fn test_xiaolin_wu_line_should_split_coverage_between_neighbouring_pixels() {
+        let pixels = line_pixels(super::LineMode::XiaolinWu,
+                                 Point3::new(2.0, 3.0, 0.0),
+                                 Point3::new(12.0, 8.0, 0.0));
+
+        // The coverage of each column along the major axis sums to one.
+        for x in 3..12 {
+            let coverage: f64 = pixels.iter().filter(|p| p.0 == x).map(|p| p.3).sum();
+            assert_relative_eq!(coverage, 1.0, epsilon = 1e-9);
+        }
+
+        // Half way between rows, the line covers both pixels equally.
+        let column: Vec<_> = pixels.iter().filter(|p| p.0 == 3).collect();
+        assert_eq!(column.len(), 2);
+        assert_relative_eq!(column[0].3, 0.5);
+        assert_relative_eq!(column[1].3, 0.5);
+    }
+
+    /// Collect the pixels covered by a point splat.
+    fn point_pixels(shape: super::PointShape, p: Point3<f64>, size: f64) -> Vec<(usize, usize)> {
+        let mut pixels = Vec::new();
+        super::rasterize_point(shape, &p, size, 16, 16, |x, y| pixels.push((x, y)));
+
+        pixels
+    }
+
+    #[test]
+    fn test_square_point_should_cover_size_squared_pixels() {
+        for size in 1..6 {
+            let pixels = point_pixels(super::PointShape::Square, Point3::new(7.0, 8.0, 0.0), size as f64);
+            assert_eq!(pixels.len(), size * size);
+
+            let pixels = point_pixels(super::PointShape::Square, Point3::new(7.5, 8.5, 0.0), size as f64);
+            assert_eq!(pixels.len(), size * size);
+        }
+    }
+
+    #[test]
+    fn test_round_point_should_stay_inside_its_square() {
+        let center = Point3::new(7.0, 8.0, 0.0);
+        let round  = point_pixels(super::PointShape::Round, center, 5.0);
+        let square = point_pixels(super::PointShape::Square, center, 5.0);
+
+        assert_eq!(round.len(), 21);
+        assert!(round.iter().all(|p| square.contains(p)));
+        assert!(!round.contains(&(5, 6)));
+        assert!(round.contains(&(7, 10)));
+    }
+
+    #[test]
+    fn test_point_should_be_clipped_to_the_image() {
+        let pixels = point_pixels(super::PointShape::Square, Point3::new(0.0, 15.0, 0.0), 3.0);
+
+        assert_eq!(pixels, vec![(0, 14), (1, 14), (0, 15), (1, 15)]);
+        assert!(point_pixels(super::PointShape::Round, Point3::new(-5.0, 5.0, 0.0), 3.0).is_empty());
+    }
+
+    #[test]
+    fn test_compare_functions_should_order_values() {
+        use super::CompareFunction::*;
+
+        let expected = [(Never, [false, false, false]),
+                        (Less, [true, false, false]),
+                        (LessEqual, [true, true, false]),
+                        (Equal, [false, true, false]),
+                        (GreaterEqual, [false, true, true]),
+                        (Greater, [false, false, true]),
+                        (NotEqual, [true, false, true]),
+                        (Always, [true, true, true])];
+
+        for &(function, results) in expected.iter() {
+            assert_eq!([function.compare(1, 2), function.compare(2, 2), function.compare(3, 2)], results);
+        }
+    }
+
+    /// Project a camera space depth and return the depth that the renderer stores for it.
+    fn project_depth(m: &Matrix4<f64>, z: f64) -> f64 {
+        let p = *m * Point4::new(0.0, 0.0, z, 1.0);
+        (1.0 - p.z / p.w) / 2.0
+    }
+
+    #[test]
+    fn test_perspective_projections_should_map_the_near_and_far_planes() {
+        let (near, far) = (-0.5, -100.0);
+        let standard = super::perspective_projection_matrix(-1.0, 1.0, 1.0, -1.0, near, far);
+        let reversed = super::reversed_perspective_projection_matrix(-1.0, 1.0, 1.0, -1.0, near, far);
+        let infinite = super::infinite_perspective_projection_matrix(-1.0, 1.0, 1.0, -1.0, near);
+        let reversed_infinite =
+            super::reversed_infinite_perspective_projection_matrix(-1.0, 1.0, 1.0, -1.0, near);
+
+        assert_relative_eq!(project_depth(&standard, near), 0.0);
+        assert_relative_eq!(project_depth(&standard, far), 1.0);
+        assert_relative_eq!(project_depth(&reversed, near), 1.0);
+        assert_relative_eq!(project_depth(&reversed, far), 0.0);
+        assert_relative_eq!(project_depth(&infinite, near), 0.0);
+        assert_relative_eq!(project_depth(&infinite, -1e12), 1.0, epsilon = 1e-9);
+        assert_relative_eq!(project_depth(&reversed_infinite, near), 1.0);
+        assert_relative_eq!(project_depth(&reversed_infinite, -1e12), 0.0, epsilon = 1e-9);
+
+        // Reversed infinite projections store the depth near / z.
+        assert_relative_eq!(project_depth(&reversed_infinite, -40.0), near / -40.0);
+    }
+
+    #[test]
+    fn test_reversed_projections_should_agree_with_the_standard_ones_in_x_and_y() {
+        let (left, right, top, bottom, near, far) = (-4.5, 3.5, 5.4, -3.4, -1.0, -6.2);
+        let standard = super::perspective_projection_matrix(left, right, top, bottom, near, far);
+        let reversed = super::reversed_perspective_projection_matrix(left, right, top, bottom, near, far);
+
+        let p = Point4::new(1.5, -2.0, -3.0, 1.0);
+        let (a, b) = (standard * p, reversed * p);
+
+        assert_relative_eq!(a.x, b.x);
+        assert_relative_eq!(a.y, b.y);
+        assert_relative_eq!(a.w, b.w);
+        assert_relative_eq!(a.z, -b.z);
+    }
 }
@@ -0,0 +1,1614 @@
+use nalgebra::{Vector3, Vector4, Point3, Matrix4};
+use num_traits::{Float, NumCast};
+use alga::general::RealField;
+use color::{Rgb, Pixel, Channel};
+use frame_buffer::{FrameBuffer, MultisampleFrameBuffer, Origin, TopLeft};
+use z_buffer::{ZBuffer, MultisampleZBuffer, DepthPyramid, DepthFormat};
+use stencil_buffer::{StencilBuffer, StencilState, StencilOp};
+use blend::BlendState;
+use mesh::Mesh;
+use clip;
+use clip::ClipVertex;
+use raster;
+use raster::{BoundingBox, FillRule, CullMode, FrontFace, Precision, FixedEdge, SampleCount};
+use raster::{LineMode, PolygonMode, PointShape, CompareFunction, DepthConvention, Viewport};
+use util;
+use std::thread;
+
+
+/// Return a renderer with initialized frame and depth buffers.
+pub fn renderer<N>(width: usize, height: usize) -> Renderer<N, TopLeft>
+    where N: Float + RealField + DepthFormat
+{
+    Renderer::new(width, height)
+}
+
+///
+/// The data handed to a shader for each pixel covered by a face. The vertex
+/// positions are in raster space, and the barycentric weights `w` are normalized
+/// so that they sum to one. When clipping splits a face, `v0`, `v1`, `v2` and `w`
+/// refer to the piece being drawn, and `b` holds the perspective correct barycentric
+/// coordinates of the pixel with respect to the corners of the original face.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Fragment<N> where N: Float + RealField {
+    pub x: usize,
+    pub y: usize,
+    pub depth: N,
+    pub face: usize,
+    pub v0: Point3<N>,
+    pub v1: Point3<N>,
+    pub v2: Point3<N>,
+    pub w: Point3<N>,
+    pub b: Point3<N>,
+}
+
+///
+/// A color returned by a shader, with intensities running from zero to one. A
+/// `Vector3` color is opaque, while the fourth component of a `Vector4` color is
+/// alpha, which is stored by pixel formats with an alpha channel and weighs the
+/// color when blending.
+///
+pub trait FragmentColor<N> {
+    fn rgba(&self) -> [N; 4];
+}
+
+impl<N> FragmentColor<N> for Vector3<N> where N: Float + RealField {
+    #[inline]
+    fn rgba(&self) -> [N; 4] {
+        [self.x, self.y, self.z, N::one()]
+    }
+}
+
+impl<N> FragmentColor<N> for Vector4<N> where N: Float + RealField {
+    #[inline]
+    fn rgba(&self) -> [N; 4] {
+        [self.x, self.y, self.z, self.w]
+    }
+}
+
+///
+/// The data handed to a shader for each pixel covered by a point. The position of
+/// the point is in raster space.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PointFragment<N> where N: Float + RealField {
+    pub x: usize,
+    pub y: usize,
+    pub depth: N,
+    pub vertex: usize,
+    pub position: Point3<N>,
+}
+
+///
+/// Counts of the work that occlusion culling saved. A triangle is culled when the
+/// depth pyramid hides all of it, and a tile is culled when a triangle that was not
+/// culled is still hidden in that tile.
+///
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct OcclusionStats {
+    pub triangles_tested: usize,
+    pub triangles_culled: usize,
+    pub tiles_tested: usize,
+    pub tiles_culled: usize,
+}
+
+///
+/// A software render pipeline. The renderer owns a depth buffer, a stencil buffer
+/// and a frame buffer of the same dimensions, and draws meshes into them one face at a time. A
+/// multisampling renderer draws into multisample buffers instead, which `resolve`
+/// averages down into the frame buffer. The depth buffer stores values in the
+/// format `D`, which defaults to the scalar type of the pipeline, and the frame
+/// buffer stores pixels in the format `P`, which defaults to 8 bit RGB.
+///
+pub struct Renderer<N, Or, D = N, P = Rgb> where N: Float + RealField, Or: Origin, D: DepthFormat, P: Pixel {
+    z_buffer: Box<ZBuffer<D>>,
+    stencil_buffer: Box<StencilBuffer>,
+    frame_buffer: Box<FrameBuffer<Or, P>>,
+    multisample: Option<Multisample<D, P>>,
+    depth_pyramid: Option<Box<DepthPyramid<D>>>,
+    occlusion_stats: OcclusionStats,
+    viewport: Viewport<N>,
+    scissor: Option<BoundingBox<usize>>,
+    state: RasterState,
+}
+
+///
+/// The render targets of a multisampling renderer. The stencil buffer holds a
+/// value for every sample, with sample `s` of the pixel in column `j` stored in
+/// column `j * count + s`.
+///
+struct Multisample<D, P> where D: DepthFormat, P: Pixel {
+    z_buffer: Box<MultisampleZBuffer<D>>,
+    stencil_buffer: Box<StencilBuffer>,
+    frame_buffer: Box<MultisampleFrameBuffer<P>>,
+}
+
+///
+/// The settings that control how primitives are turned into pixels.
+///
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct RasterState {
+    fill_rule: FillRule,
+    cull_mode: CullMode,
+    front_face: FrontFace,
+    precision: Precision,
+    samples: Option<SampleCount>,
+    polygon_mode: PolygonMode,
+    line_mode: LineMode,
+    point_shape: PointShape,
+    point_size: usize,
+    blend: Option<BlendState>,
+    /// The pixels inside the viewport and the scissor rectangle, or `None` when
+    /// they do not overlap inside the frame.
+    window: Option<BoundingBox<usize>>,
+}
+
+impl<N, Or, D, P> Renderer<N, Or, D, P>
+    where N: Float + RealField,
+          Or: Origin,
+          D: DepthFormat,
+          P: Pixel
+{
+    pub fn new(width: usize, height: usize) -> Renderer<N, Or, D, P> {
+        let mut frame_buffer = Box::new(FrameBuffer::new(width, height));
+        frame_buffer.initialize();
+
+        let mut z_buffer = Box::new(ZBuffer::new(width, height));
+        z_buffer.initialize();
+
+        let mut renderer = Renderer {
+            z_buffer: z_buffer,
+            stencil_buffer: Box::new(StencilBuffer::new(width, height)),
+            frame_buffer: frame_buffer,
+            multisample: None,
+            depth_pyramid: None,
+            occlusion_stats: OcclusionStats::default(),
+            viewport: Viewport::new(0, 0, width, height),
+            scissor: None,
+            state: RasterState {
+                fill_rule: FillRule::Inclusive,
+                cull_mode: CullMode::None,
+                front_face: FrontFace::Clockwise,
+                precision: Precision::Float,
+                samples: None,
+                polygon_mode: PolygonMode::Fill,
+                line_mode: LineMode::Bresenham,
+                point_shape: PointShape::Square,
+                point_size: 1,
+                blend: None,
+                window: None,
+            },
+        };
+        renderer.update_window();
+
+        renderer
+    }
+
+    ///
+    /// Construct a renderer that tests coverage and depth at several samples per
+    /// pixel, while running the shader once per pixel. Call `resolve` after drawing
+    /// to average the samples into the frame buffer.
+    ///
+    pub fn with_samples(width: usize, height: usize, samples: SampleCount) -> Renderer<N, Or, D, P> {
+        let mut renderer = Renderer::new(width, height);
+
+        let mut frame_buffer = Box::new(MultisampleFrameBuffer::new(width, height, samples));
+        frame_buffer.initialize();
+
+        let mut z_buffer = Box::new(MultisampleZBuffer::new(width, height, samples));
+        z_buffer.initialize();
+
+        let mut stencil_buffer = Box::new(StencilBuffer::new(width * samples.count(), height));
+        stencil_buffer.set_state(renderer.stencil_buffer.state());
+
+        renderer.multisample = Some(Multisample {
+            z_buffer: z_buffer,
+            stencil_buffer: stencil_buffer,
+            frame_buffer: frame_buffer,
+        });
+        renderer.state.samples = Some(samples);
+
+        renderer
+    }
+
+    pub fn width(&self) -> usize {
+        self.frame_buffer.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.frame_buffer.height()
+    }
+
+    /// Reset the depth buffer and clear the frame buffer to black, which is
+    /// transparent in pixel formats with an alpha channel.
+    pub fn clear(&mut self) {
+        self.clear_with(P::default());
+    }
+
+    /// Reset the depth buffer and clear the frame buffer to a background color.
+    pub fn clear_with(&mut self, background: P) {
+        let width = self.width();
+        let height = self.height();
+
+        self.z_buffer.initialize();
+        self.stencil_buffer.initialize();
+        for i in 0..height {
+            for j in 0..width {
+                self.frame_buffer[i][j] = background;
+            }
+        }
+
+        if let Some(ref mut multisample) = self.multisample {
+            multisample.z_buffer.initialize();
+            multisample.stencil_buffer.initialize();
+            multisample.frame_buffer.initialize_with(background);
+        }
+    }
+
+    pub fn fill_rule(&self) -> FillRule {
+        self.state.fill_rule
+    }
+
+    /// Select how pixels on triangle edges are assigned. Use `FillRule::TopLeft`
+    /// when pixels on shared edges must be drawn exactly once.
+    pub fn set_fill_rule(&mut self, fill_rule: FillRule) {
+        self.state.fill_rule = fill_rule;
+    }
+
+    pub fn cull_mode(&self) -> CullMode {
+        self.state.cull_mode
+    }
+
+    /// Select which faces are skipped based on their winding in raster space.
+    pub fn set_cull_mode(&mut self, cull_mode: CullMode) {
+        self.state.cull_mode = cull_mode;
+    }
+
+    pub fn front_face(&self) -> FrontFace {
+        self.state.front_face
+    }
+
+    /// Select which winding counts as front facing for culling. Faces in a `Mesh`
+    /// are clockwise by convention.
+    pub fn set_front_face(&mut self, front_face: FrontFace) {
+        self.state.front_face = front_face;
+    }
+
+    pub fn precision(&self) -> Precision {
+        self.state.precision
+    }
+
+    /// Select how edge functions are evaluated. Use `Precision::FixedPoint` for
+    /// coverage that is exact and the same for every scalar type.
+    pub fn set_precision(&mut self, precision: Precision) {
+        self.state.precision = precision;
+    }
+
+    pub fn polygon_mode(&self) -> PolygonMode {
+        self.state.polygon_mode
+    }
+
+    /// Select whether faces are filled, or drawn as a wireframe of their edges.
+    /// Wireframes draw each edge of a mesh once, and ignore the cull mode.
+    pub fn set_polygon_mode(&mut self, polygon_mode: PolygonMode) {
+        self.state.polygon_mode = polygon_mode;
+    }
+
+    pub fn line_mode(&self) -> LineMode {
+        self.state.line_mode
+    }
+
+    /// Select the algorithm used to draw the edges of a wireframe.
+    pub fn set_line_mode(&mut self, line_mode: LineMode) {
+        self.state.line_mode = line_mode;
+    }
+
+    pub fn point_shape(&self) -> PointShape {
+        self.state.point_shape
+    }
+
+    pub fn set_point_shape(&mut self, point_shape: PointShape) {
+        self.state.point_shape = point_shape;
+    }
+
+    pub fn point_size(&self) -> usize {
+        self.state.point_size
+    }
+
+    /// Set the width in pixels of the splats drawn by `draw_points`.
+    pub fn set_point_size(&mut self, point_size: usize) {
+        self.state.point_size = point_size;
+    }
+
+    pub fn depth_range(&self) -> (N, N) {
+        self.viewport.depth_range
+    }
+
+    ///
+    /// Set the range of depths that the near and far planes map to. Both ends must
+    /// lie in [0, 1], and the near end may be larger than the far one. Depths are
+    /// quantized from this range into the format of the depth buffer.
+    ///
+    pub fn set_depth_range(&mut self, near: N, far: N) {
+        assert!((N::zero() <= near) && (near <= N::one()));
+        assert!((N::zero() <= far) && (far <= N::one()));
+
+        self.viewport.depth_range = (near, far);
+    }
+
+    pub fn viewport(&self) -> Viewport<N> {
+        self.viewport
+    }
+
+    ///
+    /// Set the block of the frame to draw into and its depth range. Only pixels
+    /// inside the viewport are drawn, so `m_total` should end with the viewport's
+    /// own `matrix` to place the view volume onto it.
+    ///
+    pub fn set_viewport(&mut self, viewport: Viewport<N>) {
+        let (near, far) = viewport.depth_range;
+        self.set_depth_range(near, far);
+        self.viewport = viewport;
+        self.update_window();
+    }
+
+    pub fn scissor(&self) -> Option<BoundingBox<usize>> {
+        self.scissor
+    }
+
+    ///
+    /// Restrict drawing to the half open ranges of pixels in a scissor rectangle,
+    /// on top of the viewport. Drawing is not restricted when this is `None`,
+    /// which is the default. Clearing the buffers ignores the scissor rectangle.
+    ///
+    pub fn set_scissor(&mut self, scissor: Option<BoundingBox<usize>>) {
+        self.scissor = scissor;
+        self.update_window();
+    }
+
+    fn update_window(&mut self) {
+        let frame = BoundingBox {
+            x_min: 0,
+            x_max: self.width(),
+            y_min: 0,
+            y_max: self.height(),
+        };
+        let window = raster::intersect_bounds(&frame, &self.viewport.bounds());
+        self.state.window = match (window, self.scissor) {
+            (Some(window), Some(ref scissor)) => raster::intersect_bounds(&window, scissor),
+            (window, _) => window,
+        };
+    }
+
+    pub fn depth_convention(&self) -> DepthConvention {
+        self.z_buffer.convention()
+    }
+
+    ///
+    /// Switch the depth buffers to a depth convention. This clears them and resets
+    /// the depth test to the one that keeps the closest surfaces. Draw reversed-Z
+    /// scenes with one of the reversed projections in `raster`. Storing depths in a
+    /// floating point buffer of lower precision than `N` gives reversed-Z the most
+    /// to gain.
+    ///
+    pub fn set_depth_convention(&mut self, convention: DepthConvention) {
+        self.z_buffer.set_convention(convention);
+        if let Some(ref mut multisample) = self.multisample {
+            multisample.z_buffer.set_convention(convention);
+        }
+    }
+
+    /// The tile size of the depth pyramid, when occlusion culling is enabled.
+    pub fn occlusion_culling(&self) -> Option<usize> {
+        self.depth_pyramid.as_ref().map(|pyramid| pyramid.tile_size())
+    }
+
+    ///
+    /// Enable occlusion culling with a depth pyramid over tiles of `tile_size` x
+    /// `tile_size` pixels, or disable it with `None`. While it is enabled,
+    /// `draw_mesh` rejects triangles and tiles whose nearest vertex depth is hidden
+    /// by what the depth buffer already holds, before visiting their pixels. This
+    /// pays off when meshes are drawn roughly front to back. Multisampling
+    /// renderers and `draw_mesh_tiled` do not cull.
+    ///
+    pub fn set_occlusion_culling(&mut self, tile_size: Option<usize>) {
+        self.depth_pyramid = tile_size.map(|tile_size| {
+            Box::new(DepthPyramid::new(self.width(), self.height(), tile_size))
+        });
+    }
+
+    pub fn depth_pyramid(&self) -> Option<&DepthPyramid<D>> {
+        self.depth_pyramid.as_deref()
+    }
+
+    /// The statistics gathered by occlusion culling since the last reset.
+    pub fn occlusion_stats(&self) -> OcclusionStats {
+        self.occlusion_stats
+    }
+
+    pub fn reset_occlusion_stats(&mut self) {
+        self.occlusion_stats = OcclusionStats::default();
+    }
+
+    pub fn depth_compare(&self) -> CompareFunction {
+        self.z_buffer.compare_function()
+    }
+
+    /// Set the function that decides whether a fragment passes the depth test.
+    pub fn set_depth_compare(&mut self, compare: CompareFunction) {
+        self.z_buffer.set_compare_function(compare);
+        if let Some(ref mut multisample) = self.multisample {
+            multisample.z_buffer.set_compare_function(compare);
+        }
+    }
+
+    pub fn depth_write(&self) -> bool {
+        self.z_buffer.depth_write()
+    }
+
+    ///
+    /// Enable or disable depth writes. Fragments are still depth tested while
+    /// writes are disabled, which suits overlays and decals drawn over a scene.
+    ///
+    pub fn set_depth_write(&mut self, write: bool) {
+        self.z_buffer.set_depth_write(write);
+        if let Some(ref mut multisample) = self.multisample {
+            multisample.z_buffer.set_depth_write(write);
+        }
+    }
+
+    pub fn z_buffer(&self) -> &ZBuffer<D> {
+        &self.z_buffer
+    }
+
+    pub fn stencil_state(&self) -> StencilState {
+        self.stencil_buffer.state()
+    }
+
+    ///
+    /// Set the stencil test and the operations applied by its outcome. The default
+    /// state passes every fragment and leaves the stencil buffer alone. Lines and
+    /// points are stencil tested like triangles, and multisampling renderers test
+    /// each sample against its own stencil value.
+    ///
+    pub fn set_stencil_state(&mut self, state: StencilState) {
+        self.stencil_buffer.set_state(state);
+        if let Some(ref mut multisample) = self.multisample {
+            multisample.stencil_buffer.set_state(state);
+        }
+    }
+
+    pub fn stencil_buffer(&self) -> &StencilBuffer {
+        &self.stencil_buffer
+    }
+
+    pub fn stencil_buffer_mut(&mut self) -> &mut StencilBuffer {
+        &mut self.stencil_buffer
+    }
+
+    pub fn blend_state(&self) -> Option<BlendState> {
+        self.state.blend
+    }
+
+    ///
+    /// Blend shaded fragments with the frame buffer, or overwrite it with them
+    /// when the blend state is `None`, which is the default. Transparent passes
+    /// usually also turn off depth writes with `set_depth_write`.
+    ///
+    pub fn set_blend_state(&mut self, blend: Option<BlendState>) {
+        self.state.blend = blend;
+    }
+
+    pub fn frame_buffer(&self) -> &FrameBuffer<Or, P> {
+        &self.frame_buffer
+    }
+
+    pub fn frame_buffer_mut(&mut self) -> &mut FrameBuffer<Or, P> {
+        &mut self.frame_buffer
+    }
+
+    pub fn samples(&self) -> Option<SampleCount> {
+        self.state.samples
+    }
+
+    pub fn multisample_z_buffer(&self) -> Option<&MultisampleZBuffer<D>> {
+        self.multisample.as_ref().map(|multisample| &*multisample.z_buffer)
+    }
+
+    ///
+    /// Returns the stencil buffer of a multisampling renderer, which stores the
+    /// samples of each pixel side by side in its rows.
+    ///
+    pub fn multisample_stencil_buffer(&self) -> Option<&StencilBuffer> {
+        self.multisample.as_ref().map(|multisample| &*multisample.stencil_buffer)
+    }
+
+    pub fn multisample_frame_buffer(&self) -> Option<&MultisampleFrameBuffer<P>> {
+        self.multisample.as_ref().map(|multisample| &*multisample.frame_buffer)
+    }
+
+    ///
+    /// Average the samples of the multisample frame buffer into the frame buffer.
+    /// This does nothing when the renderer is not multisampling.
+    ///
+    pub fn resolve(&mut self) {
+        if let Some(ref multisample) = self.multisample {
+            multisample.frame_buffer.resolve_into(&mut self.frame_buffer);
+        }
+    }
+
+    ///
+    /// Draw every face of a mesh. The matrix `m_total` takes vertices from world
+    /// space to raster space. Faces are clipped against the near and far planes
+    /// before the perspective divide. Each covered pixel that passes the depth test
+    /// is colored by the shader.
+    ///
+    pub fn draw_mesh<S, C>(&mut self, mesh: &Mesh<N>, m_total: &Matrix4<N>, shader: &S)
+        where S: Fn(&Fragment<N>) -> C,
+              C: FragmentColor<N>
+    {
+        if self.state.polygon_mode == PolygonMode::Line {
+            return self.draw_edges(mesh, m_total, shader);
+        }
+
+        let bounds = BoundingBox {
+            x_min: 0,
+            x_max: self.width(),
+            y_min: 0,
+            y_max: self.height(),
+        };
+
+        // Skipping hidden triangles would also skip the stencil operations they
+        // apply, so only cull when those leave the stencil buffer alone.
+        let stencil = self.stencil_buffer.state();
+        let cull = (stencil.fail == StencilOp::Keep) && (stencil.depth_fail == StencilOp::Keep);
+
+        let primitives = self.primitives(mesh, m_total);
+        match self.multisample {
+            Some(ref mut multisample) => {
+                for primitive in primitives.iter() {
+                    rasterize_multisample(&self.state,
+                                          primitive,
+                                          &mut multisample.z_buffer,
+                                          &mut multisample.stencil_buffer,
+                                          &mut multisample.frame_buffer,
+                                          shader);
+                }
+            }
+            None => match self.depth_pyramid {
+                Some(ref mut pyramid) if cull => {
+                    // The depth buffer may have changed since the last draw, so
+                    // bring the pyramid up to date first.
+                    pyramid.build(&self.z_buffer);
+                    for primitive in primitives.iter() {
+                        rasterize_unoccluded(&self.state,
+                                             primitive,
+                                             pyramid,
+                                             &mut self.occlusion_stats,
+                                             &mut self.z_buffer,
+                                             &mut self.stencil_buffer,
+                                             &mut self.frame_buffer,
+                                             shader);
+                    }
+                }
+                _ => {
+                    for primitive in primitives.iter() {
+                        rasterize(&self.state,
+                                  primitive,
+                                  &bounds,
+                                  &mut self.z_buffer,
+                                  &mut self.stencil_buffer,
+                                  &mut self.frame_buffer,
+                                  shader);
+                    }
+                }
+            }
+        }
+    }
+
+    ///
+    /// Draw every face of a mesh like `draw_mesh`, but split the frame into square
+    /// tiles of `tile_size` pixels and shade them on `threads` threads. Each tile
+    /// draws the primitives overlapping it in the order they were submitted, so
+    /// the output is identical to that of `draw_mesh`. Multisampling renderers
+    /// and wireframes draw on the calling thread.
+    ///
+    pub fn draw_mesh_tiled<S, C>(&mut self,
+                                 mesh: &Mesh<N>,
+                                 m_total: &Matrix4<N>,
+                                 shader: &S,
+                                 tile_size: usize,
+                                 threads: usize)
+        where S: Fn(&Fragment<N>) -> C + Sync,
+              C: FragmentColor<N>,
+              N: Send + Sync,
+              Or: Send,
+              D: Send
+    {
+        assert!(tile_size > 0);
+        assert!(threads > 0);
+
+        if self.multisample.is_some() || (self.state.polygon_mode == PolygonMode::Line) {
+            return self.draw_mesh(mesh, m_total, shader);
+        }
+
+        let primitives = self.primitives(mesh, m_total);
+        let tiles_x = (self.width() + tile_size - 1) / tile_size;
+        let tiles_y = (self.height() + tile_size - 1) / tile_size;
+
+        let mut tiles = Vec::with_capacity(tiles_x * tiles_y);
+        for tile_y in 0..tiles_y {
+            for tile_x in 0..tiles_x {
+                let bounds = BoundingBox {
+                    x_min: tile_x * tile_size,
+                    x_max: Ord::min((tile_x + 1) * tile_size, self.width()),
+                    y_min: tile_y * tile_size,
+                    y_max: Ord::min((tile_y + 1) * tile_size, self.height()),
+                };
+                tiles.push(self.read_tile(bounds));
+            }
+        }
+
+        // Bin the primitives into the tiles they overlap.
+        for (index, primitive) in primitives.iter().enumerate() {
+            for tile_y in (primitive.bbox.y_min / tile_size)..(primitive.bbox.y_max / tile_size + 1) {
+                for tile_x in (primitive.bbox.x_min / tile_size)..(primitive.bbox.x_max / tile_size + 1) {
+                    tiles[tile_y * tiles_x + tile_x].primitives.push(index);
+                }
+            }
+        }
+
+        // Deal the tiles out to the threads in turn, so that each thread gets a
+        // share of every part of the frame.
+        let mut batches: Vec<Vec<Tile<Or, D, P>>> = (0..threads).map(|_| Vec::new()).collect();
+        for (index, tile) in tiles.into_iter().enumerate() {
+            batches[index % threads].push(tile);
+        }
+
+        let state = &self.state;
+        let primitives = &primitives;
+        let batches: Vec<Vec<Tile<Or, D, P>>> = thread::scope(|scope| {
+            let handles: Vec<_> = batches.into_iter().map(|mut batch| {
+                scope.spawn(move || {
+                    for tile in batch.iter_mut() {
+                        tile.draw(state, primitives, shader);
+                    }
+
+                    batch
+                })
+            }).collect();
+
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        for batch in batches {
+            for tile in batch {
+                self.write_tile(&tile);
+            }
+        }
+    }
+
+    ///
+    /// Transform, clip and set up every face of a mesh for rasterization.
+    ///
+    fn primitives(&self, mesh: &Mesh<N>, m_total: &Matrix4<N>) -> Vec<Primitive<N>> {
+        let verts = mesh.vertices();
+        let mut primitives = Vec::with_capacity(mesh.face_count());
+
+        for (face_idx, face) in mesh.faces().iter().enumerate() {
+            let v0 = *m_total * verts[face[0]].to_homogeneous();
+            let v1 = *m_total * verts[face[1]].to_homogeneous();
+            let v2 = *m_total * verts[face[2]].to_homogeneous();
+
+            for triangle in clip::clip_triangle(&v0, &v1, &v2) {
+                let primitive = Primitive::setup(&self.state,
+                                                 face_idx,
+                                                 triangle,
+                                                 self.viewport.depth_range,
+                                                 self.width(),
+                                                 self.height());
+                if let Some(primitive) = primitive {
+                    primitives.push(primitive);
+                }
+            }
+        }
+
+        primitives
+    }
+
+    ///
+    /// Draw every vertex of a mesh as a point, whether or not it belongs to a face.
+    /// The matrix `m_total` takes vertices from world space to raster space, and
+    /// vertices outside of the near and far planes are skipped. Each pixel of a
+    /// point's splat that passes the depth test is colored by the shader.
+    ///
+    pub fn draw_points<S, C>(&mut self, mesh: &Mesh<N>, m_total: &Matrix4<N>, shader: &S)
+        where S: Fn(&PointFragment<N>) -> C,
+              C: FragmentColor<N>
+    {
+        let _1 = N::one();
+        let (width, height) = (self.width(), self.height());
+        let point_shape = self.state.point_shape;
+        let point_size = util::cast(self.state.point_size);
+
+        for (vertex, v) in mesh.vertices().iter().enumerate() {
+            let p = *m_total * v.to_homogeneous();
+
+            // Keep the points inside either cone of the view volume, as in
+            // `clip::clip_triangle`.
+            if !((p.w >= Float::abs(p.z)) || (-p.w >= Float::abs(p.z))) {
+                continue;
+            }
+            let position = match Point3::from_homogeneous(p) {
+                Some(position) => position,
+                None => continue,
+            };
+            let depth = window_depth(position.z, self.viewport.depth_range);
+
+            let z_buffer = &mut self.z_buffer;
+            let stencil_buffer = &mut self.stencil_buffer;
+            let frame_buffer = &mut self.frame_buffer;
+            let multisample = &mut self.multisample;
+            let blend = self.state.blend;
+            let window = self.state.window;
+            raster::rasterize_point(point_shape, &position, point_size, width, height, |x, y| {
+                if !in_window(&window, x, y) {
+                    return;
+                }
+                let fragment = PointFragment {
+                    x: x,
+                    y: y,
+                    depth: depth,
+                    vertex: vertex,
+                    position: position,
+                };
+                match *multisample {
+                    Some(ref mut multisample) => {
+                        plot_pixel_multisample(&fragment,
+                                               x,
+                                               y,
+                                               depth,
+                                               _1,
+                                               &mut multisample.z_buffer,
+                                               &mut multisample.stencil_buffer,
+                                               &mut multisample.frame_buffer,
+                                               blend,
+                                               shader);
+                    }
+                    None => {
+                        plot_pixel(&fragment, x, y, depth, _1, z_buffer, stencil_buffer, frame_buffer, blend, shader)
+                    }
+                }
+            });
+        }
+    }
+
+    ///
+    /// Draw the unique edges of the faces of a mesh as lines. The fragments of an
+    /// edge belong to the first face that uses it.
+    ///
+    fn draw_edges<S, C>(&mut self, mesh: &Mesh<N>, m_total: &Matrix4<N>, shader: &S)
+        where S: Fn(&Fragment<N>) -> C,
+              C: FragmentColor<N>
+    {
+        let _0 = N::zero();
+        let _1 = N::one();
+        let (width, height) = (self.width(), self.height());
+        let line_mode = self.state.line_mode;
+        let depth_range = self.viewport.depth_range;
+        let verts = mesh.vertices();
+        let faces = mesh.faces();
+
+        for edge in mesh.edges() {
+            let face = &faces[edge.face];
+            let corner = |v: usize| {
+                if v == face.v0 {
+                    Point3::new(_1, _0, _0)
+                } else if v == face.v1 {
+                    Point3::new(_0, _1, _0)
+                } else {
+                    Point3::new(_0, _0, _1)
+                }
+            };
+            let a = ClipVertex::new(*m_total * verts[edge.v0].to_homogeneous(), corner(edge.v0));
+            let b = ClipVertex::new(*m_total * verts[edge.v1].to_homogeneous(), corner(edge.v1));
+
+            let (a, b) = match clip::clip_line(&a, &b) {
+                Some(segment) => segment,
+                None => continue,
+            };
+            let (p0, p1) = match (Point3::from_homogeneous(a.position), Point3::from_homogeneous(b.position)) {
+                (Some(p0), Some(p1)) => (p0, p1),
+                _ => continue,
+            };
+
+            let z_buffer = &mut self.z_buffer;
+            let stencil_buffer = &mut self.stencil_buffer;
+            let frame_buffer = &mut self.frame_buffer;
+            let multisample = &mut self.multisample;
+            let blend = self.state.blend;
+            let window = self.state.window;
+            raster::rasterize_line(line_mode, &p0, &p1, width, height, |x, y, t, coverage| {
+                if !in_window(&window, x, y) {
+                    return;
+                }
+                let fragment = line_fragment(edge.face, &a, &b, &p0, &p1, x, y, t, depth_range);
+                let depth = fragment.depth;
+                match *multisample {
+                    Some(ref mut multisample) => {
+                        plot_pixel_multisample(&fragment,
+                                               x,
+                                               y,
+                                               depth,
+                                               coverage,
+                                               &mut multisample.z_buffer,
+                                               &mut multisample.stencil_buffer,
+                                               &mut multisample.frame_buffer,
+                                               blend,
+                                               shader);
+                    }
+                    None => {
+                        plot_pixel(&fragment, x, y, depth, coverage, z_buffer, stencil_buffer, frame_buffer, blend, shader)
+                    }
+                }
+            });
+        }
+    }
+
+    /// Copy a block of the frame, depth and stencil buffers into a new tile.
+    fn read_tile(&self, bounds: BoundingBox<usize>) -> Tile<Or, D, P> {
+        let width = bounds.x_max - bounds.x_min;
+        let height = bounds.y_max - bounds.y_min;
+
+        let mut frame_buffer = FrameBuffer::new(width, height);
+        frame_buffer.initialize();
+        let mut z_buffer = ZBuffer::with_convention(width, height, self.z_buffer.convention());
+        z_buffer.set_compare_function(self.z_buffer.compare_function());
+        z_buffer.set_depth_write(self.z_buffer.depth_write());
+        let mut stencil_buffer = StencilBuffer::new(width, height);
+        stencil_buffer.set_state(self.stencil_buffer.state());
+
+        let columns = bounds.x_min..bounds.x_max;
+        for y in 0..height {
+            frame_buffer[y].copy_from_slice(&self.frame_buffer[bounds.y_min + y][columns.clone()]);
+            z_buffer[y].copy_from_slice(&self.z_buffer[bounds.y_min + y][columns.clone()]);
+            stencil_buffer[y].copy_from_slice(&self.stencil_buffer[bounds.y_min + y][columns.clone()]);
+        }
+
+        Tile {
+            bounds: bounds,
+            z_buffer: z_buffer,
+            stencil_buffer: stencil_buffer,
+            frame_buffer: frame_buffer,
+            primitives: Vec::new(),
+        }
+    }
+
+    /// Copy the contents of a tile back into the frame, depth and stencil buffers.
+    fn write_tile(&mut self, tile: &Tile<Or, D, P>) {
+        let bounds = &tile.bounds;
+        let columns = bounds.x_min..bounds.x_max;
+        for y in 0..(bounds.y_max - bounds.y_min) {
+            self.frame_buffer[bounds.y_min + y][columns.clone()].copy_from_slice(&tile.frame_buffer[y]);
+            self.z_buffer[bounds.y_min + y][columns.clone()].copy_from_slice(&tile.z_buffer[y]);
+            self.stencil_buffer[bounds.y_min + y][columns.clone()].copy_from_slice(&tile.stencil_buffer[y]);
+        }
+    }
+}
+
+///
+/// A clipped triangle projected into raster space and ready to be scan converted.
+///
+struct Primitive<N> where N: Float + RealField {
+    face: usize,
+    triangle: [ClipVertex<N>; 3],
+    v0: Point3<N>,
+    v1: Point3<N>,
+    v2: Point3<N>,
+    depth: [N; 3],
+    area: N,
+    top_left: [bool; 3],
+    bbox: BoundingBox<usize>,
+    fixed: Option<FixedPrimitive>,
+}
+
+///
+/// The vertices of a primitive snapped to the fixed point subpixel grid.
+///
+struct FixedPrimitive {
+    vertices: [(i64, i64); 3],
+    area: i64,
+}
+
+impl<N> Primitive<N> where N: Float + RealField {
+    ///
+    /// Project a clipped triangle into raster space. This returns `None` when the
+    /// triangle is degenerate, culled, or lies outside a `width` x `height` frame.
+    ///
+    fn setup(state: &RasterState,
+             face: usize,
+             triangle: [ClipVertex<N>; 3],
+             depth_range: (N, N),
+             width: usize,
+             height: usize) -> Option<Primitive<N>>
+    {
+        let _0 = N::zero();
+
+        // Clipping leaves only vertices with a nonzero w, except in the degenerate
+        // case where a corner of the triangle sits exactly on the eye.
+        let (v0, v1, v2) = match (Point3::from_homogeneous(triangle[0].position),
+                                  Point3::from_homogeneous(triangle[1].position),
+                                  Point3::from_homogeneous(triangle[2].position)) {
+            (Some(v0), Some(v1), Some(v2)) => (v0, v1, v2),
+            _ => return None,
+        };
+
+        // Fall back to floating point for vertices too far off screen to snap.
+        let fixed = match state.precision {
+            Precision::FixedPoint => {
+                match (raster::snap_to_subpixel(&v0),
+                       raster::snap_to_subpixel(&v1),
+                       raster::snap_to_subpixel(&v2)) {
+                    (Some(s0), Some(s1), Some(s2)) => Some([s0, s1, s2]),
+                    _ => None,
+                }
+            }
+            Precision::Float => None,
+        };
+
+        // The signed area carries the winding of the face, so dividing by it
+        // makes the weights of interior points positive for either winding.
+        let area = raster::compute_edge(&v0, &v1, &v2);
+
+        let (winding, bbox, top_left, fixed) = match fixed {
+            Some(vertices) => {
+                // Cull the snapped triangle, which may be degenerate even when
+                // the original one is not.
+                let area = raster::compute_area_fixed(vertices[0], vertices[1], vertices[2]);
+                let winding = if area > 0 { N::one() } else if area < 0 { -N::one() } else { _0 };
+                let bbox = raster::clip_bounding_box_fixed(vertices[0], vertices[1], vertices[2], width, height);
+                let top_left = [raster::is_top_left_edge_fixed(vertices[1], vertices[2], area),
+                                raster::is_top_left_edge_fixed(vertices[2], vertices[0], area),
+                                raster::is_top_left_edge_fixed(vertices[0], vertices[1], area)];
+                let fixed = FixedPrimitive {
+                    vertices: vertices,
+                    area: area,
+                };
+
+                (winding, bbox, top_left, Some(fixed))
+            }
+            None => {
+                let bbox = raster::bounding_box(&v0, &v1, &v2);
+                let bbox = raster::clip_bounding_box(&bbox, width, height);
+                let top_left = [raster::is_top_left_edge(&v1, &v2, area),
+                                raster::is_top_left_edge(&v2, &v0, area),
+                                raster::is_top_left_edge(&v0, &v1, area)];
+
+                (area, bbox, top_left, None)
+            }
+        };
+
+        if (winding == _0) || raster::is_culled(state.cull_mode, state.front_face, winding) {
+            return None;
+        }
+
+        // Samples sit less than half a pixel from their pixel center, so grow the
+        // box by that much to reach every pixel with a sample inside the triangle.
+        let bbox = match state.samples {
+            Some(_) => {
+                let half = N::one() / (N::one() + N::one());
+                let bbox = raster::bounding_box(&v0, &v1, &v2);
+                let bbox = BoundingBox {
+                    x_min: bbox.x_min - half,
+                    x_max: bbox.x_max + half,
+                    y_min: bbox.y_min - half,
+                    y_max: bbox.y_max + half,
+                };
+                raster::clip_bounding_box(&bbox, width, height)
+            }
+            None => bbox,
+        };
+
+        // Only walk the pixels under the triangle inside the viewport and scissor
+        // rectangle, and skip it entirely when it falls outside of them.
+        let bbox = match (bbox, state.window) {
+            (Some(bbox), Some(window)) => BoundingBox {
+                x_min: Ord::max(bbox.x_min, window.x_min),
+                x_max: Ord::min(bbox.x_max, window.x_max - 1),
+                y_min: Ord::max(bbox.y_min, window.y_min),
+                y_max: Ord::min(bbox.y_max, window.y_max - 1),
+            },
+            _ => return None,
+        };
+        if (bbox.x_min > bbox.x_max) || (bbox.y_min > bbox.y_max) {
+            return None;
+        }
+
+        Some(Primitive {
+            face: face,
+            triangle: triangle,
+            v0: v0,
+            v1: v1,
+            v2: v2,
+            depth: [window_depth(v0.z, depth_range),
+                    window_depth(v1.z, depth_range),
+                    window_depth(v2.z, depth_range)],
+            area: area,
+            top_left: top_left,
+            bbox: bbox,
+            fixed: fixed,
+        })
+    }
+}
+
+///
+/// A rectangular block of the frame with its own copy of the frame, depth and stencil buffers,
+/// along with the primitives that overlap it.
+///
+struct Tile<Or, D, P> where Or: Origin, D: DepthFormat, P: Pixel {
+    bounds: BoundingBox<usize>,
+    z_buffer: ZBuffer<D>,
+    stencil_buffer: StencilBuffer,
+    frame_buffer: FrameBuffer<Or, P>,
+    primitives: Vec<usize>,
+}
+
+impl<Or, D, P> Tile<Or, D, P>
+    where Or: Origin,
+          D: DepthFormat,
+          P: Pixel
+{
+    fn draw<N, S, C>(&mut self, state: &RasterState, primitives: &[Primitive<N>], shader: &S)
+        where N: Float + RealField,
+              S: Fn(&Fragment<N>) -> C,
+              C: FragmentColor<N>
+    {
+        for &index in self.primitives.iter() {
+            rasterize(state,
+                      &primitives[index],
+                      &self.bounds,
+                      &mut self.z_buffer,
+                      &mut self.stencil_buffer,
+                      &mut self.frame_buffer,
+                      shader);
+        }
+    }
+}
+
+///
+/// Scan convert a primitive into buffers covering the whole frame, skipping it
+/// where the depth pyramid shows that it is hidden, and keep the pyramid up to
+/// date with the depths it writes.
+///
+fn rasterize_unoccluded<N, Or, D, P, S, C>(state: &RasterState,
+                                           primitive: &Primitive<N>,
+                                           pyramid: &mut DepthPyramid<D>,
+                                           stats: &mut OcclusionStats,
+                                           z_buffer: &mut ZBuffer<D>,
+                                           stencil_buffer: &mut StencilBuffer,
+                                           frame_buffer: &mut FrameBuffer<Or, P>,
+                                           shader: &S)
+    where N: Float + RealField,
+          Or: Origin,
+          D: DepthFormat,
+          P: Pixel,
+          S: Fn(&Fragment<N>) -> C,
+          C: FragmentColor<N>
+{
+    // The depths inside a triangle are weighted averages of the depths at its
+    // vertices, so none of them is nearer than the nearest vertex.
+    let nearest = pyramid.nearest(&[D::from_depth(primitive.depth[0]),
+                                    D::from_depth(primitive.depth[1]),
+                                    D::from_depth(primitive.depth[2])]);
+
+    stats.triangles_tested += 1;
+    if pyramid.is_occluded(&primitive.bbox, nearest) {
+        stats.triangles_culled += 1;
+        return;
+    }
+
+    let tile_size = pyramid.tile_size();
+    let (width, height) = (pyramid.width(), pyramid.height());
+    let frame = BoundingBox {
+        x_min: 0,
+        x_max: width,
+        y_min: 0,
+        y_max: height,
+    };
+    for i in (primitive.bbox.y_min / tile_size)..(primitive.bbox.y_max / tile_size + 1) {
+        for j in (primitive.bbox.x_min / tile_size)..(primitive.bbox.x_max / tile_size + 1) {
+            stats.tiles_tested += 1;
+            if pyramid.is_tile_occluded(i, j, nearest) {
+                stats.tiles_culled += 1;
+                continue;
+            }
+
+            let tile = BoundingBox {
+                x_min: j * tile_size,
+                x_max: Ord::min((j + 1) * tile_size, width),
+                y_min: i * tile_size,
+                y_max: Ord::min((i + 1) * tile_size, height),
+            };
+            rasterize_within(state, primitive, &frame, &tile, z_buffer, stencil_buffer, frame_buffer, shader);
+        }
+    }
+
+    if z_buffer.depth_write() {
+        pyramid.update(z_buffer, &primitive.bbox);
+    }
+}
+
+///
+/// Scan convert a primitive inside of `bounds`, which holds the half open ranges of
+/// pixels covered by the buffers. Pixels are passed to the shader in raster space,
+/// and written to the buffers relative to the corner of `bounds`.
+///
+fn rasterize<N, Or, D, P, S, C>(state: &RasterState,
+                                primitive: &Primitive<N>,
+                                bounds: &BoundingBox<usize>,
+                                z_buffer: &mut ZBuffer<D>,
+                                stencil_buffer: &mut StencilBuffer,
+                                frame_buffer: &mut FrameBuffer<Or, P>,
+                                shader: &S)
+    where N: Float + RealField,
+          Or: Origin,
+          D: DepthFormat,
+          P: Pixel,
+          S: Fn(&Fragment<N>) -> C,
+          C: FragmentColor<N>
+{
+    rasterize_within(state, primitive, bounds, bounds, z_buffer, stencil_buffer, frame_buffer, shader);
+}
+
+///
+/// Scan convert the part of a primitive inside of the half open pixel ranges of
+/// `window`, into buffers covering `bounds`.
+///
+fn rasterize_within<N, Or, D, P, S, C>(state: &RasterState,
+                                       primitive: &Primitive<N>,
+                                       bounds: &BoundingBox<usize>,
+                                       window: &BoundingBox<usize>,
+                                       z_buffer: &mut ZBuffer<D>,
+                                       stencil_buffer: &mut StencilBuffer,
+                                       frame_buffer: &mut FrameBuffer<Or, P>,
+                                       shader: &S)
+    where N: Float + RealField,
+          Or: Origin,
+          D: DepthFormat,
+          P: Pixel,
+          S: Fn(&Fragment<N>) -> C,
+          C: FragmentColor<N>
+{
+    let x_min = Ord::max(primitive.bbox.x_min, window.x_min);
+    let x_max = Ord::min(primitive.bbox.x_max + 1, window.x_max);
+    let y_min = Ord::max(primitive.bbox.y_min, window.y_min);
+    let y_max = Ord::min(primitive.bbox.y_max + 1, window.y_max);
+    if (x_min >= x_max) || (y_min >= y_max) {
+        return;
+    }
+
+    match primitive.fixed {
+        Some(ref fixed) => {
+            let (s0, s1, s2) = (fixed.vertices[0], fixed.vertices[1], fixed.vertices[2]);
+            let area = <N as NumCast>::from(fixed.area).unwrap();
+
+            // Evaluate the edge functions once at the first pixel, and step them
+            // across the rows from there.
+            let mut row = [FixedEdge::new(s1, s2, x_min, y_min),
+                           FixedEdge::new(s2, s0, x_min, y_min),
+                           FixedEdge::new(s0, s1, x_min, y_min)];
+            for y in y_min..y_max {
+                let mut edges = row;
+                for x in x_min..x_max {
+                    let w = [edges[0].value, edges[1].value, edges[2].value];
+                    if raster::is_covered_fixed(state.fill_rule, &w, fixed.area, &primitive.top_left) {
+                        let w = Point3::new(<N as NumCast>::from(w[0]).unwrap(),
+                                            <N as NumCast>::from(w[1]).unwrap(),
+                                            <N as NumCast>::from(w[2]).unwrap());
+                        shade(state, primitive, x, y, w / area, bounds, z_buffer, stencil_buffer, frame_buffer, shader);
+                    }
+
+                    for edge in edges.iter_mut() {
+                        edge.step_x();
+                    }
+                }
+
+                for edge in row.iter_mut() {
+                    edge.step_y();
+                }
+            }
+        }
+        None => {
+            let (v0, v1, v2) = (&primitive.v0, &primitive.v1, &primitive.v2);
+            let area = primitive.area;
+
+            for y in y_min..y_max {
+                for x in x_min..x_max {
+                    let pixel = Point3::new(util::cast(x), util::cast(y), N::zero());
+                    let w = match state.fill_rule {
+                        FillRule::Inclusive => raster::barycentric_coords(v0, v1, v2, &pixel),
+                        FillRule::TopLeft => raster::barycentric_coords_watertight(v0, v1, v2, &pixel),
+                    };
+                    if raster::is_covered(state.fill_rule, &w, area, &primitive.top_left) {
+                        shade(state, primitive, x, y, w / area, bounds, z_buffer, stencil_buffer, frame_buffer, shader);
+                    }
+                }
+            }
+        }
+    }
+}
+
+///
+/// Stencil and depth test a covered pixel with normalized barycentric coordinates `w`,
+/// and on success run the shader and write the result to the buffers.
+///
+#[inline]
+fn shade<N, Or, D, P, S, C>(state: &RasterState,
+                            primitive: &Primitive<N>,
+                            x: usize,
+                            y: usize,
+                            w: Point3<N>,
+                            bounds: &BoundingBox<usize>,
+                            z_buffer: &mut ZBuffer<D>,
+                            stencil_buffer: &mut StencilBuffer,
+                            frame_buffer: &mut FrameBuffer<Or, P>,
+                            shader: &S)
+    where N: Float + RealField,
+          Or: Origin,
+          D: DepthFormat,
+          P: Pixel,
+          S: Fn(&Fragment<N>) -> C,
+          C: FragmentColor<N>
+{
+    let depth = interpolate_depth(primitive, &w);
+    let stored = D::from_depth(depth);
+    let (i, j) = (y - bounds.y_min, x - bounds.x_min);
+    if stencil_buffer.test_and_update(i, j, || z_buffer.test_and_update(stored, i, j)) {
+        let fragment = fragment(primitive, x, y, w, depth);
+        let color = shader(&fragment).rgba();
+        write_pixel(state.blend, &mut frame_buffer[i][j], &color);
+    }
+}
+
+///
+/// Scan convert a primitive into multisample buffers. Each sample covered by the
+/// primitive is stencil and depth tested on its own, and the shader runs once at
+/// the pixel center for every pixel with a sample that passes.
+///
+fn rasterize_multisample<N, D, P, S, C>(state: &RasterState,
+                                        primitive: &Primitive<N>,
+                                        z_buffer: &mut MultisampleZBuffer<D>,
+                                        stencil_buffer: &mut StencilBuffer,
+                                        frame_buffer: &mut MultisampleFrameBuffer<P>,
+                                        shader: &S)
+    where N: Float + RealField,
+          D: DepthFormat,
+          P: Pixel,
+          S: Fn(&Fragment<N>) -> C,
+          C: FragmentColor<N>
+{
+    let _0 = N::zero();
+    let _16: N = util::cast(16);
+
+    let positions = frame_buffer.samples().positions();
+    let count = positions.len();
+    let (v0, v1, v2) = (&primitive.v0, &primitive.v1, &primitive.v2);
+    let bbox = &primitive.bbox;
+
+    // Weigh a point at an offset in sixteenths of a pixel from the center of the
+    // pixel `(x, y)`, returning whether the primitive covers it along with its
+    // normalized barycentric coordinates.
+    let weigh = |x: usize, y: usize, dx: i64, dy: i64| -> (bool, Point3<N>) {
+        match primitive.fixed {
+            Some(ref fixed) => {
+                let (s0, s1, s2) = (fixed.vertices[0], fixed.vertices[1], fixed.vertices[2]);
+                let scale = 1 << (raster::SUBPIXEL_BITS - 4);
+                let w = [FixedEdge::new(s1, s2, x, y).value_at(dx * scale, dy * scale),
+                         FixedEdge::new(s2, s0, x, y).value_at(dx * scale, dy * scale),
+                         FixedEdge::new(s0, s1, x, y).value_at(dx * scale, dy * scale)];
+                let covered = raster::is_covered_fixed(state.fill_rule, &w, fixed.area, &primitive.top_left);
+                let area = <N as NumCast>::from(fixed.area).unwrap();
+                let w = Point3::new(<N as NumCast>::from(w[0]).unwrap(),
+                                    <N as NumCast>::from(w[1]).unwrap(),
+                                    <N as NumCast>::from(w[2]).unwrap());
+
+                (covered, w / area)
+            }
+            None => {
+                let sample = Point3::new(util::cast::<N>(x) + <N as NumCast>::from(dx).unwrap() / _16,
+                                         util::cast::<N>(y) + <N as NumCast>::from(dy).unwrap() / _16,
+                                         _0);
+                let w = match state.fill_rule {
+                    FillRule::Inclusive => raster::barycentric_coords(v0, v1, v2, &sample),
+                    FillRule::TopLeft => raster::barycentric_coords_watertight(v0, v1, v2, &sample),
+                };
+                let covered = raster::is_covered(state.fill_rule, &w, primitive.area, &primitive.top_left);
+
+                (covered, w / primitive.area)
+            }
+        }
+    };
+
+    let mut passed = [false; 8];
+    for y in bbox.y_min..(bbox.y_max + 1) {
+        for x in bbox.x_min..(bbox.x_max + 1) {
+            let mut any_passed = false;
+            for (sample, &(dx, dy)) in positions.iter().enumerate() {
+                let (covered, w) = weigh(x, y, dx, dy);
+                passed[sample] = false;
+                if covered {
+                    let depth = D::from_depth(interpolate_depth(primitive, &w));
+                    let column = x * count + sample;
+                    if stencil_buffer.test_and_update(y, column, || z_buffer.test_and_update(depth, y, x, sample)) {
+                        passed[sample] = true;
+                        any_passed = true;
+                    }
+                }
+            }
+
+            if any_passed {
+                // Shade at the pixel center, even when the center itself lies
+                // outside of the primitive.
+                let (_, w) = weigh(x, y, 0, 0);
+                let depth = interpolate_depth(primitive, &w);
+                let fragment = fragment(primitive, x, y, w, depth);
+                let color = shader(&fragment).rgba();
+                for (sample, pixel) in frame_buffer.pixel_mut(y, x).iter_mut().enumerate() {
+                    if passed[sample] {
+                        write_pixel(state.blend, pixel, &color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+///
+/// Returns whether the pixel in column `x` and row `y` lies in the half open ranges
+/// of pixels of a window.
+///
+#[inline]
+fn in_window(window: &Option<BoundingBox<usize>>, x: usize, y: usize) -> bool {
+    match *window {
+        Some(ref window) => (window.x_min <= x) && (x < window.x_max) && (window.y_min <= y) && (y < window.y_max),
+        None => false,
+    }
+}
+
+///
+/// Interpolate the depth of a point in a primitive from its normalized barycentric
+/// coordinates `w`.
+///
+#[inline]
+fn interpolate_depth<N>(primitive: &Primitive<N>, w: &Point3<N>) -> N where N: Float + RealField {
+    w[0] * primitive.depth[0] + w[1] * primitive.depth[1] + w[2] * primitive.depth[2]
+}
+
+///
+/// Map a z coordinate in raster space onto the depth range of the viewport.
+///
+#[inline]
+fn window_depth<N>(z: N, depth_range: (N, N)) -> N where N: Float + RealField {
+    let _1 = N::one();
+    let _2 = _1 + _1;
+
+    // Raster z runs from 1 at the near plane to -1 at the far plane. Map
+    // it onto [0, 1] so that smaller depth values are closer to the eye.
+    // Reversed projections swap the planes, which reverses the depths too.
+    let (near, far) = depth_range;
+    near + (far - near) * ((_1 - z) / _2)
+}
+
+///
+/// Assemble the fragment handed to the shader for the pixel `(x, y)`.
+///
+#[inline]
+fn fragment<N>(primitive: &Primitive<N>, x: usize, y: usize, w: Point3<N>, depth: N) -> Fragment<N>
+    where N: Float + RealField
+{
+    let triangle = &primitive.triangle;
+
+    // Interpolate the barycentric coordinates of the original
+    // face with perspective correction.
+    let q0 = w[0] / triangle[0].position.w;
+    let q1 = w[1] / triangle[1].position.w;
+    let q2 = w[2] / triangle[2].position.w;
+    let q = q0 + q1 + q2;
+    let b0 = &triangle[0].bary;
+    let b1 = &triangle[1].bary;
+    let b2 = &triangle[2].bary;
+    let b = Point3::new((q0 * b0[0] + q1 * b1[0] + q2 * b2[0]) / q,
+                        (q0 * b0[1] + q1 * b1[1] + q2 * b2[1]) / q,
+                        (q0 * b0[2] + q1 * b1[2] + q2 * b2[2]) / q);
+
+    Fragment {
+        x: x,
+        y: y,
+        depth: depth,
+        face: primitive.face,
+        v0: primitive.v0,
+        v1: primitive.v1,
+        v2: primitive.v2,
+        w: w,
+        b: b,
+    }
+}
+
+///
+/// Assemble the fragment handed to the shader for the pixel `(x, y)` at the parameter
+/// `t` along the segment from `a` to `b`. The segment is treated as a triangle whose
+/// last two corners coincide.
+///
+fn line_fragment<N>(face: usize,
+                    a: &ClipVertex<N>,
+                    b: &ClipVertex<N>,
+                    p0: &Point3<N>,
+                    p1: &Point3<N>,
+                    x: usize,
+                    y: usize,
+                    t: N,
+                    depth_range: (N, N)) -> Fragment<N>
+    where N: Float + RealField
+{
+    let _0 = N::zero();
+    let _1 = N::one();
+
+    let z = p0.z * (_1 - t) + p1.z * t;
+
+    // Interpolate the barycentric coordinates of the original
+    // face with perspective correction.
+    let q0 = (_1 - t) / a.position.w;
+    let q1 = t / b.position.w;
+    let q = q0 + q1;
+    let b = Point3::new((q0 * a.bary[0] + q1 * b.bary[0]) / q,
+                        (q0 * a.bary[1] + q1 * b.bary[1]) / q,
+                        (q0 * a.bary[2] + q1 * b.bary[2]) / q);
+
+    Fragment {
+        x: x,
+        y: y,
+        depth: window_depth(z, depth_range),
+        face: face,
+        v0: *p0,
+        v1: *p1,
+        v2: *p1,
+        w: Point3::new(_1 - t, t, _0),
+        b: b,
+    }
+}
+
+///
+/// Write a shaded color to a pixel, blending it with the pixel when there is a
+/// blend state.
+///
+#[inline]
+fn write_pixel<N, P>(blend: Option<BlendState>, pixel: &mut P, rgba: &[N; 4]) where N: Float + RealField, P: Pixel {
+    *pixel = match blend {
+        Some(ref blend) => {
+            // Every float converts to f64, and narrowing it saturates to infinity
+            // rather than failing.
+            let src = [rgba[0].to_f64().unwrap() as f32,
+                       rgba[1].to_f64().unwrap() as f32,
+                       rgba[2].to_f64().unwrap() as f32,
+                       rgba[3].to_f64().unwrap() as f32];
+            P::from_rgba(blend.blend(&src, &pixel.to_rgba()))
+        }
+        None => P::from_color(rgba),
+    };
+}
+
+///
+/// Mix a color into a pixel in proportion to how much of the pixel it covers.
+///
+fn mix<N, P>(dst: P, src: P, coverage: N) -> P where N: Float + RealField, P: Pixel {
+    let mut pixel = dst;
+    for (value, (&d, &s)) in pixel.channels_mut().iter_mut().zip(dst.channels().iter().zip(src.channels())) {
+        let d: N = <N as NumCast>::from(d.to_f64()).unwrap();
+        let s: N = <N as NumCast>::from(s.to_f64()).unwrap();
+        *value = P::Channel::from_f64((d + (s - d) * coverage).to_f64().unwrap());
+    }
+
+    pixel
+}
+
+///
+/// Stencil and depth test and draw one pixel of a line or point. Partially covered
+/// pixels of anti-aliased lines are blended with the frame buffer, and only write
+/// their depth when they are at least half covered.
+///
+fn plot_pixel<F, N, Or, D, P, S, C>(fragment: &F,
+                                    x: usize,
+                                    y: usize,
+                                    depth: N,
+                                    coverage: N,
+                                    z_buffer: &mut ZBuffer<D>,
+                                    stencil_buffer: &mut StencilBuffer,
+                                    frame_buffer: &mut FrameBuffer<Or, P>,
+                                    blend: Option<BlendState>,
+                                    shader: &S)
+    where N: Float + RealField,
+          Or: Origin,
+          D: DepthFormat,
+          P: Pixel,
+          S: Fn(&F) -> C,
+          C: FragmentColor<N>
+{
+    let _0 = N::zero();
+    let _1 = N::one();
+    let half = _1 / (_1 + _1);
+
+    if coverage <= _0 {
+        return;
+    }
+
+    // Only the solid part of an antialiased line updates the depth buffer.
+    let depth = D::from_depth(depth);
+    let (i, j) = (y, x);
+    let passed = stencil_buffer.test_and_update(i, j, || {
+        if coverage >= half {
+            z_buffer.test_and_update(depth, i, j)
+        } else {
+            z_buffer.test(depth, i, j)
+        }
+    });
+
+    if passed {
+        let dst = frame_buffer[i][j];
+        let mut color = dst;
+        write_pixel(blend, &mut color, &shader(fragment).rgba());
+        frame_buffer[i][j] = mix(dst, color, Float::min(coverage, _1));
+    }
+}
+
+///
+/// Stencil and depth test and draw one pixel of a line or point into multisample
+/// buffers. These primitives cover every sample of their pixels, so each sample
+/// is tested against the same depth.
+///
+fn plot_pixel_multisample<F, N, D, P, S, C>(fragment: &F,
+                                            x: usize,
+                                            y: usize,
+                                            depth: N,
+                                            coverage: N,
+                                            z_buffer: &mut MultisampleZBuffer<D>,
+                                            stencil_buffer: &mut StencilBuffer,
+                                            frame_buffer: &mut MultisampleFrameBuffer<P>,
+                                            blend: Option<BlendState>,
+                                            shader: &S)
+    where N: Float + RealField,
+          D: DepthFormat,
+          P: Pixel,
+          S: Fn(&F) -> C,
+          C: FragmentColor<N>
+{
+    let _0 = N::zero();
+    let _1 = N::one();
+    let half = _1 / (_1 + _1);
+
+    if coverage <= _0 {
+        return;
+    }
+
+    let depth = D::from_depth(depth);
+    let (i, j) = (y, x);
+    let mut color = None;
+    let count = frame_buffer.samples().count();
+    for sample in 0..count {
+        let passed = stencil_buffer.test_and_update(i, j * count + sample, || {
+            if coverage >= half {
+                z_buffer.test_and_update(depth, i, j, sample)
+            } else {
+                z_buffer.test(depth, i, j, sample)
+            }
+        });
+
+        if passed {
+            let color = *color.get_or_insert_with(|| shader(fragment).rgba());
+            let dst = frame_buffer.pixel(i, j)[sample];
+            let mut src = dst;
+            write_pixel(blend, &mut src, &color);
+            frame_buffer.pixel_mut(i, j)[sample] = mix(dst, src, Float::min(coverage, _1));
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use std::f64::consts::PI;
+use num_traits::Float;
+use color::Pixel;
+use frame_buffer::{FrameBuffer, Origin};
+
+
+///
+/// The filter that weighs the source pixels around each target pixel. Except for
+/// `Nearest`, filters are stretched when downsampling, so every source pixel
+/// contributes to the image and it does not alias.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Filter {
+    /// Take the source pixel under the center of each target pixel.
+    Nearest,
+    /// Interpolate linearly between the nearest source pixels.
+    Bilinear,
+    /// Average the source pixels that each target pixel covers.
+    Box,
+    /// A windowed sinc filter reaching three pixels out. It keeps images sharp,
+    /// and it can ring around hard edges.
+    Lanczos3,
+    /// The Mitchell-Netravali cubic filter, with B = C = 1/3. It rings less than
+    /// `Lanczos3` and is slightly softer.
+    Mitchell,
+}
+
+impl Filter {
+    /// Returns how far the filter reaches from its center, in source pixels.
+    fn support(&self) -> f64 {
+        match *self {
+            Filter::Nearest | Filter::Box => 0.5,
+            Filter::Bilinear => 1.0,
+            Filter::Mitchell => 2.0,
+            Filter::Lanczos3 => 3.0,
+        }
+    }
+
+    /// Returns the weight of a pixel `x` pixels from the center of the filter.
+    fn weight(&self, x: f64) -> f64 {
+        let t = x.abs();
+        match *self {
+            Filter::Nearest | Filter::Box => if (-0.5..0.5).contains(&x) { 1.0 } else { 0.0 },
+            Filter::Bilinear => Float::max(1.0 - t, 0.0),
+            Filter::Lanczos3 => if t < 3.0 { sinc(x) * sinc(x / 3.0) } else { 0.0 },
+            Filter::Mitchell => {
+                let (b, c) = (1.0 / 3.0, 1.0 / 3.0);
+                if t < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * t * t * t
+                     + (-18.0 + 12.0 * b + 6.0 * c) * t * t
+                     + (6.0 - 2.0 * b)) / 6.0
+                } else if t < 2.0 {
+                    ((-b - 6.0 * c) * t * t * t
+                     + (6.0 * b + 30.0 * c) * t * t
+                     + (-12.0 * b - 48.0 * c) * t
+                     + (8.0 * b + 24.0 * c)) / 6.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+#[inline]
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        Float::sin(PI * x) / (PI * x)
+    }
+}
+
+#[inline]
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        Float::powf((value + 0.055) / 1.055, 2.4)
+    }
+}
+
+#[inline]
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * Float::powf(value, 1.0 / 2.4) - 0.055
+    }
+}
+
+///
+/// The source pixels that make up one target pixel along a row or column, with
+/// their weights. The weights sum to one.
+///
+struct Contribution {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+///
+/// Returns the contributions to each of `target` pixels from `source` pixels. A
+/// pixel `j` covers the span from `j` to `j + 1`, so the edges of both images line
+/// up. Source pixels past the edges are left out, and the weights of the rest
+/// renormalized.
+///
+fn contributions(filter: Filter, source: usize, target: usize) -> Vec<Contribution> {
+    let scale = source as f64 / target as f64;
+    let stretch = Float::max(scale, 1.0);
+    let support = filter.support() * stretch;
+
+    (0..target).map(|i| {
+        let center = (i as f64 + 0.5) * scale;
+        let nearest = Ord::min(Float::floor(center) as usize, source - 1);
+        if filter == Filter::Nearest {
+            return Contribution { start: nearest, weights: vec![1.0] };
+        }
+
+        let first = Float::max(Float::floor(center - support), 0.0) as usize;
+        let last = Float::min(Float::ceil(center + support), source as f64) as usize;
+        let weights: Vec<f64> = (first..last).map(|j| {
+            filter.weight((j as f64 + 0.5 - center) / stretch)
+        }).collect();
+
+        let total: f64 = weights.iter().sum();
+        if total == 0.0 {
+            Contribution { start: nearest, weights: vec![1.0] }
+        } else {
+            Contribution { start: first, weights: weights.iter().map(|w| (w / total) as f32).collect() }
+        }
+    }).collect()
+}
+
+/// Returns the weighted sum of the pixels at `start`, `start + step` and so on.
+#[inline]
+fn convolve(pixels: &[[f32; 4]], start: usize, step: usize, weights: &[f32]) -> [f32; 4] {
+    let mut sum = [0.0; 4];
+    for (k, weight) in weights.iter().enumerate() {
+        let pixel = &pixels[start + k * step];
+        for channel in 0..4 {
+            sum[channel] += weight * pixel[channel];
+        }
+    }
+
+    sum
+}
+
+///
+/// Resample an image of linear, premultiplied pixels stored row by row, first
+/// along its rows and then along its columns.
+///
+fn resample_separable(pixels: &[[f32; 4]],
+                      shape: (usize, usize),
+                      target: (usize, usize),
+                      filter: Filter) -> Vec<[f32; 4]>
+{
+    let (width, height) = shape;
+    let (target_width, target_height) = target;
+
+    let columns = contributions(filter, width, target_width);
+    let mut resampled_rows = Vec::with_capacity(target_width * height);
+    for i in 0..height {
+        for column in columns.iter() {
+            resampled_rows.push(convolve(pixels, i * width + column.start, 1, &column.weights));
+        }
+    }
+
+    let rows = contributions(filter, height, target_height);
+    let mut resampled = Vec::with_capacity(target_width * target_height);
+    for row in rows.iter() {
+        for j in 0..target_width {
+            resampled.push(convolve(&resampled_rows, row.start * target_width + j, target_width, &row.weights));
+        }
+    }
+
+    resampled
+}
+
+///
+/// Box filter an image of linear, premultiplied pixels down by whole factors,
+/// averaging each block of `factor_x` by `factor_y` pixels. This gives the same
+/// image as `resample_separable` with a box filter, in a single pass.
+///
+fn downsample_box(pixels: &[[f32; 4]], shape: (usize, usize), factor_x: usize, factor_y: usize) -> Vec<[f32; 4]> {
+    let (width, height) = shape;
+    let (target_width, target_height) = (width / factor_x, height / factor_y);
+    let scale = 1.0 / (factor_x * factor_y) as f32;
+
+    let mut resampled = vec![[0.0; 4]; target_width * target_height];
+    for i in 0..height {
+        let target_row = &mut resampled[(i / factor_y) * target_width..((i / factor_y) + 1) * target_width];
+        for (j, pixel) in pixels[(i * width)..((i + 1) * width)].iter().enumerate() {
+            let sum = &mut target_row[j / factor_x];
+            for channel in 0..4 {
+                sum[channel] += scale * pixel[channel];
+            }
+        }
+    }
+
+    resampled
+}
+
+///
+/// Resizes frame buffers. Filtering mixes colors, and it takes place in linear
+/// light with alpha premultiplied, so edges between bright and dark pixels keep
+/// their brightness and transparent pixels do not bleed their colors into opaque
+/// ones.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Resampler {
+    pub filter: Filter,
+    /// Whether pixels hold sRGB encoded colors, which are decoded to linear light
+    /// before filtering and encoded again after. Turn this off for buffers that
+    /// already hold linear colors, such as HDR renders in floating point formats.
+    pub srgb: bool,
+}
+
+impl Resampler {
+    /// Resample sRGB encoded images with a filter.
+    pub fn new(filter: Filter) -> Resampler {
+        Resampler {
+            filter: filter,
+            srgb: true,
+        }
+    }
+
+    ///
+    /// Returns a frame buffer of the given size holding the image scaled to fit it
+    /// exactly. The image keeps its orientation. Box filtering down by whole
+    /// factors in both directions takes a faster path.
+    ///
+    pub fn resample<Or, P>(&self, frame_buffer: &FrameBuffer<Or, P>, width: usize, height: usize) -> FrameBuffer<Or, P>
+        where Or: Origin, P: Pixel
+    {
+        assert!((width > 0) && (height > 0));
+        let shape = frame_buffer.shape();
+        assert!((shape.0 > 0) && (shape.1 > 0));
+
+        let mut pixels = Vec::with_capacity(shape.0 * shape.1);
+        for i in 0..shape.1 {
+            pixels.extend(frame_buffer.row(i).iter().map(|pixel| self.decode(pixel.to_rgba())));
+        }
+
+        let resampled = if (self.filter == Filter::Box) && (shape.0 % width == 0) && (shape.1 % height == 0) {
+            downsample_box(&pixels, shape, shape.0 / width, shape.1 / height)
+        } else {
+            resample_separable(&pixels, shape, (width, height), self.filter)
+        };
+
+        let mut result = FrameBuffer::new(width, height);
+        for (i, row) in resampled.chunks(width).enumerate() {
+            for (pixel, rgba) in result.row_mut(i).iter_mut().zip(row.iter()) {
+                *pixel = P::from_rgba(self.encode(rgba));
+            }
+        }
+
+        result
+    }
+
+    #[inline]
+    fn decode(&self, rgba: [f32; 4]) -> [f32; 4] {
+        let decode = |value: f32| if self.srgb { srgb_to_linear(value) } else { value };
+        let alpha = rgba[3];
+        [alpha * decode(rgba[0]), alpha * decode(rgba[1]), alpha * decode(rgba[2]), alpha]
+    }
+
+    #[inline]
+    fn encode(&self, rgba: &[f32; 4]) -> [f32; 4] {
+        let alpha = rgba[3];
+        let encode = |value: f32| {
+            let value = if alpha > 0.0 { value / alpha } else { 0.0 };
+            if self.srgb { linear_to_srgb(value) } else { value }
+        };
+        [encode(rgba[0]), encode(rgba[1]), encode(rgba[2]), alpha]
+    }
+}
+
+/// Resize an sRGB encoded frame buffer with a filter. See `Resampler::resample`.
+pub fn resample<Or, P>(frame_buffer: &FrameBuffer<Or, P>, width: usize, height: usize, filter: Filter) -> FrameBuffer<Or, P>
+    where Or: Origin, P: Pixel
+{
+    Resampler::new(filter).resample(frame_buffer, width, height)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use color::{Rgb, Rgba8, Gray8};
+    use frame_buffer::{FrameBuffer, TopLeft, BottomLeft};
+    use super::{Filter, Resampler};
+
+
+    fn gray_ramp(width: usize, height: usize) -> FrameBuffer<TopLeft, Gray8> {
+        let mut frame_buffer = FrameBuffer::new(width, height);
+        for i in 0..height {
+            for (j, pixel) in frame_buffer.row_mut(i).iter_mut().enumerate() {
+                *pixel = Gray8::from_channels(((37 * i + 91 * j) % 256) as u8);
+            }
+        }
+
+        frame_buffer
+    }
+
+    #[test]
+    fn test_interpolating_filters_should_keep_images_of_the_same_size() {
+        let image = gray_ramp(7, 5);
+        for &filter in [Filter::Nearest, Filter::Bilinear, Filter::Box, Filter::Lanczos3].iter() {
+            assert_eq!(super::resample(&image, 7, 5, filter), image);
+        }
+    }
+
+    #[test]
+    fn test_every_filter_should_keep_flat_images_flat() {
+        let color = Rgba8::from_channels(200, 30, 90, 128);
+        let mut image = FrameBuffer::<BottomLeft, Rgba8>::new(7, 5);
+        image.initialize_with(color);
+
+        let mut expected = FrameBuffer::<BottomLeft, Rgba8>::new(3, 9);
+        expected.initialize_with(color);
+        for &filter in [Filter::Nearest, Filter::Bilinear, Filter::Box, Filter::Lanczos3, Filter::Mitchell].iter() {
+            assert_eq!(super::resample(&image, 3, 9, filter), expected);
+        }
+    }
+
+    #[test]
+    fn test_filtering_should_take_place_in_linear_light() {
+        let mut image = FrameBuffer::<TopLeft>::new(2, 1);
+        image[0][1] = Rgb::from_channels(255, 255, 255);
+
+        // Half of full brightness is 188 once encoded for sRGB.
+        let srgb = super::resample(&image, 1, 1, Filter::Box);
+        assert_eq!(srgb[0][0], Rgb::from_channels(188, 188, 188));
+
+        let linear = Resampler { srgb: false, ..Resampler::new(Filter::Box) }.resample(&image, 1, 1);
+        assert_eq!(linear[0][0], Rgb::from_channels(128, 128, 128));
+
+        // Upsampling interpolates between the pixel centers and holds the edges.
+        let upsampled = Resampler { srgb: false, ..Resampler::new(Filter::Bilinear) }.resample(&image, 4, 1);
+        let values: Vec<u8> = upsampled.row(0).iter().map(|rgb| rgb.r()).collect();
+        assert_eq!(values, vec![0, 64, 191, 255]);
+    }
+
+    #[test]
+    fn test_box_downsampling_by_whole_factors_should_match_the_separable_filter() {
+        let image = gray_ramp(12, 8);
+        let pixels: Vec<[f32; 4]> = image.as_slice().iter().map(|&pixel| {
+            Resampler::new(Filter::Box).decode(::color::Pixel::to_rgba(&pixel))
+        }).collect();
+
+        let fast = super::downsample_box(&pixels, (12, 8), 3, 2);
+        let separable = super::resample_separable(&pixels, (12, 8), (4, 4), Filter::Box);
+        assert_eq!(fast.len(), separable.len());
+        for (a, b) in fast.iter().zip(separable.iter()) {
+            for channel in 0..4 {
+                assert!((a[channel] - b[channel]).abs() < 1e-5);
+            }
+        }
+    }
+}
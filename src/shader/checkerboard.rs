@@ -1,14 +1,14 @@
 use shader::texture::TextureMap;
 use nalgebra::{Vector2, Vector3, Point3};
 use num_traits::Float;
-use alga::general::Real;
+use alga::general::RealField;
 use std::marker::PhantomData;
 
 
 ///
 /// Factory function for creating a new `CheckerboardShader`.
 ///
-pub fn shader<N: Float + Real>(n_squares: usize) -> CheckerboardShader<N> {
+pub fn shader<N: Float + RealField>(n_squares: usize) -> CheckerboardShader<N> {
     CheckerboardShader::new(n_squares)
 }
 
@@ -17,7 +17,7 @@ pub struct CheckerboardShader<N> {
     _phantom: PhantomData<N>,
 }
 
-impl<N> CheckerboardShader<N> where N: Float + Real {
+impl<N> CheckerboardShader<N> where N: Float + RealField {
     fn new(n_squares: usize) -> CheckerboardShader<N> {
         CheckerboardShader {
             n_squares: n_squares,
@@ -49,7 +49,7 @@ macro_rules! checkerboard_impl {
 
                 let m = self.n_squares as $type_name;
 
-                let p = ((((s*m % 1.0 > 0.5) as usize) ^ (t*m % 1.0 < 0.5) as usize)) as $type_name;
+                let p = (((s*m % 1.0 > 0.5) as usize) ^ (t*m % 1.0 < 0.5) as usize) as $type_name;
 
                 Vector3::new(p,p,p)
             }
@@ -65,10 +65,9 @@ specific_fn_impl!(CheckerboardShader<f32>, f32, Args<f32>);
 specific_fn_impl!(CheckerboardShader<f64>, f64, Args<f64>);
 
 
+#[cfg(test)]
 mod tests {
     use nalgebra::{Vector2, Vector3, Point3};
-    use num_traits::Float;
-    use alga::general::Real;
 
     ///
     /// Peform a checkerboard shading at a point on a triangle primitive.
@@ -90,7 +89,7 @@ mod tests {
         s *= z;
         t *= z;
 
-        let p = ((((s*m % 1.0 > 0.5) as usize) ^ (t*m % 1.0 < 0.5) as usize)) as f32;
+        let p = (((s*m % 1.0 > 0.5) as usize) ^ (t*m % 1.0 < 0.5) as usize) as f32;
 
         Vector3::new(p,p,p)
     }
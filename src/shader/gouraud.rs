@@ -1,14 +1,14 @@
 use shader::texture::TextureMap;
 use nalgebra::{Vector3, Point3};
 use num_traits::Float;
-use alga::general::Real;
+use alga::general::RealField;
 use std::marker::PhantomData;
 
 
 ///
 /// Convenience function for creating a new `GouraudShader`.
 ///
-pub fn shader<N: Float + Real>() -> GouraudShader<N> {
+pub fn shader<N: Float + RealField>() -> GouraudShader<N> {
     GouraudShader::new()
 }
 
@@ -16,7 +16,7 @@ pub struct GouraudShader<N> {
     _phantom: PhantomData<N>,
 }
 
-impl<N> GouraudShader<N> where N: Float + Real {
+impl<N> GouraudShader<N> where N: Float + RealField {
     fn new() -> GouraudShader<N> {
         GouraudShader {
             _phantom: PhantomData,
@@ -26,7 +26,7 @@ impl<N> GouraudShader<N> where N: Float + Real {
 
 type Args<N> = (Vector3<N>, Vector3<N>, Vector3<N>, Point3<N>);
 
-impl<N> TextureMap<N, Args<N>> for GouraudShader<N> where N: Float + Real {
+impl<N> TextureMap<N, Args<N>> for GouraudShader<N> where N: Float + RealField {
     /// 
     /// Compute the Gouraud shading of a triangle primitive.
     ///
@@ -42,7 +42,7 @@ fn_impl!(GouraudShader<N>, N, Args<N>);
 mod tests {
     use nalgebra::{Vector3, Point3};
     use num_traits::Float;
-    use alga::general::Real;
+    use alga::general::RealField;
 
     /// 
     /// Compute the Gouraud shading of a triangle primitive.
@@ -51,7 +51,7 @@ mod tests {
                       color1: Vector3<N>, 
                       color2: Vector3<N>, 
                       bary:   Point3<N>) -> Vector3<N>
-        where N: Float + Real
+        where N: Float + RealField
     {
         color0 * bary[0] + color1 * bary[1] + color2 * bary[2]
     }
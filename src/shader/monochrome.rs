@@ -1,22 +1,22 @@
 use shader::texture::TextureMap;
 use nalgebra::{Vector3, Point3};
 use num_traits::Float;
-use alga::general::Real;
+use alga::general::RealField;
 
 
 ///
 /// Factory function for creating a new `MonochromeShader`.
 ///
-pub fn shader<N: Float + Real>(color: Vector3<N>) -> MonochromeShader<N> {
+pub fn shader<N: Float + RealField>(color: Vector3<N>) -> MonochromeShader<N> {
     MonochromeShader::new(color)
 }
 
 
-pub struct MonochromeShader<N> where N: Float + Real {
+pub struct MonochromeShader<N> where N: Float + RealField {
     color: Vector3<N>,
 }
 
-impl<N> MonochromeShader<N> where N: Float + Real {
+impl<N> MonochromeShader<N> where N: Float + RealField {
     fn new(color: Vector3<N>) -> MonochromeShader<N> {
         MonochromeShader {
             color: color,
@@ -26,7 +26,7 @@ impl<N> MonochromeShader<N> where N: Float + Real {
 
 type Args<N> = (Point3<N>,);
 
-impl<N> TextureMap<N, Args<N>> for MonochromeShader<N> where N: Float + Real {
+impl<N> TextureMap<N, Args<N>> for MonochromeShader<N> where N: Float + RealField {
     /// 
     /// Compute the monochrome shading of a triangle primitive.
     ///
@@ -42,8 +42,6 @@ fn_impl!(MonochromeShader<N>, N, Args<N>);
 #[cfg(test)]
 mod tests {
     use nalgebra::{Vector3, Point3};
-    use num_traits::Float;
-    use alga::general::Real;
 
 
     #[test]
@@ -1,12 +1,10 @@
-use mesh::{Face, Mesh};
-use nalgebra::{Vector3, Point3};
-use num_traits::Float;
-use alga::general::Real;
+use mesh::Mesh;
 
 
+#[allow(dead_code)]
 type Cube<N> = Mesh<N>;
 /*
-pub fn create< N: Real + Float>(bottom_left_rear_corner: Vector3<N>, 
+pub fn create< N: RealField + Float>(bottom_left_rear_corner: Vector3<N>, 
             side_length: N) -> Mesh<N>
 {
 
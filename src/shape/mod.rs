@@ -1,5 +1,5 @@
 mod cube;
 mod normal;
 pub mod plane;
-mod tetrahedron;
+pub mod tetrahedron;
 pub mod triangle;
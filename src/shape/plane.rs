@@ -1,12 +1,12 @@
 use mesh::{Face, Mesh};
-use nalgebra::{Vector3, Point3};
+use nalgebra::Point3;
 use num_traits::Float;
-use alga::general::Real;
+use alga::general::RealField;
 
 
 type Plane<N> = Mesh<N>;
 
-pub fn create<N: Real + Float>(bottom_left: Point3<N>, 
+pub fn create<N: RealField + Float>(bottom_left: Point3<N>, 
                                top_left: Point3<N>, 
                                bottom_right: Point3<N>) -> Plane<N> {
     let v0 = bottom_left;
@@ -1,12 +1,12 @@
 use mesh::{Face, Mesh};
-use nalgebra::{Vector3, Point3};
+use nalgebra::Point3;
 use num_traits::Float;
-use alga::general::Real;
+use alga::general::RealField;
 
 
 type Triangle<N> = Mesh<N>;
 
-pub fn create<N: Real + Float>(v0: Point3<N>, v1: Point3<N>, v2: Point3<N>) -> Triangle<N> {
+pub fn create<N: RealField + Float>(v0: Point3<N>, v1: Point3<N>, v2: Point3<N>) -> Triangle<N> {
     let mut mesh = Mesh::with_dims(3, 1);
 
     mesh.push_vertex(v0);
@@ -0,0 +1,238 @@
+use raster::CompareFunction;
+use std::ops;
+
+
+/// Return a cleared heap-allocated stencil buffer.
+pub fn stencil_buffer(width: usize, height: usize) -> Box<StencilBuffer> {
+    Box::new(StencilBuffer::new(width, height))
+}
+
+///
+/// What to do with the stencil value of a pixel after the stencil and depth tests.
+/// The clamping operations saturate at 0 and 255, while the wrapping ones roll over.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    Replace,
+    IncrementClamp,
+    DecrementClamp,
+    IncrementWrap,
+    DecrementWrap,
+    Invert,
+}
+
+impl StencilOp {
+    /// Apply the operation to a stored stencil value.
+    #[inline]
+    pub fn apply(&self, value: u8, reference: u8) -> u8 {
+        match *self {
+            StencilOp::Keep           => value,
+            StencilOp::Zero           => 0,
+            StencilOp::Replace        => reference,
+            StencilOp::IncrementClamp => value.saturating_add(1),
+            StencilOp::DecrementClamp => value.saturating_sub(1),
+            StencilOp::IncrementWrap  => value.wrapping_add(1),
+            StencilOp::DecrementWrap  => value.wrapping_sub(1),
+            StencilOp::Invert         => !value,
+        }
+    }
+}
+
+///
+/// The stencil test and the operations applied by its outcome. A fragment passes
+/// the test when `compare` holds between `reference & read_mask` and the stored
+/// value masked the same way. Fragments that fail the stencil test apply `fail` and
+/// are discarded before the depth test. The others apply `depth_fail` or `pass`
+/// depending on the depth test. Only the bits in `write_mask` are ever changed.
+///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct StencilState {
+    pub compare: CompareFunction,
+    pub reference: u8,
+    pub read_mask: u8,
+    pub write_mask: u8,
+    pub fail: StencilOp,
+    pub depth_fail: StencilOp,
+    pub pass: StencilOp,
+}
+
+impl Default for StencilState {
+    /// A stencil test that every fragment passes, and that never changes the buffer.
+    fn default() -> StencilState {
+        StencilState {
+            compare: CompareFunction::Always,
+            reference: 0,
+            read_mask: 0xFF,
+            write_mask: 0xFF,
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
+/// An 8-bit stencil buffer holding one value per pixel, with rows stored from the
+/// bottom of the image up.
+#[derive(PartialEq, Eq)]
+pub struct StencilBuffer {
+    width: usize,
+    height: usize,
+    buf: Vec<u8>,
+    state: StencilState,
+}
+
+impl StencilBuffer {
+    pub fn new(width: usize, height: usize) -> StencilBuffer {
+        StencilBuffer {
+            width: width,
+            height: height,
+            buf: vec![0; width * height],
+            state: StencilState::default(),
+        }
+    }
+
+    pub fn initialize(&mut self) {
+        self.initialize_with(0);
+    }
+
+    /// Clear every pixel to a stencil value.
+    pub fn initialize_with(&mut self, value: u8) {
+        for stencil in self.buf.iter_mut() {
+            *stencil = value;
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn state(&self) -> StencilState {
+        self.state
+    }
+
+    pub fn set_state(&mut self, state: StencilState) {
+        self.state = state;
+    }
+
+    /// Run the stencil test for the pixel in row `i` and column `j`.
+    #[inline]
+    pub fn test(&self, i: usize, j: usize) -> bool {
+        let mask = self.state.read_mask;
+        self.state.compare.compare(self.state.reference & mask, self.buf[self.width * i + j] & mask)
+    }
+
+    /// Apply a stencil operation to the pixel in row `i` and column `j`, through the
+    /// write mask.
+    #[inline]
+    pub fn apply(&mut self, op: StencilOp, i: usize, j: usize) {
+        let mask = self.state.write_mask;
+        let index = self.width * i + j;
+        let value = self.buf[index];
+        let updated = op.apply(value, self.state.reference);
+        self.buf[index] = (value & !mask) | (updated & mask);
+    }
+
+    ///
+    /// Run the stencil test for the pixel in row `i` and column `j`, then the depth
+    /// test `depth_test` when the stencil test passes, and apply the operation for
+    /// the outcome. Returns whether the fragment passed both tests.
+    ///
+    #[inline]
+    pub fn test_and_update<F>(&mut self, i: usize, j: usize, depth_test: F) -> bool
+        where F: FnOnce() -> bool
+    {
+        if !self.test(i, j) {
+            let fail = self.state.fail;
+            self.apply(fail, i, j);
+            return false;
+        }
+
+        let passed = depth_test();
+        let op = if passed { self.state.pass } else { self.state.depth_fail };
+        self.apply(op, i, j);
+
+        passed
+    }
+}
+
+impl ops::Index<usize> for StencilBuffer {
+    type Output = [u8];
+
+    #[inline]
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.buf[(self.width * index)..(self.width * (index + 1))]
+    }
+}
+
+impl ops::IndexMut<usize> for StencilBuffer {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.buf[(self.width * index)..(self.width * (index + 1))]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use raster::CompareFunction;
+    use super::{StencilOp, StencilState};
+
+    #[test]
+    fn test_stencil_ops_should_wrap_or_clamp() {
+        assert_eq!(StencilOp::Keep.apply(7, 3), 7);
+        assert_eq!(StencilOp::Zero.apply(7, 3), 0);
+        assert_eq!(StencilOp::Replace.apply(7, 3), 3);
+        assert_eq!(StencilOp::IncrementClamp.apply(255, 3), 255);
+        assert_eq!(StencilOp::DecrementClamp.apply(0, 3), 0);
+        assert_eq!(StencilOp::IncrementWrap.apply(255, 3), 0);
+        assert_eq!(StencilOp::DecrementWrap.apply(0, 3), 255);
+        assert_eq!(StencilOp::Invert.apply(0b1010_0101, 3), 0b0101_1010);
+    }
+
+    #[test]
+    fn test_stencil_buffer_should_apply_the_operation_for_each_outcome() {
+        let mut buf = super::stencil_buffer(4, 4);
+        buf.set_state(StencilState {
+            compare: CompareFunction::Equal,
+            reference: 1,
+            fail: StencilOp::Replace,
+            depth_fail: StencilOp::IncrementClamp,
+            pass: StencilOp::Invert,
+            ..StencilState::default()
+        });
+
+        // The stencil test fails and skips the depth test.
+        assert!(!buf.test_and_update(0, 0, || panic!("the depth test should be skipped")));
+        assert_eq!(buf[0][0], 1);
+
+        assert!(!buf.test_and_update(0, 0, || false));
+        assert_eq!(buf[0][0], 2);
+
+        buf[1][1] = 1;
+        assert!(buf.test_and_update(1, 1, || true));
+        assert_eq!(buf[1][1], 0xFE);
+    }
+
+    #[test]
+    fn test_stencil_masks_should_limit_the_bits_compared_and_written() {
+        let mut buf = super::stencil_buffer(2, 2);
+        buf.initialize_with(0b1111_0001);
+        buf.set_state(StencilState {
+            compare: CompareFunction::Equal,
+            reference: 0b0000_0001,
+            read_mask: 0b0000_1111,
+            write_mask: 0b0000_1111,
+            pass: StencilOp::Zero,
+            ..StencilState::default()
+        });
+
+        assert!(buf.test_and_update(0, 1, || true));
+        assert_eq!(buf[0][1], 0b1111_0000);
+        assert!(!buf.test(0, 1));
+    }
+}
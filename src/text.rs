@@ -0,0 +1,335 @@
+use std::io;
+use color::Pixel;
+use draw::{self, Rect};
+use frame_buffer::{FrameBuffer, Origin};
+use ppm::NetPBMDecoder;
+
+
+///
+/// The glyphs of the built-in font for the printable ASCII characters, from the
+/// space to the tilde. Each glyph is five columns from left to right, and bit `i`
+/// of a column is set when the pixel `i` rows from the top of the glyph is inked.
+///
+const BUILTIN_GLYPHS: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], [0x00, 0x00, 0x5F, 0x00, 0x00], [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], [0x24, 0x2A, 0x7F, 0x2A, 0x12], [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x55, 0x22, 0x50], [0x00, 0x05, 0x03, 0x00, 0x00], [0x00, 0x1C, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1C, 0x00], [0x14, 0x08, 0x3E, 0x08, 0x14], [0x08, 0x08, 0x3E, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00], [0x08, 0x08, 0x08, 0x08, 0x08], [0x00, 0x60, 0x60, 0x00, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02], [0x3E, 0x51, 0x49, 0x45, 0x3E], [0x00, 0x42, 0x7F, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46], [0x21, 0x41, 0x45, 0x4B, 0x31], [0x18, 0x14, 0x12, 0x7F, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39], [0x3C, 0x4A, 0x49, 0x49, 0x30], [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36], [0x06, 0x49, 0x49, 0x29, 0x1E], [0x00, 0x36, 0x36, 0x00, 0x00],
+    [0x00, 0x56, 0x36, 0x00, 0x00], [0x08, 0x14, 0x22, 0x41, 0x00], [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08], [0x02, 0x01, 0x51, 0x09, 0x06], [0x32, 0x49, 0x79, 0x41, 0x3E],
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], [0x7F, 0x49, 0x49, 0x49, 0x36], [0x3E, 0x41, 0x41, 0x41, 0x22],
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], [0x7F, 0x49, 0x49, 0x49, 0x41], [0x7F, 0x09, 0x09, 0x09, 0x01],
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], [0x7F, 0x08, 0x08, 0x08, 0x7F], [0x00, 0x41, 0x7F, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3F, 0x01], [0x7F, 0x08, 0x14, 0x22, 0x41], [0x7F, 0x40, 0x40, 0x40, 0x40],
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], [0x7F, 0x04, 0x08, 0x10, 0x7F], [0x3E, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x09, 0x09, 0x09, 0x06], [0x3E, 0x41, 0x51, 0x21, 0x5E], [0x7F, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31], [0x01, 0x01, 0x7F, 0x01, 0x01], [0x3F, 0x40, 0x40, 0x40, 0x3F],
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], [0x3F, 0x40, 0x38, 0x40, 0x3F], [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x07, 0x08, 0x70, 0x08, 0x07], [0x61, 0x51, 0x49, 0x45, 0x43], [0x00, 0x7F, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20], [0x00, 0x41, 0x41, 0x7F, 0x00], [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40], [0x00, 0x01, 0x02, 0x04, 0x00], [0x20, 0x54, 0x54, 0x54, 0x78],
+    [0x7F, 0x48, 0x44, 0x44, 0x38], [0x38, 0x44, 0x44, 0x44, 0x20], [0x38, 0x44, 0x44, 0x48, 0x7F],
+    [0x38, 0x54, 0x54, 0x54, 0x18], [0x08, 0x7E, 0x09, 0x01, 0x02], [0x0C, 0x52, 0x52, 0x52, 0x3E],
+    [0x7F, 0x08, 0x04, 0x04, 0x78], [0x00, 0x44, 0x7D, 0x40, 0x00], [0x20, 0x40, 0x44, 0x3D, 0x00],
+    [0x7F, 0x10, 0x28, 0x44, 0x00], [0x00, 0x41, 0x7F, 0x40, 0x00], [0x7C, 0x04, 0x18, 0x04, 0x78],
+    [0x7C, 0x08, 0x04, 0x04, 0x78], [0x38, 0x44, 0x44, 0x44, 0x38], [0x7C, 0x14, 0x14, 0x14, 0x08],
+    [0x08, 0x14, 0x14, 0x18, 0x7C], [0x7C, 0x08, 0x04, 0x04, 0x08], [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3F, 0x44, 0x40, 0x20], [0x3C, 0x40, 0x40, 0x20, 0x7C], [0x1C, 0x20, 0x40, 0x20, 0x1C],
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], [0x44, 0x28, 0x10, 0x28, 0x44], [0x0C, 0x50, 0x50, 0x50, 0x3C],
+    [0x44, 0x64, 0x54, 0x4C, 0x44], [0x00, 0x08, 0x36, 0x41, 0x00], [0x00, 0x00, 0x7F, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00], [0x08, 0x04, 0x08, 0x10, 0x08],
+];
+
+///
+/// A fixed-width bitmap font. Every glyph fills a cell of the same size, which
+/// includes the spacing between characters and between lines, and the glyphs cover
+/// a run of consecutive characters.
+///
+#[derive(Clone, PartialEq, Debug)]
+pub struct Font {
+    cell_width: usize,
+    cell_height: usize,
+    first: u32,
+    glyph_count: usize,
+    /// The pixels of each glyph in turn, from its top row down.
+    pixels: Vec<bool>,
+}
+
+impl Font {
+    ///
+    /// The built-in font for printable ASCII. Its glyphs are five pixels wide and
+    /// seven tall, in cells of six by eight pixels.
+    ///
+    pub fn builtin() -> Font {
+        let (cell_width, cell_height) = (6, 8);
+        let mut pixels = vec![false; BUILTIN_GLYPHS.len() * cell_width * cell_height];
+        for (glyph, columns) in pixels.chunks_mut(cell_width * cell_height).zip(BUILTIN_GLYPHS.iter()) {
+            for (j, column) in columns.iter().enumerate() {
+                for i in 0..7 {
+                    glyph[i * cell_width + j] = (column >> i) & 1 != 0;
+                }
+            }
+        }
+
+        Font {
+            cell_width: cell_width,
+            cell_height: cell_height,
+            first: ' ' as u32,
+            glyph_count: BUILTIN_GLYPHS.len(),
+            pixels: pixels,
+        }
+    }
+
+    ///
+    /// Load a font from a PBM glyph sheet, in which the black pixels are inked. The
+    /// sheet is a grid of cells of the given size, holding the glyphs of consecutive
+    /// characters from `first` in reading order. This fails when the sheet is not a
+    /// bit map or does not divide into whole cells.
+    ///
+    pub fn from_pbm<R: io::Read>(reader: &mut R, cell_width: usize, cell_height: usize, first: char) -> io::Result<Font> {
+        let (image, width, height) = NetPBMDecoder::new(reader).decode_bitmap()?;
+        let (width, height) = (width as usize, height as usize);
+        if (cell_width == 0) || (cell_height == 0) || (width % cell_width != 0) || (height % cell_height != 0) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      "The glyph sheet does not divide into whole cells."));
+        }
+
+        let columns = width / cell_width;
+        let glyph_count = columns * (height / cell_height);
+        let mut pixels = Vec::with_capacity(image.len());
+        for glyph in 0..glyph_count {
+            let (top, left) = ((glyph / columns) * cell_height, (glyph % columns) * cell_width);
+            for i in top..(top + cell_height) {
+                pixels.extend(image[(i * width + left)..(i * width + left + cell_width)].iter().map(|&p| p != 0));
+            }
+        }
+
+        Ok(Font {
+            cell_width: cell_width,
+            cell_height: cell_height,
+            first: first as u32,
+            glyph_count: glyph_count,
+            pixels: pixels,
+        })
+    }
+
+    /// Returns the width and height of a character cell, in pixels.
+    #[inline]
+    pub fn cell_size(&self) -> (usize, usize) {
+        (self.cell_width, self.cell_height)
+    }
+
+    /// Returns whether the font has a glyph for a character.
+    pub fn has_glyph(&self, c: char) -> bool {
+        (c as u32 >= self.first) && (((c as u32 - self.first) as usize) < self.glyph_count)
+    }
+
+    ///
+    /// Returns the pixels of the glyph for a character, from its top row down.
+    /// Characters without a glyph are drawn as `?`, or left blank when the font
+    /// has no `?` either.
+    ///
+    fn glyph(&self, c: char) -> Option<&[bool]> {
+        let c = if self.has_glyph(c) { c } else { '?' };
+        if !self.has_glyph(c) {
+            return None;
+        }
+
+        let size = self.cell_width * self.cell_height;
+        let start = (c as u32 - self.first) as usize * size;
+        Some(&self.pixels[start..(start + size)])
+    }
+
+    ///
+    /// Returns the width and height in pixels of a string drawn at a scale. Each
+    /// `\n` starts a new line, and the width is that of the longest line.
+    ///
+    pub fn text_size(&self, text: &str, scale: usize) -> (usize, usize) {
+        let columns = text.split('\n').map(|line| line.chars().count()).max().unwrap_or(0);
+        let lines = text.split('\n').count();
+
+        (columns * self.cell_width * scale, lines * self.cell_height * scale)
+    }
+}
+
+///
+/// How text is drawn. Each font pixel becomes a square of `scale` by `scale`
+/// pixels, so a scale of zero draws nothing. The cells of the characters are
+/// filled with the background first, unless it is `None`, in which case only the
+/// inked pixels are drawn.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TextStyle<P> {
+    pub foreground: P,
+    pub background: Option<P>,
+    pub scale: usize,
+}
+
+impl<P: Pixel> TextStyle<P> {
+    /// Draw text in a color over what is already there, at its natural size.
+    pub fn new(foreground: P) -> TextStyle<P> {
+        TextStyle {
+            foreground: foreground,
+            background: None,
+            scale: 1,
+        }
+    }
+}
+
+///
+/// Draw a string into a frame buffer, clipped to its edges. As in the `draw`
+/// module, `position` counts columns from the left and rows away from the origin
+/// of the frame buffer, and it places the corner of the text nearest the origin.
+/// That is the top left corner of the text for `TopLeft` buffers and the bottom
+/// left one for `BottomLeft` buffers. Text always reads upright, and each `\n`
+/// starts a new line below the last.
+///
+pub fn draw_text<Or, P>(frame_buffer: &mut FrameBuffer<Or, P>,
+                        font: &Font,
+                        position: (isize, isize),
+                        text: &str,
+                        style: &TextStyle<P>)
+    where Or: Origin, P: Pixel
+{
+    let scale = style.scale;
+    if scale == 0 {
+        return;
+    }
+
+    let (_, text_height) = font.text_size(text, scale);
+    let (cell_width, cell_height) = (font.cell_width * scale, font.cell_height * scale);
+
+    // Place a rectangle given from the top left corner of the text. Counted from
+    // the bottom of the text, its rows map to rows from the corner nearest the
+    // origin just as the rows of a frame buffer do.
+    let rect_at = |x: usize, y: usize, width: usize, height: usize| {
+        let from_bottom = text_height - y - height;
+        let from_origin = Ord::min(Or::storage_row(from_bottom, text_height),
+                                   Or::storage_row(from_bottom + height - 1, text_height));
+        Rect::new(position.0 + x as isize, position.1 + from_origin as isize, width, height)
+    };
+
+    for (line, string) in text.split('\n').enumerate() {
+        for (column, c) in string.chars().enumerate() {
+            let (left, top) = (column * cell_width, line * cell_height);
+            if let Some(background) = style.background {
+                draw::fill_rect(frame_buffer, &rect_at(left, top, cell_width, cell_height), background);
+            }
+
+            if let Some(glyph) = font.glyph(c) {
+                for (k, _) in glyph.iter().enumerate().filter(|&(_, &inked)| inked) {
+                    let (i, j) = (k / font.cell_width, k % font.cell_width);
+                    draw::fill_rect(frame_buffer,
+                                    &rect_at(left + j * scale, top + i * scale, scale, scale),
+                                    style.foreground);
+                }
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use color::Rgb;
+    use frame_buffer::{FrameBuffer, TopLeft, BottomLeft};
+    use super::{Font, TextStyle};
+
+
+    /// Draw the frame buffer as text, from the top row down, with `#` for white pixels
+    /// and `-` for gray ones.
+    fn picture<Or: ::frame_buffer::Origin>(frame_buffer: &FrameBuffer<Or>) -> Vec<String> {
+        let white = Rgb::from_channels(255, 255, 255);
+        let gray = Rgb::from_channels(128, 128, 128);
+        (0..frame_buffer.height()).rev().map(|i| {
+            frame_buffer.row(i).iter().map(|&rgb| {
+                if rgb == white { '#' } else if rgb == gray { '-' } else { '.' }
+            }).collect()
+        }).collect()
+    }
+
+    #[test]
+    fn test_text_should_read_upright_for_either_origin() {
+        let white = Rgb::from_channels(255, 255, 255);
+        let font = Font::builtin();
+        let expected = vec!["........",
+                            ".#####..",
+                            ".#......",
+                            ".####...",
+                            ".....#..",
+                            ".....#..",
+                            ".#...#..",
+                            "..###...",
+                            "........",
+                            "........"];
+
+        let mut top_left = FrameBuffer::<TopLeft>::new(8, 10);
+        super::draw_text(&mut top_left, &font, (1, 1), "5", &TextStyle::new(white));
+        assert_eq!(picture(&top_left), expected);
+
+        let mut bottom_left = FrameBuffer::<BottomLeft>::new(8, 10);
+        super::draw_text(&mut bottom_left, &font, (1, 1), "5", &TextStyle::new(white));
+        assert_eq!(picture(&bottom_left), expected);
+    }
+
+    #[test]
+    fn test_scaled_text_should_fill_its_cells_and_be_clipped() {
+        let white = Rgb::from_channels(255, 255, 255);
+        let gray = Rgb::from_channels(128, 128, 128);
+        let font = Font::builtin();
+        let style = TextStyle { background: Some(gray), scale: 2, ..TextStyle::new(white) };
+        assert_eq!(font.text_size("I\nI", 2), (12, 32));
+
+        // The glyph is cut off at the top and left, and the second line falls off
+        // the bottom.
+        let mut buf = FrameBuffer::<TopLeft>::new(8, 4);
+        super::draw_text(&mut buf, &font, (-2, -4), "H\nH", &style);
+        assert_eq!(picture(&buf), vec!["------##",
+                                       "------##",
+                                       "########",
+                                       "########"]);
+
+        let mut buf = FrameBuffer::<TopLeft>::new(4, 1);
+        super::draw_text(&mut buf, &font, (-2, -6), "-", &style);
+        assert_eq!(picture(&buf), vec!["####"]);
+
+        let mut buf = FrameBuffer::<BottomLeft>::new(4, 1);
+        super::draw_text(&mut buf, &font, (0, 0), "H", &TextStyle { scale: 0, ..style });
+        assert_eq!(picture(&buf), vec!["...."]);
+    }
+
+    #[test]
+    fn test_fonts_should_load_from_glyph_sheets() {
+        let white = Rgb::from_channels(255, 255, 255);
+        // Two three by three glyphs, side by side, for 'a' and 'b'.
+        let sheet = b"P1\n# glyphs\n6 3\n010 101\n101 010\n010 101\n";
+        let font = Font::from_pbm(&mut &sheet[..], 3, 3, 'a').unwrap();
+        assert!(font.has_glyph('a') && font.has_glyph('b'));
+        assert!(!font.has_glyph('c') && !font.has_glyph('?'));
+
+        // Characters without a glyph leave their cells blank.
+        let mut buf = FrameBuffer::<TopLeft>::new(9, 3);
+        super::draw_text(&mut buf, &font, (0, 0), "bca", &TextStyle::new(white));
+        assert_eq!(picture(&buf), vec!["#.#....#.", ".#....#.#", "#.#....#."]);
+
+        // The same sheet as a binary bit map, with each row padded to a byte.
+        let binary = [b'P', b'4', b' ', b'6', b' ', b'3', b'\n', 0b01010100, 0b10101000, 0b01010100];
+        assert_eq!(Font::from_pbm(&mut &binary[..], 3, 3, 'a').unwrap(), font);
+
+        assert!(Font::from_pbm(&mut &sheet[..], 4, 3, 'a').is_err());
+        assert!(Font::from_pbm(&mut &b"P2\n1 1\n255\n0\n"[..], 1, 1, 'a').is_err());
+
+        // Sheets too large for their data fail before anything is allocated.
+        assert!(Font::from_pbm(&mut &b"P1 4000000000 4000000000\n0"[..], 1, 1, 'a').is_err());
+        assert!(Font::from_pbm(&mut &b"P4 4000000000 4000000000\n\0"[..], 1, 1, 'a').is_err());
+
+        // An empty sheet is well formed and holds no glyphs.
+        let empty = Font::from_pbm(&mut &b"P4 0 3\n"[..], 1, 1, 'a').unwrap();
+        assert!(!empty.has_glyph('a'));
+    }
+}
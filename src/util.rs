@@ -1,22 +1,30 @@
 #![allow(dead_code)]
-use num_traits::Float;
-use alga::general::Real;
+use num_traits::{Float, NumCast};
+use alga::general::RealField;
 
 
 ///
 /// Calculate the minimum of three different items.
 ///
-pub fn min3<N: Float + Real>(x: N, y: N, z: N) -> N {
+pub fn min3<N: Float + RealField>(x: N, y: N, z: N) -> N {
     Float::min(Float::min(x, y), z)
 }
 
 ///
 /// Calculate the maximum of three different items.
 ///
-pub fn max3<N: Float + Real>(x: N, y: N, z: N) -> N {
+pub fn max3<N: Float + RealField>(x: N, y: N, z: N) -> N {
     Float::max(Float::max(x, y), z)
 }
 
+///
+/// Convert a pixel index into a raster space coordinate.
+///
+#[inline]
+pub fn cast<N: Float + RealField>(value: usize) -> N {
+    <N as NumCast>::from(value).unwrap()
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -1,5 +1,5 @@
 use nalgebra::{Vector3, Point3};
-use alga::general::Real;
+use alga::general::RealField;
 use num_traits::Float;
 
 
@@ -9,7 +9,7 @@ use num_traits::Float;
 #[inline(always)]
 pub fn perspective_correct<N>(position: Point3<N>, 
                               vertex: Vector3<N>) -> Vector3<N> 
-    where N: Float + Real
+    where N: Float + RealField
 {
     Vector3::new(vertex.x / position.z, vertex.y / position.z, vertex.z)
 }
@@ -20,7 +20,7 @@ pub fn perspective_correct<N>(position: Point3<N>,
 #[inline(always)]
 pub fn perspective_correct_inplace<N>(position: Point3<N>, 
                                       vertex: &mut Vector3<N>) 
-    where N: Float + Real
+    where N: Float + RealField
 {
     vertex.x /= position.z;
     vertex.y /= position.z;
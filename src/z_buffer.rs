@@ -1,60 +1,263 @@
-use nalgebra::core::Scalar;
-use nalgebra;
-use alga::general::Real;
+use alga::general::RealField;
+use raster::{BoundingBox, SampleCount, CompareFunction, DepthConvention};
 use std::ops;
-use num_traits::Float;
+use std::fmt;
+use num_traits::{Float, NumCast};
 
 
 /// Return an initialized heap-allocated z-buffer.
-pub fn z_buffer<N: Float + Real>(width: usize, height: usize) -> Box<ZBuffer<N>> {
+pub fn z_buffer<N: DepthFormat>(width: usize, height: usize) -> Box<ZBuffer<N>> {
     let mut z_buffer = Box::new(ZBuffer::new(width, height));
     z_buffer.initialize();
 
     z_buffer
 }
 
-/// Use a floating point ZBuffer for right now.
-/// TODO: Convert to an integer Z-Buffer.
+///
+/// The storage format of the values in a depth buffer. Depths are normalized to
+/// the viewport depth range, which lies in [0, 1]. Smaller values are closer to
+/// the eye, unless the buffer uses reversed-Z.
+///
+pub trait DepthFormat: Copy + PartialOrd + fmt::Debug {
+    /// The largest value of the format, which is the farthest value under the
+    /// standard convention. Standard buffers are cleared to it.
+    fn far() -> Self;
+
+    /// The smallest value of the format, which lies in front of every depth.
+    /// Reversed-Z buffers are cleared to it.
+    fn near() -> Self;
+
+    /// The value of a cleared buffer under a depth convention.
+    fn cleared(convention: DepthConvention) -> Self {
+        match convention {
+            DepthConvention::Standard => Self::far(),
+            DepthConvention::Reversed => Self::near(),
+        }
+    }
+
+    /// Quantize a normalized depth into this format.
+    fn from_depth<N: Float + RealField>(depth: N) -> Self;
+
+    /// Convert a stored value back into a normalized depth.
+    fn to_depth<N: Float + RealField>(&self) -> N;
+}
+
+impl DepthFormat for f32 {
+    /// Floating point buffers are cleared to infinity, so that even depths past
+    /// the far plane pass the depth test.
+    fn far() -> f32 {
+        Float::infinity()
+    }
+
+    fn near() -> f32 {
+        Float::neg_infinity()
+    }
+
+    #[inline]
+    fn from_depth<N: Float + RealField>(depth: N) -> f32 {
+        <f32 as NumCast>::from(depth).unwrap()
+    }
+
+    #[inline]
+    fn to_depth<N: Float + RealField>(&self) -> N {
+        <N as NumCast>::from(*self).unwrap()
+    }
+}
+
+impl DepthFormat for f64 {
+    fn far() -> f64 {
+        Float::infinity()
+    }
+
+    fn near() -> f64 {
+        Float::neg_infinity()
+    }
+
+    #[inline]
+    fn from_depth<N: Float + RealField>(depth: N) -> f64 {
+        <f64 as NumCast>::from(depth).unwrap()
+    }
+
+    #[inline]
+    fn to_depth<N: Float + RealField>(&self) -> N {
+        <N as NumCast>::from(*self).unwrap()
+    }
+}
+
+///
+/// Quantize a normalized depth to an unsigned integer in [0, max], rounding to the
+/// nearest value. Depths outside of [0, 1] are clamped, and NaN maps to `max`.
+///
+#[inline]
+fn quantize<N: Float + RealField>(depth: N, max: u32) -> u32 {
+    let _0 = N::zero();
+    let _1 = N::one();
+
+    if depth.is_nan() || (depth >= _1) {
+        return max;
+    }
+
+    let scaled = Float::max(depth, _0) * <N as NumCast>::from(max).unwrap();
+    Ord::min(Float::round(scaled).to_u64().unwrap_or(0), max as u64) as u32
+}
+
+#[inline]
+fn dequantize<N: Float + RealField>(value: u32, max: u32) -> N {
+    <N as NumCast>::from(value).unwrap() / <N as NumCast>::from(max).unwrap()
+}
+
+/// A 16 bit unsigned normalized depth.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Unorm16(pub u16);
+
+impl Unorm16 {
+    pub const MAX: u32 = 0xFFFF;
+}
+
+impl DepthFormat for Unorm16 {
+    fn far() -> Unorm16 {
+        Unorm16(Unorm16::MAX as u16)
+    }
+
+    fn near() -> Unorm16 {
+        Unorm16(0)
+    }
+
+    #[inline]
+    fn from_depth<N: Float + RealField>(depth: N) -> Unorm16 {
+        Unorm16(quantize(depth, Unorm16::MAX) as u16)
+    }
+
+    #[inline]
+    fn to_depth<N: Float + RealField>(&self) -> N {
+        dequantize(self.0 as u32, Unorm16::MAX)
+    }
+}
+
+/// A 24 bit unsigned normalized depth, stored in the low bits of a 32 bit word.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Unorm24(pub u32);
+
+impl Unorm24 {
+    pub const MAX: u32 = 0xFF_FFFF;
+}
+
+impl DepthFormat for Unorm24 {
+    fn far() -> Unorm24 {
+        Unorm24(Unorm24::MAX)
+    }
+
+    fn near() -> Unorm24 {
+        Unorm24(0)
+    }
+
+    #[inline]
+    fn from_depth<N: Float + RealField>(depth: N) -> Unorm24 {
+        Unorm24(quantize(depth, Unorm24::MAX))
+    }
+
+    #[inline]
+    fn to_depth<N: Float + RealField>(&self) -> N {
+        dequantize(self.0, Unorm24::MAX)
+    }
+}
+
+/// A 32 bit unsigned normalized depth.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Unorm32(pub u32);
+
+impl Unorm32 {
+    pub const MAX: u32 = 0xFFFF_FFFF;
+}
+
+impl DepthFormat for Unorm32 {
+    fn far() -> Unorm32 {
+        Unorm32(Unorm32::MAX)
+    }
+
+    fn near() -> Unorm32 {
+        Unorm32(0)
+    }
+
+    #[inline]
+    fn from_depth<N: Float + RealField>(depth: N) -> Unorm32 {
+        Unorm32(quantize(depth, Unorm32::MAX))
+    }
+
+    #[inline]
+    fn to_depth<N: Float + RealField>(&self) -> N {
+        dequantize(self.0, Unorm32::MAX)
+    }
+}
+
+///
+/// A depth buffer holding one value per pixel in the format `N`. Incoming depths
+/// are tested against the stored ones with a compare function, `Less` by default,
+/// and depths that pass are written back unless depth writes are disabled. The
+/// values are stored in one contiguous allocation, with rows `stride` values apart
+/// starting from the bottom row.
+///
 #[derive(PartialEq, Eq)]
 pub struct ZBuffer<N> {
     width: usize,
     height: usize,
-    buf: Vec<Vec<N>>,
+    stride: usize,
+    buf: Vec<N>,
+    compare: CompareFunction,
+    write: bool,
+    convention: DepthConvention,
 }
 
-impl<N> ZBuffer<N> where N: Float + Real {
+impl<N> ZBuffer<N> where N: DepthFormat {
     pub fn new(width: usize, height: usize) -> ZBuffer<N> {
-        let mut z_buffer = ZBuffer {
+        ZBuffer::with_stride(width, height, width)
+    }
+
+    /// Create a cleared depth buffer whose rows sit `stride` values apart.
+    pub fn with_stride(width: usize, height: usize, stride: usize) -> ZBuffer<N> {
+        assert!(stride >= width);
+
+        ZBuffer {
             width: width,
             height: height,
-            buf: Vec::with_capacity(height)
-        };
-
-        for _ in 0..z_buffer.height {
-            z_buffer.buf.push(Vec::with_capacity(width));
+            stride: stride,
+            buf: vec![N::far(); stride * height],
+            compare: CompareFunction::Less,
+            write: true,
+            convention: DepthConvention::Standard,
         }
+    }
 
-        let _0 = nalgebra::zero();
-
-        for i in 0..z_buffer.height {
-            for _ in 0..z_buffer.width {
-                z_buffer.buf[i].push(_0);
-            }
-        }
+    /// Create a cleared depth buffer that follows a depth convention.
+    pub fn with_convention(width: usize, height: usize, convention: DepthConvention) -> ZBuffer<N> {
+        let mut z_buffer = ZBuffer::new(width, height);
+        z_buffer.set_convention(convention);
 
         z_buffer
     }
 
     pub fn initialize(&mut self) {
-        let inf = N::infinity();
+        let far = N::cleared(self.convention);
 
-        for i in 0..self.height {
-            for j in 0..self.width {
-                self.buf[i][j] = inf;
-            }
+        for depth in self.buf.iter_mut() {
+            *depth = far;
         }
     }
 
+    pub fn convention(&self) -> DepthConvention {
+        self.convention
+    }
+
+    ///
+    /// Switch the buffer to a depth convention. This clears the buffer and resets
+    /// the compare function to the one that keeps the closest surfaces.
+    ///
+    pub fn set_convention(&mut self, convention: DepthConvention) {
+        self.convention = convention;
+        self.compare = convention.compare_function();
+        self.initialize();
+    }
+
     pub fn width(&self) -> usize {
         self.width
     }
@@ -63,59 +266,625 @@ impl<N> ZBuffer<N> where N: Float + Real {
         self.height
     }
 
-    pub fn test(&self, z: N, i: usize, j: usize) -> bool {
-        z < self[i][j]
+    /// The distance in values between the starts of consecutive rows in memory.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Returns row `i`, counted from the bottom of the image.
+    #[inline]
+    pub fn row(&self, i: usize) -> &[N] {
+        assert!(i < self.height);
+        let start = i * self.stride;
+
+        &self.buf[start..(start + self.width)]
+    }
+
+    #[inline]
+    pub fn row_mut(&mut self, i: usize) -> &mut [N] {
+        assert!(i < self.height);
+        let start = i * self.stride;
+
+        &mut self.buf[start..(start + self.width)]
+    }
+
+    ///
+    /// Returns the value in row `i` and column `j` without bounds checks.
+    ///
+    /// # Safety
+    ///
+    /// `i` must be less than the height and `j` less than the width.
+    ///
+    #[inline]
+    pub unsafe fn get_unchecked(&self, i: usize, j: usize) -> &N {
+        self.buf.get_unchecked(i * self.stride + j)
+    }
+
+    ///
+    /// Returns the value in row `i` and column `j` mutably without bounds checks.
+    ///
+    /// # Safety
+    ///
+    /// `i` must be less than the height and `j` less than the width.
+    ///
+    #[inline]
+    pub unsafe fn get_unchecked_mut(&mut self, i: usize, j: usize) -> &mut N {
+        self.buf.get_unchecked_mut(i * self.stride + j)
+    }
+
+    /// Returns every row, including any padding, starting from the bottom row.
+    pub fn as_slice(&self) -> &[N] {
+        &self.buf
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [N] {
+        &mut self.buf
     }
 
-    fn lines(&self) -> ZBufferLineIter<N> {
+    /// Iterate over the rows of the buffer, starting from the bottom.
+    pub fn lines(&self) -> ZBufferLineIter<'_, N> {
         ZBufferLineIter {
             index: 0,
-            lines: &self.buf
+            z_buffer: self,
+        }
+    }
+
+    pub fn compare_function(&self) -> CompareFunction {
+        self.compare
+    }
+
+    pub fn set_compare_function(&mut self, compare: CompareFunction) {
+        self.compare = compare;
+    }
+
+    pub fn depth_write(&self) -> bool {
+        self.write
+    }
+
+    pub fn set_depth_write(&mut self, write: bool) {
+        self.write = write;
+    }
+
+    /// Test a depth against the one stored in row `i` and column `j`.
+    #[inline]
+    pub fn test(&self, z: N, i: usize, j: usize) -> bool {
+        self.compare.compare(z, self.buf[i * self.stride + j])
+    }
+
+    ///
+    /// Test a depth against the one stored in row `i` and column `j`, and store it
+    /// if it passes and depth writes are enabled. Returns whether the test passed.
+    ///
+    #[inline]
+    pub fn test_and_update(&mut self, z: N, i: usize, j: usize) -> bool {
+        let passed = self.test(z, i, j);
+        if passed && self.write {
+            self.buf[i * self.stride + j] = z;
+        }
+
+        passed
+    }
+
+    ///
+    /// Convert the buffer to 8-bit gray levels, one byte per pixel starting from
+    /// the top row, so that the image lines up with a `FrameBuffer<TopLeft>`.
+    /// Surfaces near the eye are dark and those near the far plane are light,
+    /// whichever depth convention the buffer follows.
+    ///
+    pub fn to_gray8<M>(&self, mapping: &DepthMapping<M>) -> Vec<u8> where M: Float + RealField {
+        let max = <M as NumCast>::from(u8::MAX).unwrap();
+        self.to_gray(mapping, |level| <u8 as NumCast>::from(Float::round(level * max)).unwrap_or(u8::MAX))
+    }
+
+    /// Convert the buffer to 16-bit gray levels, as in `to_gray8`.
+    pub fn to_gray16<M>(&self, mapping: &DepthMapping<M>) -> Vec<u16> where M: Float + RealField {
+        let max = <M as NumCast>::from(u16::MAX).unwrap();
+        self.to_gray(mapping, |level| <u16 as NumCast>::from(Float::round(level * max)).unwrap_or(u16::MAX))
+    }
+
+    fn to_gray<M, T, F>(&self, mapping: &DepthMapping<M>, quantize: F) -> Vec<T>
+        where M: Float + RealField,
+              F: Fn(M) -> T
+    {
+        let mut gray = Vec::with_capacity(self.width * self.height);
+        // Rows are stored from the bottom of the image up.
+        for i in (0..self.height).rev() {
+            for value in self.row(i).iter() {
+                gray.push(quantize(mapping.gray_level(value.to_depth(), self.convention)));
+            }
         }
+
+        gray
     }
 }
 
-struct ZBufferLineIter<'a, N: 'a> {
+///
+/// How `ZBuffer::to_gray8` and `ZBuffer::to_gray16` turn depths into gray levels.
+/// Depths are mapped onto [0, 1] from the near plane to the far plane, either as
+/// stored or linearized back into distances from the eye. Gray levels outside of
+/// [0, 1] are clamped to it, and NaN levels are white.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct DepthMapping<N> {
+    /// The near and far planes the depths were projected with, used to turn them
+    /// back into distances. These are the planes passed to
+    /// `raster::perspective_projection_matrix`, and the depth range must be [0, 1].
+    pub linearize: Option<(N, N)>,
+    /// The gray level of pixels with an infinite depth, which were never drawn to.
+    pub background: N,
+}
+
+impl<N> DepthMapping<N> where N: Float + RealField {
+    /// Map the stored depths directly, with a white background.
+    pub fn normalized() -> DepthMapping<N> {
+        DepthMapping {
+            linearize: None,
+            background: N::one(),
+        }
+    }
+
+    /// Map the distances from the eye between the near and far planes, with a
+    /// white background.
+    pub fn linear(near: N, far: N) -> DepthMapping<N> {
+        DepthMapping {
+            linearize: Some((near, far)),
+            background: N::one(),
+        }
+    }
+
+    fn gray_level(&self, depth: N, convention: DepthConvention) -> N {
+        let _0 = N::zero();
+        let _1 = N::one();
+        let clamp = |level: N| if level.is_nan() { _1 } else { Float::max(_0, Float::min(level, _1)) };
+
+        if !depth.is_finite() {
+            return clamp(self.background);
+        }
+
+        let depth = match convention {
+            DepthConvention::Standard => depth,
+            DepthConvention::Reversed => _1 - depth,
+        };
+        let level = match self.linearize {
+            Some((near, far)) => {
+                // Invert the hyperbolic mapping of the perspective projection.
+                let (near, far) = (Float::abs(near), Float::abs(far));
+                let distance = (near * far) / (far - depth * (far - near));
+                (distance - near) / (far - near)
+            }
+            None => depth,
+        };
+
+        clamp(level)
+    }
+}
+
+pub struct ZBufferLineIter<'a, N: 'a> {
     index: usize,
-    lines: &'a [Vec<N>],
+    z_buffer: &'a ZBuffer<N>,
 }
 
-impl<'a, N: 'a> Iterator for ZBufferLineIter<'a, N> {
+impl<'a, N: 'a> Iterator for ZBufferLineIter<'a, N> where N: DepthFormat {
     type Item = &'a [N];
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.index += 1;
-        if self.index < self.lines.len() {
-            Some(&self.lines[self.index])
+        if self.index < self.z_buffer.height {
+            self.index += 1;
+            Some(self.z_buffer.row(self.index - 1))
         } else {
             None
         }
     }
 }
 
-impl<N> ops::Index<usize> for ZBuffer<N> where N: Scalar {
+impl<N> ops::Index<usize> for ZBuffer<N> where N: DepthFormat {
     type Output = [N];
 
+    #[inline]
     fn index(&self, index: usize) -> &Self::Output {
-        &self.buf[index]
+        self.row(index)
     } 
 }
 
-impl<'a, N> ops::Index<usize> for &'a ZBuffer<N> where N: Scalar {
+impl<N> ops::Index<usize> for &ZBuffer<N> where N: DepthFormat {
     type Output = [N];
 
+    #[inline]
     fn index(&self, index: usize) -> &Self::Output {
-        &self.buf[index]
+        self.row(index)
     } 
 }
 
-impl<N> ops::IndexMut<usize> for ZBuffer<N> where N: Scalar {
+impl<N> ops::IndexMut<usize> for ZBuffer<N> where N: DepthFormat {
+    #[inline]
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.buf[index]
+        self.row_mut(index)
     }
 }
 
 
+///
+/// A z-buffer that stores a depth value for each sample of a multisampled pixel.
+/// It is laid out the same way as a `MultisampleFrameBuffer`.
+///
+#[derive(PartialEq, Eq)]
+pub struct MultisampleZBuffer<N> {
+    width: usize,
+    height: usize,
+    samples: SampleCount,
+    buf: Vec<N>,
+    compare: CompareFunction,
+    write: bool,
+    convention: DepthConvention,
+}
+
+impl<N> MultisampleZBuffer<N> where N: DepthFormat {
+    pub fn new(width: usize, height: usize, samples: SampleCount) -> MultisampleZBuffer<N> {
+        MultisampleZBuffer {
+            width: width,
+            height: height,
+            samples: samples,
+            buf: vec![N::far(); width * height * samples.count()],
+            compare: CompareFunction::Less,
+            write: true,
+            convention: DepthConvention::Standard,
+        }
+    }
+
+    pub fn initialize(&mut self) {
+        let far = N::cleared(self.convention);
+
+        for sample in self.buf.iter_mut() {
+            *sample = far;
+        }
+    }
+
+    pub fn convention(&self) -> DepthConvention {
+        self.convention
+    }
+
+    /// Switch the buffer to a depth convention, as in `ZBuffer::set_convention`.
+    pub fn set_convention(&mut self, convention: DepthConvention) {
+        self.convention = convention;
+        self.compare = convention.compare_function();
+        self.initialize();
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn samples(&self) -> SampleCount {
+        self.samples
+    }
+
+    /// Returns the samples of the pixel in row `i` and column `j`.
+    #[inline]
+    pub fn pixel(&self, i: usize, j: usize) -> &[N] {
+        let count = self.samples.count();
+        let start = (self.width * i + j) * count;
+
+        &self.buf[start..(start + count)]
+    }
+
+    #[inline]
+    pub fn pixel_mut(&mut self, i: usize, j: usize) -> &mut [N] {
+        let count = self.samples.count();
+        let start = (self.width * i + j) * count;
+
+        &mut self.buf[start..(start + count)]
+    }
+
+    pub fn compare_function(&self) -> CompareFunction {
+        self.compare
+    }
+
+    pub fn set_compare_function(&mut self, compare: CompareFunction) {
+        self.compare = compare;
+    }
+
+    pub fn depth_write(&self) -> bool {
+        self.write
+    }
+
+    pub fn set_depth_write(&mut self, write: bool) {
+        self.write = write;
+    }
+
+    #[inline]
+    pub fn test(&self, z: N, i: usize, j: usize, sample: usize) -> bool {
+        self.compare.compare(z, self.pixel(i, j)[sample])
+    }
+
+    /// Test and conditionally store the depth of one sample, as in
+    /// `ZBuffer::test_and_update`.
+    #[inline]
+    pub fn test_and_update(&mut self, z: N, i: usize, j: usize, sample: usize) -> bool {
+        let passed = self.test(z, i, j, sample);
+        if passed && self.write {
+            self.pixel_mut(i, j)[sample] = z;
+        }
+
+        passed
+    }
+}
+
+///
+/// A hierarchical depth pyramid over a `ZBuffer`. The first level holds the farthest
+/// depth in each square tile of pixels, and every following level holds the farthest
+/// depth of 2 x 2 cells of the level below, until a single cell covers the buffer.
+/// A triangle whose nearest depth fails the depth test against the farthest depth
+/// of a cell is hidden by everything drawn in that cell, so it can be rejected
+/// there without visiting its pixels.
+///
+/// Rejection is only possible with the ordered compare functions `Less`,
+/// `LessEqual`, `Greater` and `GreaterEqual`. Under any other function nothing is
+/// reported as occluded.
+///
+pub struct DepthPyramid<N> {
+    width: usize,
+    height: usize,
+    tile_size: usize,
+    compare: CompareFunction,
+    levels: Vec<PyramidLevel<N>>,
+}
+
+struct PyramidLevel<N> {
+    width: usize,
+    height: usize,
+    buf: Vec<N>,
+}
+
+impl<N> PyramidLevel<N> where N: Copy {
+    #[inline]
+    fn get(&self, i: usize, j: usize) -> N {
+        self.buf[self.width * i + j]
+    }
+}
+
+impl<N> DepthPyramid<N> where N: DepthFormat {
+    ///
+    /// Create a pyramid for a `width` x `height` depth buffer with tiles of
+    /// `tile_size` x `tile_size` pixels. Every cell starts out at the far plane.
+    ///
+    pub fn new(width: usize, height: usize, tile_size: usize) -> DepthPyramid<N> {
+        assert!(tile_size > 0);
+
+        let mut levels = Vec::new();
+        let mut level_width = (width + tile_size - 1) / tile_size;
+        let mut level_height = (height + tile_size - 1) / tile_size;
+        loop {
+            levels.push(PyramidLevel {
+                width: level_width,
+                height: level_height,
+                buf: vec![N::far(); level_width * level_height],
+            });
+
+            if (level_width <= 1) && (level_height <= 1) {
+                break;
+            }
+            level_width = (level_width + 1) / 2;
+            level_height = (level_height + 1) / 2;
+        }
+
+        DepthPyramid {
+            width: width,
+            height: height,
+            tile_size: tile_size,
+            compare: CompareFunction::Less,
+            levels: levels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn tile_size(&self) -> usize {
+        self.tile_size
+    }
+
+    /// The number of levels, including the tile level and the single top cell.
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// The number of columns and rows of cells in a level.
+    pub fn level_shape(&self, level: usize) -> (usize, usize) {
+        (self.levels[level].width, self.levels[level].height)
+    }
+
+    /// The farthest depth in the cell at row `i` and column `j` of a level.
+    pub fn farthest(&self, level: usize, i: usize, j: usize) -> N {
+        self.levels[level].get(i, j)
+    }
+
+    ///
+    /// Rebuild every level from a depth buffer of the same size, taking its compare
+    /// function to decide which depths are farthest.
+    ///
+    pub fn build(&mut self, z_buffer: &ZBuffer<N>) {
+        assert_eq!((z_buffer.width(), z_buffer.height()), (self.width, self.height));
+
+        self.compare = z_buffer.compare_function();
+        let all = BoundingBox {
+            x_min: 0,
+            x_max: self.levels[0].width,
+            y_min: 0,
+            y_max: self.levels[0].height,
+        };
+        self.update_tiles(z_buffer, &all);
+    }
+
+    ///
+    /// Recompute the cells that cover the inclusive pixel bounds `bbox` from a depth
+    /// buffer, along with the cells above them.
+    ///
+    pub fn update(&mut self, z_buffer: &ZBuffer<N>, bbox: &BoundingBox<usize>) {
+        if (self.width == 0) || (self.height == 0) {
+            return;
+        }
+
+        let tiles = BoundingBox {
+            x_min: bbox.x_min / self.tile_size,
+            x_max: Ord::min(bbox.x_max, self.width - 1) / self.tile_size + 1,
+            y_min: bbox.y_min / self.tile_size,
+            y_max: Ord::min(bbox.y_max, self.height - 1) / self.tile_size + 1,
+        };
+        self.update_tiles(z_buffer, &tiles);
+    }
+
+    /// Recompute the half open range of tiles `tiles` and propagate them upwards.
+    fn update_tiles(&mut self, z_buffer: &ZBuffer<N>, tiles: &BoundingBox<usize>) {
+        if (self.width == 0) || (self.height == 0) {
+            return;
+        }
+
+        let far = N::far();
+        let ts = self.tile_size;
+        for ti in tiles.y_min..tiles.y_max {
+            for tj in tiles.x_min..tiles.x_max {
+                let mut farthest = None;
+                for i in (ti * ts)..Ord::min((ti + 1) * ts, self.height) {
+                    for j in (tj * ts)..Ord::min((tj + 1) * ts, self.width) {
+                        farthest = Some(self.farther(farthest, z_buffer[i][j]));
+                    }
+                }
+
+                let level = &mut self.levels[0];
+                level.buf[level.width * ti + tj] = farthest.unwrap_or(far);
+            }
+        }
+
+        let (mut x_min, mut x_max, mut y_min, mut y_max) = (tiles.x_min, tiles.x_max, tiles.y_min, tiles.y_max);
+        for level in 1..self.levels.len() {
+            x_min /= 2;
+            y_min /= 2;
+            x_max = (x_max + 1) / 2;
+            y_max = (y_max + 1) / 2;
+
+            for i in y_min..y_max {
+                for j in x_min..x_max {
+                    let mut farthest = None;
+                    {
+                        let below = &self.levels[level - 1];
+                        for ci in (2 * i)..Ord::min(2 * i + 2, below.height) {
+                            for cj in (2 * j)..Ord::min(2 * j + 2, below.width) {
+                                farthest = Some(self.farther(farthest, below.get(ci, cj)));
+                            }
+                        }
+                    }
+
+                    let cells = &mut self.levels[level];
+                    cells.buf[cells.width * i + j] = farthest.unwrap_or(far);
+                }
+            }
+        }
+    }
+
+    /// Whether larger depths lie farther away under the compare function.
+    #[inline]
+    fn larger_is_farther(&self) -> bool {
+        !matches!(self.compare, CompareFunction::Greater | CompareFunction::GreaterEqual)
+    }
+
+    #[inline]
+    fn is_farther(&self, depth: N, other: N) -> bool {
+        if self.larger_is_farther() { depth > other } else { depth < other }
+    }
+
+    #[inline]
+    fn farther(&self, farthest: Option<N>, depth: N) -> N {
+        match farthest {
+            Some(farthest) if !self.is_farther(depth, farthest) => farthest,
+            _ => depth,
+        }
+    }
+
+    /// Return the depth closest to the eye among the vertex depths of a triangle.
+    pub fn nearest(&self, depths: &[N; 3]) -> N {
+        let mut nearest = depths[0];
+        for &depth in depths[1..].iter() {
+            if self.is_farther(nearest, depth) {
+                nearest = depth;
+            }
+        }
+
+        nearest
+    }
+
+    #[inline]
+    fn rejects(&self, nearest: N, farthest: N) -> bool {
+        match self.compare {
+            CompareFunction::Less | CompareFunction::LessEqual |
+            CompareFunction::Greater | CompareFunction::GreaterEqual => {
+                !self.compare.compare(nearest, farthest)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether a surface no nearer than `nearest` is hidden everywhere in a tile.
+    pub fn is_tile_occluded(&self, i: usize, j: usize, nearest: N) -> bool {
+        self.rejects(nearest, self.levels[0].get(i, j))
+    }
+
+    ///
+    /// Whether a surface no nearer than `nearest` is hidden everywhere inside the
+    /// inclusive pixel bounds `bbox`, such as those of a clipped triangle. The test
+    /// walks down from the top of the pyramid into the cells it cannot reject.
+    ///
+    pub fn is_occluded(&self, bbox: &BoundingBox<usize>, nearest: N) -> bool {
+        let top = self.levels.len() - 1;
+        let (width, height) = self.level_shape(top);
+        for i in 0..height {
+            for j in 0..width {
+                if self.is_visible(top, i, j, bbox, nearest) {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    fn is_visible(&self, level: usize, i: usize, j: usize, bbox: &BoundingBox<usize>, nearest: N) -> bool {
+        // Skip the cells that lie outside of the bounding box.
+        let size = self.tile_size << level;
+        if (j * size > bbox.x_max) || ((j + 1) * size <= bbox.x_min) ||
+           (i * size > bbox.y_max) || ((i + 1) * size <= bbox.y_min) {
+            return false;
+        }
+
+        if self.rejects(nearest, self.levels[level].get(i, j)) {
+            return false;
+        }
+
+        if level == 0 {
+            return true;
+        }
+
+        let below = &self.levels[level - 1];
+        for ci in (2 * i)..Ord::min(2 * i + 2, below.height) {
+            for cj in (2 * j)..Ord::min(2 * j + 2, below.width) {
+                if self.is_visible(level - 1, ci, cj, bbox, nearest) {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -148,4 +917,252 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_multisample_z_buffer_should_test_each_sample_separately() {
+        use raster::SampleCount;
+
+        let mut buf = super::MultisampleZBuffer::<f32>::new(8, 8, SampleCount::X4);
+        buf.initialize();
+        buf.pixel_mut(2, 3)[1] = 0.5;
+
+        assert_eq!(buf.pixel(2, 3).len(), 4);
+        assert!(buf.test(0.75, 2, 3, 0));
+        assert!(!buf.test(0.75, 2, 3, 1));
+        assert!(buf.test(0.25, 2, 3, 1));
+        assert!(buf.test(0.75, 3, 2, 1));
+    }
+
+    #[test]
+    fn test_unorm_formats_should_quantize_normalized_depths() {
+        use super::{DepthFormat, Unorm16, Unorm24, Unorm32};
+
+        assert_eq!(Unorm16::from_depth(0.0f32), Unorm16(0));
+        assert_eq!(Unorm16::from_depth(1.0f32), Unorm16(0xFFFF));
+        assert_eq!(Unorm16::from_depth(0.5f64), Unorm16(0x8000));
+        assert_eq!(Unorm24::from_depth(0.25f64), Unorm24(0x40_0000));
+        assert_eq!(Unorm24::from_depth(1.0f64), Unorm24(0xFF_FFFF));
+        assert_eq!(Unorm32::from_depth(1.0f32), Unorm32(0xFFFF_FFFF));
+        assert_eq!(Unorm32::from_depth(0.0f64), Unorm32(0));
+
+        // Depths outside of the range are clamped.
+        assert_eq!(Unorm16::from_depth(-0.5f32), Unorm16(0));
+        assert_eq!(Unorm16::from_depth(2.0f32), Unorm16(0xFFFF));
+        assert_eq!(Unorm16::from_depth(f32::NAN), Unorm16(0xFFFF));
+    }
+
+    #[test]
+    fn test_unorm_formats_should_round_trip_within_their_precision() {
+        use super::{DepthFormat, Unorm16, Unorm24, Unorm32};
+
+        for i in 0..100 {
+            let depth = (i as f64) / 99.0;
+            let d16: f64 = Unorm16::from_depth(depth).to_depth();
+            let d24: f64 = Unorm24::from_depth(depth).to_depth();
+            let d32: f64 = Unorm32::from_depth(depth).to_depth();
+
+            assert!((d16 - depth).abs() <= 0.5 / 65535.0);
+            assert!((d24 - depth).abs() <= 0.5 / 16777215.0);
+            assert!((d32 - depth).abs() <= 0.5 / 4294967295.0);
+        }
+    }
+
+    #[test]
+    fn test_unorm_z_buffer_should_clear_to_the_far_plane() {
+        use super::Unorm24;
+
+        let buf = super::z_buffer::<Unorm24>(16, 16);
+
+        for i in 0..buf.height() {
+            for j in 0..buf.width() {
+                assert_eq!(buf[i][j], Unorm24(0xFF_FFFF));
+            }
+        }
+    }
+
+    #[test]
+    fn test_and_update_should_follow_the_compare_function_and_write_flag() {
+        use raster::CompareFunction;
+
+        let mut buf = super::z_buffer::<f32>(4, 4);
+        assert!(buf.test_and_update(0.5, 1, 2));
+        assert_eq!(buf[1][2], 0.5);
+
+        // Equal depths fail the default test, but pass a less-or-equal one.
+        assert!(!buf.test_and_update(0.5, 1, 2));
+        buf.set_compare_function(CompareFunction::LessEqual);
+        assert!(buf.test_and_update(0.5, 1, 2));
+
+        // Depths that pass leave the buffer alone while writes are disabled.
+        buf.set_depth_write(false);
+        assert!(buf.test_and_update(0.25, 1, 2));
+        assert_eq!(buf[1][2], 0.5);
+
+        buf.set_depth_write(true);
+        buf.set_compare_function(CompareFunction::Greater);
+        assert!(!buf.test_and_update(0.25, 1, 2));
+        assert!(buf.test_and_update(0.75, 1, 2));
+        assert_eq!(buf[1][2], 0.75);
+
+        buf.set_compare_function(CompareFunction::Never);
+        assert!(!buf.test_and_update(0.0, 1, 2));
+    }
+
+    #[test]
+    fn test_reversed_z_buffer_should_clear_to_zero_and_keep_the_largest_depths() {
+        use raster::{CompareFunction, DepthConvention};
+        use super::{ZBuffer, Unorm16};
+
+        let mut buf = ZBuffer::<Unorm16>::with_convention(4, 4, DepthConvention::Reversed);
+        assert_eq!(buf.compare_function(), CompareFunction::Greater);
+        assert_eq!(buf[0][0], Unorm16(0));
+
+        assert!(buf.test_and_update(Unorm16(100), 0, 0));
+        assert!(!buf.test_and_update(Unorm16(50), 0, 0));
+        assert_eq!(buf[0][0], Unorm16(100));
+
+        buf.initialize();
+        assert_eq!(buf[0][0], Unorm16(0));
+
+        buf.set_convention(DepthConvention::Standard);
+        assert_eq!(buf.compare_function(), CompareFunction::Less);
+        assert_eq!(buf[0][0], Unorm16(0xFFFF));
+    }
+
+    #[test]
+    fn test_depth_pyramid_should_hold_the_farthest_depth_of_each_cell() {
+        use raster::BoundingBox;
+        use super::DepthPyramid;
+
+        let mut buf = super::z_buffer::<f32>(10, 6);
+        for i in 0..6 {
+            for j in 0..10 {
+                buf.test_and_update(0.5, i, j);
+            }
+        }
+        buf.test_and_update(0.25, 5, 9);
+
+        let mut pyramid = DepthPyramid::new(10, 6, 4);
+        pyramid.build(&buf);
+
+        assert_eq!(pyramid.level_count(), 3);
+        assert_eq!(pyramid.level_shape(0), (3, 2));
+        assert_eq!(pyramid.level_shape(1), (2, 1));
+        assert_eq!(pyramid.level_shape(2), (1, 1));
+        assert_eq!(pyramid.farthest(2, 0, 0), 0.5);
+
+        // Surfaces behind everything drawn are hidden, but not those in front.
+        let bbox = BoundingBox { x_min: 2, x_max: 7, y_min: 1, y_max: 5 };
+        assert!(pyramid.is_occluded(&bbox, 0.5));
+        assert!(!pyramid.is_occluded(&bbox, 0.4));
+        assert_eq!(pyramid.nearest(&[0.75, 0.5, 0.6]), 0.5);
+
+        // Leaving a single pixel at the far plane exposes only its tile.
+        buf.initialize();
+        for i in 0..6 {
+            for j in 0..10 {
+                if (i, j) != (0, 9) {
+                    buf.test_and_update(0.5, i, j);
+                }
+            }
+        }
+        let pixel = BoundingBox { x_min: 9, x_max: 9, y_min: 0, y_max: 0 };
+        pyramid.update(&buf, &pixel);
+
+        assert!(!pyramid.is_tile_occluded(0, 2, 0.5));
+        assert!(pyramid.is_tile_occluded(0, 1, 0.5));
+        assert!(pyramid.is_occluded(&BoundingBox { x_min: 0, x_max: 7, y_min: 0, y_max: 5 }, 0.5));
+        assert!(!pyramid.is_occluded(&BoundingBox { x_min: 0, x_max: 9, y_min: 0, y_max: 0 }, 0.5));
+    }
+
+    #[test]
+    fn test_depth_pyramid_should_follow_reversed_depths() {
+        use raster::{BoundingBox, DepthConvention};
+        use super::{ZBuffer, DepthPyramid, Unorm16};
+
+        let mut buf = ZBuffer::<Unorm16>::with_convention(8, 8, DepthConvention::Reversed);
+        for i in 0..8 {
+            for j in 0..8 {
+                buf.test_and_update(Unorm16(1000 + (i * 8 + j) as u16), i, j);
+            }
+        }
+
+        let mut pyramid = DepthPyramid::new(8, 8, 4);
+        pyramid.build(&buf);
+
+        let bbox = BoundingBox { x_min: 0, x_max: 7, y_min: 0, y_max: 7 };
+        assert_eq!(pyramid.farthest(1, 0, 0), Unorm16(1000));
+        assert_eq!(pyramid.nearest(&[Unorm16(10), Unorm16(900), Unorm16(20)]), Unorm16(900));
+        assert!(pyramid.is_occluded(&bbox, Unorm16(1000)));
+        assert!(!pyramid.is_occluded(&bbox, Unorm16(1001)));
+    }
+
+    #[test]
+    fn test_depth_images_should_map_depths_onto_gray_levels() {
+        use raster::DepthConvention;
+        use super::{ZBuffer, DepthMapping, Unorm16};
+
+        let mut buf = super::z_buffer::<f32>(3, 1);
+        buf.test_and_update(0.0, 0, 0);
+        buf.test_and_update(0.5, 0, 1);
+
+        let mut mapping = DepthMapping::<f32>::normalized();
+        assert_eq!(buf.to_gray8(&mapping), vec![0, 128, 255]);
+        assert_eq!(buf.to_gray16(&mapping), vec![0, 32768, 65535]);
+
+        mapping.background = 0.0;
+        assert_eq!(buf.to_gray8(&mapping), vec![0, 128, 0]);
+
+        // Backgrounds out of range are clamped, and NaN is white.
+        mapping.background = 2.0;
+        assert_eq!(buf.to_gray8(&mapping), vec![0, 128, 255]);
+        mapping.background = -1.0;
+        assert_eq!(buf.to_gray16(&mapping), vec![0, 32768, 0]);
+        mapping.background = f32::NAN;
+        assert_eq!(buf.to_gray8(&mapping), vec![0, 128, 255]);
+
+        // So are levels that the linearization leaves undefined.
+        assert_eq!(buf.to_gray8(&DepthMapping::linear(1.0, 1.0)), vec![255, 255, 255]);
+
+        // Reversed depths come out the same way round.
+        let mut buf = ZBuffer::<Unorm16>::with_convention(2, 1, DepthConvention::Reversed);
+        buf.test_and_update(Unorm16(0xFFFF), 0, 0);
+        buf.test_and_update(Unorm16(0x4000), 0, 1);
+        assert_eq!(buf.to_gray8(&DepthMapping::<f64>::normalized()), vec![0, 191]);
+    }
+
+    #[test]
+    fn test_linearized_depth_images_should_recover_distances() {
+        use raster;
+        use nalgebra::Point4;
+        use super::DepthMapping;
+
+        let (near, far) = (-1.0, -9.0);
+        let m = raster::perspective_projection_matrix(-1.0, 1.0, 1.0, -1.0, near, far);
+
+        // A point half way between the planes is far past half way in depth.
+        let p = m * Point4::new(0.0, 0.0, -5.0, 1.0);
+        let depth = (1.0 - p.z / p.w) / 2.0;
+        let mut buf = super::z_buffer::<f64>(1, 1);
+        buf.test_and_update(depth, 0, 0);
+
+        assert!(buf.to_gray8(&DepthMapping::<f64>::normalized())[0] > 200);
+        assert_eq!(buf.to_gray8(&DepthMapping::linear(near, far)), vec![128]);
+    }
+
+    #[test]
+    fn test_padded_z_buffer_should_address_rows_by_stride() {
+        use num_traits::Float;
+        use super::ZBuffer;
+
+        let mut buf = ZBuffer::<f32>::with_stride(3, 2, 4);
+        assert!(buf.test_and_update(0.5, 1, 2));
+        buf[0][1] = 0.25;
+
+        assert_eq!(buf.stride(), 4);
+        assert_eq!(buf.row(1), &[Float::infinity(), Float::infinity(), 0.5][..]);
+        assert_eq!(buf.as_slice()[4 + 2], 0.5);
+        assert_eq!(unsafe { *buf.get_unchecked(0, 1) }, 0.25);
+        assert_eq!(buf.lines().count(), 2);
+    }
 }
@@ -0,0 +1,1062 @@
+extern crate nalgebra;
+extern crate num_traits;
+extern crate alga;
+extern crate graphics;
+#[macro_use]
+extern crate approx;
+
+use graphics::color::{Rgb, RgbCast, Rgba8, Rgb32F};
+use graphics::camera::CameraModel;
+use graphics::frame_buffer::{FrameBuffer, Origin, TopLeft};
+use graphics::mesh::{Face, Mesh};
+use graphics::raster;
+use graphics::raster::{FillRule, CullMode, FrontFace, Precision, SampleCount, LineMode, PolygonMode, PointShape};
+use graphics::raster::{CompareFunction, DepthConvention, Viewport, BoundingBox};
+use graphics::renderer::{Renderer, Fragment, PointFragment, OcclusionStats};
+use graphics::z_buffer::{DepthFormat, Unorm16, Unorm24, Unorm32};
+use graphics::stencil_buffer::{StencilState, StencilOp};
+use graphics::blend::BlendState;
+use graphics::compare::{self, Tolerance};
+use graphics::shape::{plane, tetrahedron, triangle};
+use nalgebra::{Vector3, Vector4, Point3, Matrix4};
+use num_traits::Float;
+use alga::general::RealField;
+use std::cell::Cell;
+
+
+/// Each integration test uses the same camera parameters.
+fn make_camera(width: usize, height: usize) -> Matrix4<f32> {
+    let eye  = Vector3::new(0.0, 0.0, 5.0);
+    let gaze = Vector3::new(0.0, 0.0, -1.0);
+    let top  = Vector3::new(0.0, 1.0, 0.0);
+    let camera = CameraModel::from_spec(5.0, 80.0, 80.0);
+
+    let m_cam = raster::world_to_camera_matrix::<f32>(eye, gaze, top);
+    let m_per = camera.get_matrix(-5.0, -10.0);
+    let m_vp  = raster::viewport_matrix::<f32>(width, height);
+
+    m_vp * m_per * m_cam
+}
+
+/// A perspective camera at the origin looking down the negative z axis.
+fn make_perspective_camera(width: usize, height: usize) -> Matrix4<f32> {
+    let m_per = raster::perspective_projection_matrix(-1.0, 1.0, 1.0, -1.0, -1.0, -10.0);
+    let m_vp  = raster::viewport_matrix::<f32>(width, height);
+
+    m_vp * m_per
+}
+
+/// Pack two triangles into a single mesh so they are drawn in one call.
+fn make_two_triangle_mesh(u: [Point3<f32>; 3], v: [Point3<f32>; 3]) -> Mesh<f32> {
+    let mut mesh = Mesh::with_dims(6, 2);
+    for vertex in u.iter().chain(v.iter()) {
+        mesh.push_vertex(*vertex);
+    }
+    mesh.push_face(&Face::new(0, 1, 2));
+    mesh.push_face(&Face::new(3, 4, 5));
+
+    mesh
+}
+
+/// Assert that a render matches the expected one within a tolerance, reporting how it differs.
+fn assert_render_matches<OrExpected, Or>(expected: &FrameBuffer<OrExpected>, actual: &FrameBuffer<Or>, tolerance: Tolerance)
+    where OrExpected: Origin, Or: Origin
+{
+    let comparison = compare::compare(expected, actual, &tolerance);
+    assert!(comparison.is_within_tolerance(), "The render does not match: {}", comparison);
+}
+
+fn face_color(frag: &Fragment<f32>) -> Vector3<f32> {
+    match frag.face {
+        0 => Vector3::new(1.0, 0.0, 0.0),
+        _ => Vector3::new(0.0, 1.0, 0.0),
+    }
+}
+
+#[test]
+fn test_renderer_should_draw_a_triangle_into_the_frame_buffer() {
+    let width  = 128;
+    let height = 128;
+    let m_total = make_camera(width, height);
+    let mesh = triangle::create(Point3::new(30.0, 30.0, 0.0),
+                                Point3::new(30.0, -30.0, 0.0),
+                                Point3::new(-30.0, -30.0, 0.0));
+    let green = Vector3::new(0.0, 1.0, 0.0);
+
+    let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    renderer.draw_mesh(&mesh, &m_total, &|_: &Fragment<f32>| green);
+
+    let mut covered = 0;
+    for i in 0..height {
+        for j in 0..width {
+            if renderer.frame_buffer()[i][j] == Rgb::from_channels(0, 255, 0) {
+                assert!(renderer.z_buffer()[i][j] < 1.0);
+                covered += 1;
+            } else {
+                assert_eq!(renderer.frame_buffer()[i][j], Rgb::from_channels(0, 0, 0));
+            }
+        }
+    }
+
+    // The triangle covers roughly half of the 60 x 60 square it sits in.
+    assert!(covered > 0);
+    assert!(covered < width * height);
+}
+
+#[test]
+fn test_renderer_should_not_depend_on_the_order_of_faces() {
+    let width  = 128;
+    let height = 128;
+    let m_total = make_camera(width, height);
+    let back  = [Point3::new(30.0, 30.0, -1.0),
+                 Point3::new(0.0, -30.0, -1.0),
+                 Point3::new(-30.0, 30.0, -1.0)];
+    let front = [Point3::new(20.0, 20.0, 0.0),
+                 Point3::new(20.0, -20.0, 0.0),
+                 Point3::new(-20.0, -20.0, 0.0)];
+
+    let mut back_to_front: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    back_to_front.draw_mesh(&make_two_triangle_mesh(back, front), &m_total, &face_color);
+
+    let mut front_to_back: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    front_to_back.draw_mesh(&make_two_triangle_mesh(front, back), &m_total, &|frag: &Fragment<f32>| {
+        // The face indices are swapped in this mesh, so swap the colors too.
+        match frag.face {
+            0 => Vector3::new(0.0, 1.0, 0.0),
+            _ => Vector3::new(1.0, 0.0, 0.0),
+        }
+    });
+
+    assert_eq!(back_to_front.frame_buffer(), front_to_back.frame_buffer());
+}
+
+#[test]
+fn test_renderer_should_leave_buffers_untouched_for_a_mesh_without_faces() {
+    let width  = 64;
+    let height = 64;
+    let m_total = make_camera(width, height);
+    let mut mesh = Mesh::with_dims(1, 0);
+    mesh.push_vertex(Point3::new(0.0, 0.0, 0.0));
+    let background = Rgb::from_channels(0x3B, 0x44, 0x4B);
+
+    let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    renderer.clear_with(background);
+    renderer.draw_mesh(&mesh, &m_total, &face_color);
+
+    for i in 0..height {
+        for j in 0..width {
+            assert_eq!(renderer.frame_buffer()[i][j], background);
+        }
+    }
+}
+
+#[test]
+fn test_top_left_fill_rule_should_shade_shared_edges_once() {
+    let width  = 128;
+    let height = 128;
+    let m_total = make_camera(width, height);
+    let mesh = plane::create(Point3::new(-30.0, -30.0, 0.0),
+                             Point3::new(-30.0, 30.0, 0.0),
+                             Point3::new(30.0, -30.0, 0.0));
+    let shaded = Cell::new(0);
+    let white = Vector3::new(1.0, 1.0, 1.0);
+
+    let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    renderer.set_fill_rule(FillRule::TopLeft);
+    renderer.draw_mesh(&mesh, &m_total, &|_: &Fragment<f32>| {
+        shaded.set(shaded.get() + 1);
+        white
+    });
+
+    let mut covered = 0;
+    for i in 0..height {
+        for j in 0..width {
+            if renderer.frame_buffer()[i][j] == Rgb::from_channels(255, 255, 255) {
+                covered += 1;
+            }
+        }
+    }
+
+    assert!(covered > 0);
+    assert_eq!(shaded.get(), covered);
+}
+
+#[test]
+fn test_renderer_should_clip_faces_crossing_the_near_plane() {
+    let width  = 64;
+    let height = 64;
+    let m_total = make_perspective_camera(width, height);
+    let white = Vector3::new(1.0, 1.0, 1.0);
+
+    // A floor running from in front of the camera to behind it. One corner
+    // sits in the plane of the eye, and another one behind it.
+    let meshes = [triangle::create(Point3::new(-2.0, -1.0, -3.0),
+                                   Point3::new(2.0, -1.0, -3.0),
+                                   Point3::new(0.0, -1.0, 0.0)),
+                  triangle::create(Point3::new(-2.0, -1.0, -3.0),
+                                   Point3::new(2.0, -1.0, -3.0),
+                                   Point3::new(0.0, -1.0, 2.0))];
+
+    for mesh in meshes.iter() {
+        let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+        renderer.draw_mesh(mesh, &m_total, &|_: &Fragment<f32>| white);
+
+        let mut covered = 0;
+        for i in 0..height {
+            for j in 0..width {
+                if renderer.frame_buffer()[i][j] == Rgb::from_channels(255, 255, 255) {
+                    let depth = renderer.z_buffer()[i][j];
+                    assert!((0.0..=1.0).contains(&depth));
+                    // The floor lies below the horizon, in the bottom half of the image.
+                    assert!(i < height / 2);
+                    covered += 1;
+                }
+            }
+        }
+
+        assert!(covered > 0);
+    }
+}
+
+/// Render a mesh with the perspective camera and record which faces were shaded.
+fn shaded_faces(mesh: &Mesh<f32>, cull_mode: CullMode, front_face: FrontFace) -> u32 {
+    let width  = 64;
+    let height = 64;
+    let m_total = make_perspective_camera(width, height);
+    let faces = Cell::new(0);
+
+    let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    renderer.set_cull_mode(cull_mode);
+    renderer.set_front_face(front_face);
+    renderer.draw_mesh(mesh, &m_total, &|frag: &Fragment<f32>| {
+        faces.set(faces.get() | (1 << frag.face));
+        Vector3::new(1.0, 1.0, 1.0)
+    });
+
+    faces.get()
+}
+
+#[test]
+fn test_cull_mode_should_select_faces_by_winding() {
+    // This triangle runs clockwise as seen by the camera.
+    let mesh = triangle::create(Point3::new(-1.0, -1.0, -3.0),
+                                Point3::new(0.0, 1.0, -3.0),
+                                Point3::new(1.0, -1.0, -3.0));
+
+    assert_eq!(shaded_faces(&mesh, CullMode::None, FrontFace::Clockwise), 1);
+    assert_eq!(shaded_faces(&mesh, CullMode::Back, FrontFace::Clockwise), 1);
+    assert_eq!(shaded_faces(&mesh, CullMode::Front, FrontFace::Clockwise), 0);
+    assert_eq!(shaded_faces(&mesh, CullMode::Back, FrontFace::CounterClockwise), 0);
+    assert_eq!(shaded_faces(&mesh, CullMode::Front, FrontFace::CounterClockwise), 1);
+}
+
+#[test]
+fn test_back_and_front_culling_should_split_a_closed_mesh() {
+    let mesh = tetrahedron::create(Point3::new(-1.0, -1.0, -3.0),
+                                   Point3::new(1.0, -1.0, -3.0),
+                                   Point3::new(0.0, -1.0, -5.0),
+                                   Point3::new(0.0, 1.0, -4.0));
+
+    let back_culled  = shaded_faces(&mesh, CullMode::Back, FrontFace::Clockwise);
+    let front_culled = shaded_faces(&mesh, CullMode::Front, FrontFace::Clockwise);
+
+    // Each face of a closed mesh faces either toward or away from the camera.
+    assert!(back_culled != 0);
+    assert!(front_culled != 0);
+    assert_eq!(back_culled & front_culled, 0);
+    assert_eq!(back_culled | front_culled, 0b1111);
+}
+
+#[test]
+fn test_tiled_renderer_should_match_the_single_threaded_renderer() {
+    let width  = 100;
+    let height = 90;
+    let m_total = make_perspective_camera(width, height);
+    let mesh = tetrahedron::create(Point3::new(-1.0, -1.0, -3.0),
+                                   Point3::new(1.0, -1.0, -3.0),
+                                   Point3::new(0.0, -1.0, -5.0),
+                                   Point3::new(0.0, 1.0, -4.0));
+    let shader = |frag: &Fragment<f32>| Vector3::new(frag.b[0], frag.b[1], frag.depth);
+
+    let mut expected: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    expected.set_fill_rule(FillRule::TopLeft);
+    expected.draw_mesh(&mesh, &m_total, &shader);
+
+    for &tile_size in [7, 16, 64, 256].iter() {
+        for &threads in [1, 3, 4].iter() {
+            let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+            renderer.set_fill_rule(FillRule::TopLeft);
+            renderer.draw_mesh_tiled(&mesh, &m_total, &shader, tile_size, threads);
+
+            assert_eq!(renderer.frame_buffer(), expected.frame_buffer());
+            assert!(renderer.z_buffer() == expected.z_buffer());
+        }
+    }
+}
+
+/// Render a triangle in a flat gray.
+fn render_gray_triangle(value: f32) -> Renderer<f32, TopLeft> {
+    let (width, height) = (32, 32);
+    let mesh = triangle::create(Point3::new(-1.0, -1.0, -3.0),
+                                Point3::new(1.0, -1.0, -3.0),
+                                Point3::new(0.0, 1.0, -3.0));
+    let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    renderer.draw_mesh(&mesh, &make_perspective_camera(width, height), &|_: &Fragment<f32>| {
+        Vector3::new(value, value, value)
+    });
+
+    renderer
+}
+
+#[test]
+fn test_renders_one_bit_apart_should_match_within_a_one_bit_tolerance() {
+    let expected = render_gray_triangle(0.5);
+    let actual = render_gray_triangle(0.5 + 1.0 / 255.0);
+    assert!(expected.frame_buffer() != actual.frame_buffer());
+
+    assert_render_matches(expected.frame_buffer(), actual.frame_buffer(), Tolerance::new(1.0, 0));
+}
+
+#[test]
+#[should_panic(expected = "The render does not match")]
+fn test_renders_one_bit_apart_should_not_match_exactly() {
+    let expected = render_gray_triangle(0.5);
+    let actual = render_gray_triangle(0.5 + 1.0 / 255.0);
+
+    assert_render_matches(expected.frame_buffer(), actual.frame_buffer(), Tolerance::exact());
+}
+
+/// Render the coverage of a mesh in raster space with the given precision.
+fn coverage<N>(mesh: &Mesh<N>, fill_rule: FillRule, precision: Precision) -> Vec<Vec<bool>>
+    where N: Float + RealField + DepthFormat,
+          Rgb: RgbCast<Vector3<N>, RgbValue=Rgb>
+{
+    let width  = 32;
+    let height = 32;
+    let white = Vector3::new(N::one(), N::one(), N::one());
+
+    let mut renderer: Renderer<N, TopLeft> = Renderer::new(width, height);
+    renderer.set_fill_rule(fill_rule);
+    renderer.set_precision(precision);
+    renderer.draw_mesh(mesh, &Matrix4::identity(), &|_: &Fragment<N>| white);
+
+    (0..height).map(|i| {
+        (0..width).map(|j| renderer.frame_buffer()[i][j] == Rgb::from_channels(255, 255, 255)).collect()
+    }).collect()
+}
+
+#[test]
+fn test_fixed_point_rasterization_should_match_floating_point_on_the_subpixel_grid() {
+    // Vertices on the subpixel grid snap without moving, so both paths see the
+    // same triangle.
+    let mesh: Mesh<f64> = plane::create(Point3::new(2.5, 3.25, 0.0),
+                                        Point3::new(4.0, 28.0, 0.0),
+                                        Point3::new(29.75, 1.0, 0.0));
+
+    for &fill_rule in [FillRule::Inclusive, FillRule::TopLeft].iter() {
+        assert_eq!(coverage(&mesh, fill_rule, Precision::FixedPoint),
+                   coverage(&mesh, fill_rule, Precision::Float));
+    }
+}
+
+#[test]
+fn test_fixed_point_rasterization_should_not_depend_on_the_scalar_type() {
+    let corners = [(1.0 / 3.0, 2.0 / 7.0), (5.1, 29.9), (30.3, 0.7)];
+    let mesh_f32: Mesh<f32> = plane::create(Point3::new(corners[0].0 as f32, corners[0].1 as f32, 0.0),
+                                            Point3::new(corners[1].0 as f32, corners[1].1 as f32, 0.0),
+                                            Point3::new(corners[2].0 as f32, corners[2].1 as f32, 0.0));
+    let mesh_f64: Mesh<f64> = plane::create(Point3::new(corners[0].0, corners[0].1, 0.0),
+                                            Point3::new(corners[1].0, corners[1].1, 0.0),
+                                            Point3::new(corners[2].0, corners[2].1, 0.0));
+
+    assert_eq!(coverage(&mesh_f32, FillRule::TopLeft, Precision::FixedPoint),
+               coverage(&mesh_f64, FillRule::TopLeft, Precision::FixedPoint));
+}
+
+#[test]
+fn test_multisampling_should_shade_each_pixel_once_and_blend_the_edges() {
+    let width  = 32;
+    let height = 32;
+    let mesh: Mesh<f32> = triangle::create(Point3::new(2.3, 3.1, 0.0),
+                                           Point3::new(12.7, 29.4, 0.0),
+                                           Point3::new(28.9, 5.6, 0.0));
+    let white = Vector3::new(1.0, 1.0, 1.0);
+
+    for &samples in [SampleCount::X2, SampleCount::X4, SampleCount::X8].iter() {
+        for &precision in [Precision::Float, Precision::FixedPoint].iter() {
+            let shaded = Cell::new(0);
+            let mut renderer: Renderer<f32, TopLeft> = Renderer::with_samples(width, height, samples);
+            renderer.set_fill_rule(FillRule::TopLeft);
+            renderer.set_precision(precision);
+            renderer.draw_mesh(&mesh, &Matrix4::identity(), &|_: &Fragment<f32>| {
+                shaded.set(shaded.get() + 1);
+                white
+            });
+            renderer.resolve();
+
+            let mut touched = 0;
+            let mut partial = 0;
+            for i in 0..height {
+                for j in 0..width {
+                    let rgb = renderer.frame_buffer()[i][j];
+                    assert_eq!(rgb[0], rgb[1]);
+                    if rgb != Rgb::from_channels(0, 0, 0) {
+                        touched += 1;
+                    }
+                    if (rgb[0] != 0) && (rgb[0] != 255) {
+                        partial += 1;
+                    }
+                }
+            }
+
+            assert_eq!(shaded.get(), touched);
+            assert!(partial > 0);
+        }
+    }
+}
+
+#[test]
+fn test_multisampling_should_fill_the_interior_of_a_face() {
+    let width  = 16;
+    let height = 16;
+    let mesh: Mesh<f32> = plane::create(Point3::new(-1.0, -1.0, 0.0),
+                                        Point3::new(-1.0, 17.0, 0.0),
+                                        Point3::new(17.0, -1.0, 0.0));
+    let color = Vector3::new(0.25, 0.5, 1.0);
+
+    let mut single: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    single.draw_mesh(&mesh, &Matrix4::identity(), &|_: &Fragment<f32>| color);
+
+    let mut multi: Renderer<f32, TopLeft> = Renderer::with_samples(width, height, SampleCount::X4);
+    multi.draw_mesh(&mesh, &Matrix4::identity(), &|_: &Fragment<f32>| color);
+    multi.resolve();
+
+    assert_eq!(multi.frame_buffer(), single.frame_buffer());
+    assert_eq!(multi.frame_buffer(), &multi.multisample_frame_buffer().unwrap().resolve());
+}
+
+#[test]
+fn test_wireframe_should_draw_each_edge_once_and_leave_the_interior_empty() {
+    let width  = 32;
+    let height = 32;
+    let mesh: Mesh<f32> = plane::create(Point3::new(4.0, 4.0, 0.0),
+                                        Point3::new(4.0, 27.0, 0.0),
+                                        Point3::new(27.0, 4.0, 0.0));
+    let white = Vector3::new(1.0, 1.0, 1.0);
+
+    let shaded = Cell::new(0);
+    let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    renderer.set_polygon_mode(PolygonMode::Line);
+    renderer.draw_mesh(&mesh, &Matrix4::identity(), &|_: &Fragment<f32>| {
+        shaded.set(shaded.get() + 1);
+        white
+    });
+
+    let fb = renderer.frame_buffer();
+    let lit = Rgb::from_channels(255, 255, 255);
+    for k in 4..28 {
+        // The outline of the square, and the diagonal shared by its two faces.
+        assert_eq!(fb[4][k], lit);
+        assert_eq!(fb[27][k], lit);
+        assert_eq!(fb[k][4], lit);
+        assert_eq!(fb[k][27], lit);
+        assert_eq!(fb[k][31 - k], lit);
+    }
+    assert_eq!(fb[10][10], Rgb::from_channels(0, 0, 0));
+    assert_eq!(fb[0][0], Rgb::from_channels(0, 0, 0));
+
+    // Pixels where edges meet are shaded by the first edge to reach them, since
+    // the depth test rejects the others.
+    let mut covered = 0;
+    for i in 0..height {
+        for j in 0..width {
+            if fb[i][j] == lit {
+                covered += 1;
+            }
+        }
+    }
+    assert_eq!(covered, 4 * 23 + 22);
+    assert_eq!(shaded.get(), covered);
+}
+
+#[test]
+fn test_wireframe_should_be_hidden_behind_nearer_faces() {
+    let width  = 32;
+    let height = 32;
+    let front: Mesh<f32> = plane::create(Point3::new(0.0, 0.0, 0.5),
+                                         Point3::new(0.0, 16.0, 0.5),
+                                         Point3::new(32.0, 0.0, 0.5));
+    let back: Mesh<f32> = plane::create(Point3::new(4.0, 4.0, -0.5),
+                                        Point3::new(4.0, 27.0, -0.5),
+                                        Point3::new(27.0, 4.0, -0.5));
+    let red = Vector3::new(1.0, 0.0, 0.0);
+    let white = Vector3::new(1.0, 1.0, 1.0);
+
+    for &line_mode in [LineMode::Bresenham, LineMode::XiaolinWu].iter() {
+        let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+        renderer.draw_mesh(&front, &Matrix4::identity(), &|_: &Fragment<f32>| red);
+        renderer.set_polygon_mode(PolygonMode::Line);
+        renderer.set_line_mode(line_mode);
+        renderer.draw_mesh(&back, &Matrix4::identity(), &|_: &Fragment<f32>| white);
+
+        let fb = renderer.frame_buffer();
+        for k in 5..27 {
+            // The bottom half of the wireframe lies behind the front face. Away
+            // from the corners, horizontal edges cover their pixels fully in
+            // either mode.
+            assert_eq!(fb[k / 2][4], Rgb::from_channels(255, 0, 0));
+            assert_eq!(fb[27][k], Rgb::from_channels(255, 255, 255));
+        }
+    }
+}
+
+#[test]
+fn test_points_should_draw_a_splat_for_each_vertex_of_a_point_cloud() {
+    let width  = 64;
+    let height = 64;
+    let m_total = make_camera(width, height);
+    let mut mesh = Mesh::with_dims(3, 0);
+    mesh.push_vertex(Point3::new(-20.0, 10.0, 0.0));
+    mesh.push_vertex(Point3::new(15.0, -5.0, 0.0));
+    mesh.push_vertex(Point3::new(0.0, 0.0, -1.0));
+
+    for &(shape, size) in [(PointShape::Square, 1), (PointShape::Square, 4), (PointShape::Round, 5)].iter() {
+        let shaded = Cell::new(0);
+        let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+        renderer.set_point_shape(shape);
+        renderer.set_point_size(size);
+        renderer.draw_points(&mesh, &m_total, &|frag: &PointFragment<f32>| {
+            shaded.set(shaded.get() + 1);
+            match frag.vertex {
+                0 => Vector3::new(1.0, 0.0, 0.0),
+                1 => Vector3::new(0.0, 1.0, 0.0),
+                _ => Vector3::new(0.0, 0.0, 1.0),
+            }
+        });
+
+        let mut counts = [0; 3];
+        for i in 0..height {
+            for j in 0..width {
+                match renderer.frame_buffer()[i][j] {
+                    rgb if rgb == Rgb::from_channels(255, 0, 0) => counts[0] += 1,
+                    rgb if rgb == Rgb::from_channels(0, 255, 0) => counts[1] += 1,
+                    rgb if rgb == Rgb::from_channels(0, 0, 255) => counts[2] += 1,
+                    rgb => assert_eq!(rgb, Rgb::from_channels(0, 0, 0)),
+                }
+            }
+        }
+
+        // Round splats cover most of the square around them, depending on where
+        // the point falls within its pixel.
+        for &count in counts.iter() {
+            match shape {
+                PointShape::Square => assert_eq!(count, size * size),
+                PointShape::Round => assert!((2 * count > size * size) && (count < size * size)),
+            }
+        }
+        assert_eq!(shaded.get(), counts[0] + counts[1] + counts[2]);
+    }
+}
+
+#[test]
+fn test_points_should_be_depth_tested() {
+    let width  = 32;
+    let height = 32;
+    let mut mesh = Mesh::with_dims(2, 0);
+    mesh.push_vertex(Point3::new(10.0, 10.0, 0.5));
+    mesh.push_vertex(Point3::new(11.0, 10.0, -0.5));
+
+    let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    renderer.set_point_size(3);
+    renderer.draw_points(&mesh, &Matrix4::identity(), &|frag: &PointFragment<f32>| {
+        match frag.vertex {
+            0 => Vector3::new(1.0, 0.0, 0.0),
+            _ => Vector3::new(0.0, 1.0, 0.0),
+        }
+    });
+
+    // The first point is nearer, so it hides the overlapping part of the second.
+    for x in 9..12 {
+        assert_eq!(renderer.frame_buffer()[10][x], Rgb::from_channels(255, 0, 0));
+    }
+    assert_eq!(renderer.frame_buffer()[10][12], Rgb::from_channels(0, 255, 0));
+    assert_relative_eq!(renderer.z_buffer()[10][10], 0.25);
+}
+
+/// Draw a far red square, then a green one a little in front of it.
+fn draw_close_surfaces<D>(separation: f32) -> Renderer<f32, TopLeft, D> where D: DepthFormat {
+    let width  = 16;
+    let height = 16;
+    // The far square sits at a depth of 0.4, which falls in the middle of a
+    // 16 bit quantization step.
+    let z = 0.2;
+    let far: Mesh<f32> = plane::create(Point3::new(0.0, 0.0, z),
+                                       Point3::new(0.0, 16.0, z),
+                                       Point3::new(16.0, 0.0, z));
+    let near: Mesh<f32> = plane::create(Point3::new(0.0, 0.0, z + separation),
+                                        Point3::new(0.0, 16.0, z + separation),
+                                        Point3::new(16.0, 0.0, z + separation));
+
+    let mut renderer: Renderer<f32, TopLeft, D> = Renderer::new(width, height);
+    renderer.draw_mesh(&far, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(1.0, 0.0, 0.0));
+    renderer.draw_mesh(&near, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(0.0, 1.0, 0.0));
+
+    renderer
+}
+
+#[test]
+fn test_depth_formats_should_resolve_surfaces_within_their_precision() {
+    let red   = Rgb::from_channels(255, 0, 0);
+    let green = Rgb::from_channels(0, 255, 0);
+
+    // The surfaces are 1e-3 apart in raster z, which is half that in depth.
+    assert_eq!(draw_close_surfaces::<f32>(1e-3).frame_buffer()[8][8], green);
+    assert_eq!(draw_close_surfaces::<Unorm16>(1e-3).frame_buffer()[8][8], green);
+    assert_eq!(draw_close_surfaces::<Unorm24>(1e-3).frame_buffer()[8][8], green);
+    assert_eq!(draw_close_surfaces::<Unorm32>(1e-3).frame_buffer()[8][8], green);
+
+    // A 16 bit buffer cannot tell surfaces apart that are closer than one step.
+    assert_eq!(draw_close_surfaces::<f32>(1e-5).frame_buffer()[8][8], green);
+    assert_eq!(draw_close_surfaces::<Unorm16>(1e-5).frame_buffer()[8][8], red);
+    assert_eq!(draw_close_surfaces::<Unorm24>(1e-5).frame_buffer()[8][8], green);
+}
+
+#[test]
+fn test_depths_should_be_quantized_from_the_depth_range() {
+    let width  = 16;
+    let height = 16;
+    let mesh: Mesh<f32> = plane::create(Point3::new(0.0, 0.0, 0.0),
+                                        Point3::new(0.0, 16.0, 0.0),
+                                        Point3::new(16.0, 0.0, 0.0));
+
+    let mut renderer: Renderer<f32, TopLeft, Unorm16> = Renderer::new(width, height);
+    renderer.set_depth_range(0.25, 0.75);
+    renderer.draw_mesh(&mesh, &Matrix4::identity(), &|frag: &Fragment<f32>| {
+        assert_relative_eq!(frag.depth, 0.5);
+        Vector3::new(1.0, 1.0, 1.0)
+    });
+
+    // Raster z = 0 lies half way between the near and far planes.
+    assert_eq!(renderer.z_buffer()[8][8], Unorm16(0x8000));
+}
+
+#[test]
+fn test_equal_depth_pass_should_only_shade_visible_surfaces() {
+    let width  = 16;
+    let height = 16;
+    let far: Mesh<f32> = plane::create(Point3::new(0.0, 0.0, 0.0),
+                                       Point3::new(0.0, 16.0, 0.0),
+                                       Point3::new(16.0, 0.0, 0.0));
+    let near: Mesh<f32> = plane::create(Point3::new(0.0, 0.0, 0.5),
+                                        Point3::new(0.0, 8.0, 0.5),
+                                        Point3::new(8.0, 0.0, 0.5));
+    let black = |_: &Fragment<f32>| Vector3::new(0.0, 0.0, 0.0);
+    let blue  = |_: &Fragment<f32>| Vector3::new(0.0, 0.0, 1.0);
+
+    // Lay down depth first, then shade each mesh again where it is visible.
+    let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    renderer.draw_mesh(&far, &Matrix4::identity(), &black);
+    renderer.draw_mesh(&near, &Matrix4::identity(), &black);
+
+    renderer.set_depth_compare(CompareFunction::Equal);
+    renderer.set_depth_write(false);
+    renderer.draw_mesh(&far, &Matrix4::identity(), &blue);
+
+    assert_eq!(renderer.depth_compare(), CompareFunction::Equal);
+    assert!(!renderer.depth_write());
+    assert_eq!(renderer.frame_buffer()[4][4], Rgb::from_channels(0, 0, 0));
+    assert_eq!(renderer.frame_buffer()[12][12], Rgb::from_channels(0, 0, 255));
+}
+
+#[test]
+fn test_overlays_should_not_write_depth_when_depth_writes_are_disabled() {
+    let width  = 16;
+    let height = 16;
+    let base: Mesh<f32> = plane::create(Point3::new(0.0, 0.0, 0.0),
+                                        Point3::new(0.0, 16.0, 0.0),
+                                        Point3::new(16.0, 0.0, 0.0));
+    let overlay: Mesh<f32> = plane::create(Point3::new(0.0, 0.0, 0.5),
+                                           Point3::new(0.0, 16.0, 0.5),
+                                           Point3::new(16.0, 0.0, 0.5));
+
+    let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    renderer.draw_mesh(&base, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(1.0, 0.0, 0.0));
+    renderer.set_depth_write(false);
+    renderer.draw_mesh(&overlay, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(0.0, 1.0, 0.0));
+
+    // The overlay covers the base, but leaves its depths in place.
+    assert_eq!(renderer.frame_buffer()[8][8], Rgb::from_channels(0, 255, 0));
+    assert_relative_eq!(renderer.z_buffer()[8][8], 0.5);
+}
+
+/// Draw a red square, then a green one slightly closer, far away from the camera.
+fn draw_distant_surfaces(m_per: Matrix4<f64>, convention: DepthConvention) -> Rgb {
+    let width  = 16;
+    let height = 16;
+    let m_total = raster::viewport_matrix::<f64>(width, height) * m_per;
+    let square = |z: f64| -> Mesh<f64> {
+        plane::create(Point3::new(-1e5, -1e5, z), Point3::new(-1e5, 1e5, z), Point3::new(1e5, -1e5, z))
+    };
+
+    let mut renderer: Renderer<f64, TopLeft, f32> = Renderer::new(width, height);
+    renderer.set_depth_convention(convention);
+    renderer.draw_mesh(&square(-5000.5), &m_total, &|_: &Fragment<f64>| Vector3::new(1.0, 0.0, 0.0));
+    renderer.draw_mesh(&square(-5000.0), &m_total, &|_: &Fragment<f64>| Vector3::new(0.0, 1.0, 0.0));
+
+    renderer.frame_buffer()[8][8]
+}
+
+#[test]
+fn test_reversed_z_should_resolve_distant_surfaces_in_a_float_depth_buffer() {
+    let red   = Rgb::from_channels(255, 0, 0);
+    let green = Rgb::from_channels(0, 255, 0);
+    let (near, far) = (-0.1, -1e4);
+
+    // Standard depths crowd up against 1, where a float has little precision left.
+    let standard = raster::perspective_projection_matrix(-0.1, 0.1, 0.1, -0.1, near, far);
+    assert_eq!(draw_distant_surfaces(standard, DepthConvention::Standard), red);
+
+    let reversed = raster::reversed_perspective_projection_matrix(-0.1, 0.1, 0.1, -0.1, near, far);
+    assert_eq!(draw_distant_surfaces(reversed, DepthConvention::Reversed), green);
+
+    let infinite = raster::reversed_infinite_perspective_projection_matrix(-0.1, 0.1, 0.1, -0.1, near);
+    assert_eq!(draw_distant_surfaces(infinite, DepthConvention::Reversed), green);
+}
+
+/// A grid of `n` x `n` small triangles at raster depth `z` covering a 32 x 32 frame.
+fn make_triangle_grid(n: usize, z: f32) -> Mesh<f32> {
+    let cell = 32.0 / n as f32;
+    let mut mesh = Mesh::with_dims(3 * n * n, n * n);
+    for i in 0..n {
+        for j in 0..n {
+            let (x, y) = (j as f32 * cell, i as f32 * cell);
+            let base = mesh.vertices().len();
+            mesh.push_vertex(Point3::new(x, y, z));
+            mesh.push_vertex(Point3::new(x, y + cell, z));
+            mesh.push_vertex(Point3::new(x + cell, y, z));
+            mesh.push_face(&Face::new(base, base + 1, base + 2));
+        }
+    }
+
+    mesh
+}
+
+#[test]
+fn test_occlusion_culling_should_reject_hidden_triangles_without_changing_the_image() {
+    let width  = 32;
+    let height = 32;
+    // A wall across the left half of the frame, in front of everything else.
+    let wall: Mesh<f32> = plane::create(Point3::new(-1.0, -1.0, 0.5),
+                                        Point3::new(-1.0, 33.0, 0.5),
+                                        Point3::new(15.5, -1.0, 0.5));
+    let hidden = make_triangle_grid(8, 0.0);
+    let visible = make_triangle_grid(4, 0.75);
+    let draw = |culling: Option<usize>| {
+        let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+        renderer.set_occlusion_culling(culling);
+        renderer.draw_mesh(&wall, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(1.0, 0.0, 0.0));
+        renderer.draw_mesh(&hidden, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(0.0, 1.0, 0.0));
+        renderer.draw_mesh(&visible, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(0.0, 0.0, 1.0));
+
+        renderer
+    };
+
+    let plain = draw(None);
+    let culled = draw(Some(4));
+    assert_eq!(culled.occlusion_culling(), Some(4));
+    assert!(plain.frame_buffer() == culled.frame_buffer());
+    assert!(plain.z_buffer() == culled.z_buffer());
+
+    // The 24 grid triangles in the first three columns sit entirely behind the
+    // wall. Those in the fourth column reach past its edge, so they are drawn, but
+    // skip the tiles that the wall hides.
+    let stats = culled.occlusion_stats();
+    assert_eq!(stats.triangles_tested, 2 + 64 + 16);
+    assert_eq!(stats.triangles_culled, 24);
+    assert!(stats.tiles_culled > 0);
+
+    let mut culled = culled;
+    culled.reset_occlusion_stats();
+    assert_eq!(culled.occlusion_stats(), OcclusionStats::default());
+}
+
+#[test]
+fn test_stencil_buffer_should_mask_later_draws() {
+    let width  = 16;
+    let height = 16;
+    let window: Mesh<f32> = plane::create(Point3::new(3.5, 3.5, 0.0),
+                                          Point3::new(3.5, 7.5, 0.0),
+                                          Point3::new(7.5, 3.5, 0.0));
+    let scene: Mesh<f32> = plane::create(Point3::new(-1.0, -1.0, 0.5),
+                                         Point3::new(-1.0, 17.0, 0.5),
+                                         Point3::new(17.0, -1.0, 0.5));
+
+    let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    renderer.set_stencil_state(StencilState {
+        reference: 1,
+        pass: StencilOp::Replace,
+        ..StencilState::default()
+    });
+    renderer.set_depth_write(false);
+    renderer.draw_mesh(&window, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(0.0, 0.0, 0.0));
+
+    renderer.set_stencil_state(StencilState {
+        compare: CompareFunction::Equal,
+        reference: 1,
+        ..StencilState::default()
+    });
+    renderer.set_depth_write(true);
+    renderer.draw_mesh(&scene, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(1.0, 1.0, 1.0));
+
+    // The scene only shows through the window, which covers pixels 4 through 7.
+    for y in 0..height {
+        for x in 0..width {
+            let inside = (4..=7).contains(&x) && (4..=7).contains(&y);
+            assert_eq!(renderer.stencil_buffer()[y][x], if inside { 1 } else { 0 });
+            assert_eq!(renderer.frame_buffer()[y][x][0], if inside { 255 } else { 0 });
+        }
+    }
+}
+
+#[test]
+fn test_multisampling_renderers_should_stencil_test_each_sample() {
+    let width  = 16;
+    let height = 16;
+    let window: Mesh<f32> = plane::create(Point3::new(3.5, 3.5, 0.0),
+                                          Point3::new(3.5, 7.5, 0.0),
+                                          Point3::new(7.5, 3.5, 0.0));
+    let scene: Mesh<f32> = plane::create(Point3::new(-1.0, -1.0, 0.5),
+                                         Point3::new(-1.0, 17.0, 0.5),
+                                         Point3::new(17.0, -1.0, 0.5));
+
+    let mut renderer: Renderer<f32, TopLeft> = Renderer::with_samples(width, height, SampleCount::X4);
+    renderer.set_stencil_state(StencilState {
+        reference: 1,
+        pass: StencilOp::Replace,
+        ..StencilState::default()
+    });
+    renderer.set_depth_write(false);
+    renderer.draw_mesh(&window, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(0.0, 0.0, 0.0));
+
+    renderer.set_stencil_state(StencilState {
+        compare: CompareFunction::Equal,
+        reference: 1,
+        ..StencilState::default()
+    });
+    renderer.set_depth_write(true);
+    renderer.draw_mesh(&scene, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(1.0, 1.0, 1.0));
+    renderer.resolve();
+
+    // The edges of the window lie between pixels, so every sample of a pixel
+    // agrees.
+    let stencil = renderer.multisample_stencil_buffer().unwrap();
+    assert_eq!(stencil.width(), 4 * width);
+    for y in 0..height {
+        for x in 0..width {
+            let inside = (4..=7).contains(&x) && (4..=7).contains(&y);
+            assert_eq!(&stencil[y][(4 * x)..(4 * x + 4)], &[if inside { 1 } else { 0 }; 4]);
+            assert_eq!(renderer.frame_buffer()[y][x][0], if inside { 255 } else { 0 });
+        }
+    }
+}
+
+#[test]
+fn test_stencil_depth_fail_operations_should_count_hidden_surfaces() {
+    let width  = 16;
+    let height = 16;
+    let wall: Mesh<f32> = plane::create(Point3::new(-1.0, -1.0, 0.5),
+                                        Point3::new(-1.0, 17.0, 0.5),
+                                        Point3::new(17.0, -1.0, 0.5));
+    let behind = make_triangle_grid(2, 0.0);
+    let mut counts = Vec::new();
+
+    for &tiled in [false, true].iter() {
+        let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+        renderer.set_occlusion_culling(Some(4));
+        renderer.draw_mesh(&wall, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(1.0, 0.0, 0.0));
+        renderer.set_stencil_state(StencilState {
+            depth_fail: StencilOp::IncrementWrap,
+            ..StencilState::default()
+        });
+        for _ in 0..3 {
+            if tiled {
+                renderer.draw_mesh_tiled(&wall, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(0.0, 1.0, 0.0), 8, 2);
+                renderer.draw_mesh_tiled(&behind, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(0.0, 1.0, 0.0), 8, 2);
+            } else {
+                renderer.draw_mesh(&wall, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(0.0, 1.0, 0.0));
+                renderer.draw_mesh(&behind, &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(0.0, 1.0, 0.0));
+            }
+        }
+
+        // The triangles behind the wall fail the depth test but are not culled.
+        assert_eq!(renderer.occlusion_stats().triangles_culled, 0);
+        assert_eq!(renderer.frame_buffer()[2][2], Rgb::from_channels(255, 0, 0));
+        counts.push((0..height).map(|y| renderer.stencil_buffer()[y].to_vec()).collect::<Vec<_>>());
+    }
+
+    // Redrawing the wall at its own depth fails the less than test too.
+    assert_eq!(counts[0][2][2], 6);
+    assert!(counts[0] == counts[1]);
+}
+
+#[test]
+fn test_float_frame_buffers_should_keep_high_dynamic_range_colors() {
+    let width  = 16;
+    let height = 16;
+    let mesh: Mesh<f32> = triangle::create(Point3::new(1.0, 1.0, 0.0),
+                                           Point3::new(8.0, 14.0, 0.0),
+                                           Point3::new(14.0, 1.0, 0.0));
+    let bright = Vector3::new(4.0, 0.25, 0.0);
+
+    let mut hdr: Renderer<f32, TopLeft, f32, Rgb32F> = Renderer::new(width, height);
+    hdr.draw_mesh(&mesh, &Matrix4::identity(), &|_: &Fragment<f32>| bright);
+    let mut ldr: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    ldr.draw_mesh(&mesh, &Matrix4::identity(), &|_: &Fragment<f32>| bright);
+
+    assert_eq!(hdr.frame_buffer()[4][8], Rgb32F::from_channels(4.0, 0.25, 0.0));
+    assert_eq!(ldr.frame_buffer()[4][8], Rgb::from_channels(255, 63, 0));
+
+    // Quantizing rounds where the shader output truncates.
+    let quantized = hdr.frame_buffer().convert::<Rgb>();
+    for i in 0..height {
+        for j in 0..width {
+            let expected = match ldr.frame_buffer()[i][j] {
+                rgb if rgb == Rgb::from_channels(255, 63, 0) => Rgb::from_channels(255, 64, 0),
+                rgb => rgb,
+            };
+            assert_eq!(quantized[i][j], expected);
+        }
+    }
+}
+
+#[test]
+fn test_colors_too_large_for_float_pixels_should_become_infinite() {
+    let width  = 16;
+    let height = 16;
+    let mesh: Mesh<f64> = triangle::create(Point3::new(1.0, 1.0, 0.0),
+                                           Point3::new(8.0, 14.0, 0.0),
+                                           Point3::new(14.0, 1.0, 0.0));
+    let huge = |_: &Fragment<f64>| Vector3::new(1.0e300, 0.5, 0.0);
+
+    for &blend in [None, Some(BlendState::additive())].iter() {
+        let mut renderer: Renderer<f64, TopLeft, f64, Rgb32F> = Renderer::new(width, height);
+        renderer.set_blend_state(blend);
+        renderer.draw_mesh(&mesh, &Matrix4::identity(), &huge);
+        assert_eq!(renderer.frame_buffer()[4][8], Rgb32F::from_channels(f32::INFINITY, 0.5, 0.0));
+    }
+}
+
+#[test]
+fn test_multisampling_should_blend_every_channel_of_the_pixel_format() {
+    let width  = 32;
+    let height = 32;
+    let mesh: Mesh<f32> = triangle::create(Point3::new(2.3, 3.1, 0.0),
+                                           Point3::new(12.7, 29.4, 0.0),
+                                           Point3::new(28.9, 5.6, 0.0));
+    let white = Vector3::new(1.0, 1.0, 1.0);
+
+    let mut rgba: Renderer<f32, TopLeft, f32, Rgba8> = Renderer::with_samples(width, height, SampleCount::X4);
+    rgba.draw_mesh(&mesh, &Matrix4::identity(), &|_: &Fragment<f32>| white);
+    rgba.resolve();
+    let mut rgb: Renderer<f32, TopLeft> = Renderer::with_samples(width, height, SampleCount::X4);
+    rgb.draw_mesh(&mesh, &Matrix4::identity(), &|_: &Fragment<f32>| white);
+    rgb.resolve();
+
+    // Alpha follows the coverage of each pixel over a transparent background.
+    for i in 0..height {
+        for j in 0..width {
+            let pixel = rgba.frame_buffer()[i][j];
+            assert_eq!(pixel.a(), pixel.r());
+            assert_eq!(pixel.r(), rgb.frame_buffer()[i][j].r());
+        }
+    }
+}
+
+#[test]
+fn test_blending_should_composite_transparent_passes_over_opaque_surfaces() {
+    let width  = 16;
+    let height = 16;
+    let triangle_at = |z: f32| -> Mesh<f32> {
+        triangle::create(Point3::new(1.0, 1.0, z), Point3::new(8.0, 14.0, z), Point3::new(14.0, 1.0, z))
+    };
+
+    let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    renderer.draw_mesh(&triangle_at(0.0), &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(0.0, 1.0, 0.0));
+    let depth = renderer.z_buffer()[4][8];
+
+    // A pane of glass in front that leaves the depth buffer alone.
+    renderer.set_blend_state(Some(BlendState::over()));
+    renderer.set_depth_write(false);
+    renderer.draw_mesh(&triangle_at(0.5), &Matrix4::identity(), &|_: &Fragment<f32>| {
+        Vector4::new(1.0, 0.0, 0.0, 0.25)
+    });
+    assert_eq!(renderer.frame_buffer()[4][8], Rgb::from_channels(64, 191, 0));
+    assert_eq!(renderer.z_buffer()[4][8], depth);
+    assert_eq!(renderer.frame_buffer()[0][0], Rgb::from_channels(0, 0, 0));
+
+    renderer.set_blend_state(Some(BlendState::additive()));
+    renderer.draw_mesh(&triangle_at(0.5), &Matrix4::identity(), &|_: &Fragment<f32>| Vector3::new(0.0, 0.0, 0.5));
+    assert_eq!(renderer.frame_buffer()[4][8], Rgb::from_channels(64, 191, 128));
+
+    // Premultiplied colors composite into the alpha channel of the target.
+    let mut layer: Renderer<f32, TopLeft, f32, Rgba8> = Renderer::new(width, height);
+    layer.set_blend_state(Some(BlendState::premultiplied()));
+    layer.draw_mesh(&triangle_at(0.0), &Matrix4::identity(), &|_: &Fragment<f32>| {
+        Vector4::new(0.25, 0.0, 0.0, 0.5)
+    });
+    assert_eq!(layer.frame_buffer()[4][8], Rgba8::from_channels(64, 0, 0, 128));
+    assert_eq!(layer.frame_buffer()[0][0], Rgba8::from_channels(0, 0, 0, 0));
+}
+
+#[test]
+fn test_viewports_and_scissor_rectangles_should_confine_drawing() {
+    let width  = 32;
+    let height = 16;
+    // A triangle that covers the whole view volume, and spills well outside of it.
+    let mesh: Mesh<f32> = triangle::create(Point3::new(-1.0, -1.0, 0.0),
+                                           Point3::new(-1.0, 3.0, 0.0),
+                                           Point3::new(3.0, -1.0, 0.0));
+    let red = Vector3::new(1.0, 0.0, 0.0);
+    let green = Vector3::new(0.0, 1.0, 0.0);
+    let blue = Vector3::new(0.0, 0.0, 1.0);
+
+    let mut renderer: Renderer<f32, TopLeft> = Renderer::new(width, height);
+    renderer.set_point_size(5);
+    let left = Viewport::new(0, 0, 16, 16);
+    renderer.set_viewport(left);
+    renderer.draw_mesh(&mesh, &left.matrix(), &|_: &Fragment<f32>| red);
+    renderer.set_depth_compare(CompareFunction::LessEqual);
+    renderer.draw_points(&mesh, &left.matrix(), &|_: &PointFragment<f32>| blue);
+
+    let right = Viewport { depth_range: (0.25, 0.75), ..Viewport::new(16, 0, 16, 16) };
+    renderer.set_viewport(right);
+    renderer.draw_mesh(&mesh, &right.matrix(), &|_: &Fragment<f32>| green);
+    assert_eq!(renderer.depth_range(), (0.25, 0.75));
+
+    // Only the point at the lower left corner of the left viewport lies inside of
+    // it, and the one at (31.5, -0.5) is clipped away even though it is in the frame.
+    assert_eq!(renderer.frame_buffer()[0][0], Rgb::from_channels(0, 0, 255));
+    for i in 0..height {
+        for j in 0..width {
+            let rgb = renderer.frame_buffer()[i][j];
+            if j >= 16 {
+                assert_eq!(rgb, Rgb::from_channels(0, 255, 0));
+            } else if (i > 2) || (j > 2) {
+                assert_eq!(rgb, Rgb::from_channels(255, 0, 0));
+            }
+        }
+    }
+    assert_eq!(renderer.z_buffer()[8][8], 0.5);
+    assert_eq!(renderer.z_buffer()[8][24], 0.5);
+
+    // The scissor rectangle cuts the viewport down further.
+    renderer.clear();
+    renderer.set_scissor(Some(BoundingBox { x_min: 20, x_max: 40, y_min: 4, y_max: 6 }));
+    renderer.draw_mesh_tiled(&mesh, &right.matrix(), &|_: &Fragment<f32>| blue, 8, 2);
+    for i in 0..height {
+        for j in 0..width {
+            let inside = (j >= 20) && (4..6).contains(&i);
+            assert_eq!(renderer.frame_buffer()[i][j] == Rgb::from_channels(0, 0, 255), inside);
+        }
+    }
+}
@@ -6,7 +6,8 @@ use graphics::camera::CameraModel;
 use graphics::frame_buffer;
 use graphics::frame_buffer::{FrameBuffer, TopLeft};
 use graphics::z_buffer;
-use graphics::z_buffer::ZBuffer;
+use graphics::z_buffer::{ZBuffer, DepthMapping};
+use graphics::ppm::{NetPBM, NetPBMEncoder};
 use graphics::raster;
 use graphics::shader::monochrome;
 use graphics::shader::monochrome::MonochromeShader;
@@ -26,11 +27,6 @@ impl MockGraphicsPipelineWithZBuffer {
         }
     }
 
-    fn reinitialize(&mut self) {
-        self.z_buffer.initialize();
-        self.frame_buffer.initialize();
-    }
-
     fn run_with(&mut self, shader: &MonochromeShader<f32>, m_wtor: Matrix4<f32>, v0: Point3<f32>, v1: Point3<f32>, v2: Point3<f32>) {
         let height = self.frame_buffer.height();
         let width  = self.frame_buffer.width();
@@ -77,11 +73,7 @@ impl MockGraphicsPipelineWithoutZBuffer {
         }
     }
 
-    fn reinitialize(&mut self) {
-        self.frame_buffer.initialize();
-    }
-
-    fn run_with(&mut self, shader: &MonochromeShader<f32>, m_wtor: Matrix4<f32>, v0: Point3<f32>, v1: Point3<f32>, v2: Point3<f32>) {
+    fn run_with(&mut self, shader: &MonochromeShader<f32>, _m_wtor: Matrix4<f32>, v0: Point3<f32>, v1: Point3<f32>, v2: Point3<f32>) {
         let height = self.frame_buffer.height();
         let width  = self.frame_buffer.width();
 
@@ -453,3 +445,29 @@ fn test_z_buffer_should_give_same_results_as_rendering_objects_back_to_front() {
 
     assert_eq!(pipeline_z.frame_buffer, pipeline_no_z.frame_buffer);
 }
+
+#[test]
+fn test_depth_images_should_encode_as_gray_maps() {
+    let mut z_buffer = z_buffer::z_buffer::<f32>(2, 2);
+    z_buffer.test_and_update(0.0, 0, 0);
+    z_buffer.test_and_update(0.5, 0, 1);
+    z_buffer.test_and_update(1.0, 1, 0);
+    let mapping = DepthMapping::<f32>::normalized();
+
+    let mut ascii = Vec::new();
+    NetPBMEncoder::new(NetPBM::GrayMapAscii, &mut ascii)
+        .encode(&z_buffer.to_gray8(&mapping), 2, 2)
+        .unwrap();
+    assert_eq!(String::from_utf8(ascii).unwrap(), "P2\n2 2\n255\n255 255 \n0 128 \n");
+
+    let mut binary = Vec::new();
+    NetPBMEncoder::new(NetPBM::GrayMapBinary, &mut binary)
+        .encode16(&z_buffer.to_gray16(&mapping), 2, 2)
+        .unwrap();
+    let mut expected = b"P5\n2 2\n65535\n".to_vec();
+    expected.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x80, 0x00]);
+    assert_eq!(binary, expected);
+
+    let mut pix_map = Vec::new();
+    assert!(NetPBMEncoder::new(NetPBM::PixMapBinary, &mut pix_map).encode16(&[0; 4], 2, 2).is_err());
+}